@@ -0,0 +1,66 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+use linear_regression::{
+  application_error::GenericResult, html_dataframe::html_dataframe, sample_options::SampleOptions,
+};
+use linear_regression::dataframe_cache::FileFingerprint;
+use linear_regression::dataset_loader::DatasetFormat;
+use linear_regression::partials::create_html_notebook;
+use linear_regression::provenance::{DatasetProvenance, Provenance};
+use linear_regression::pumpkins::{pumpkins_schema, validate_pumpkins, PUMPKINS_DATASET};
+use maud::{html, PreEscaped};
+
+use crate::lessons::state::AppState;
+
+/// Gets the data-quality page of the US pumpkins dataset: its declared schema and the values
+/// of the file that break it.
+///
+pub async fn get_data_quality(State(state): State<AppState>) -> GenericResult<impl IntoResponse> {
+  let mut article_elements: Vec<PreEscaped<String>> = Vec::new();
+
+  let dataset = state.dataset(PUMPKINS_DATASET)?;
+  let fingerprint = FileFingerprint::read(&dataset.path)?;
+
+  article_elements.push(html! {
+    h2 { "1. Declared schema" }
+    p {
+      "The file " code { (dataset.path) } " is read as text and every value is checked against the "
+      "schema below before it is cast, so a malformed row anywhere in the file is reported here."
+    }
+    ( html_dataframe(&pumpkins_schema().describe()?, None)? )
+  });
+
+  if dataset.format != DatasetFormat::Csv {
+    article_elements.push(html! {
+      h2 { "2. Validation report" }
+      p { "The dataset is a " (dataset.format) " file, which carries its own types: only CSV files are validated." }
+    });
+    let (_, dataset_provenance) = state.load_with_provenance(PUMPKINS_DATASET)?;
+    let provenance = Provenance::new().dataset(dataset_provenance);
+    return Ok((StatusCode::OK, create_html_notebook("Data Quality: US Pumpkins", article_elements, &provenance)?).into_response());
+  }
+
+  let validated = validate_pumpkins(&dataset.path)?;
+
+  article_elements.push(html! {
+    h2 { "2. Validation report" }
+    p { "Loaded rows: " (validated.df.height()) ", columns: " (validated.df.width()) "." }
+    @if validated.is_valid() {
+      p { "Every value follows the schema." }
+    } @else {
+      p {
+        (validated.report.height()) " violations were found. Values of the wrong type, not allowed or out "
+        "of range are loaded as null."
+      }
+      h3 { "Violations per column" }
+      ( html_dataframe(&validated.summary()?, None)? )
+      h3 { "First violations" }
+      ( html_dataframe(&validated.report, Some( SampleOptions::builder().sample_size(100).build() ) )? )
+    }
+  });
+
+  let provenance = Provenance::new()
+    .dataset(DatasetProvenance::new(&dataset, &fingerprint, &validated.df))
+    .steps(&["pumpkins_schema().load"]);
+
+  Ok((StatusCode::OK, create_html_notebook("Data Quality: US Pumpkins", article_elements, &provenance)?).into_response())
+}
@@ -0,0 +1,124 @@
+use axum::extract::multipart::MultipartError;
+use axum::extract::{Multipart, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Redirect, Response};
+use linear_regression::application_error::GenericResult;
+use linear_regression::partials::create_html_page;
+use maud::{html, PreEscaped};
+
+use crate::lessons::state::AppState;
+
+/// Maximum size of an uploaded dataset.
+pub const UPLOAD_LIMIT_BYTES: usize = 100 * 1024 * 1024;
+
+/// Gets the page listing the registered datasets, with a form to upload a new one.
+///
+pub async fn get_datasets(State(state): State<AppState>) -> GenericResult<impl IntoResponse> {
+  let datasets = state.datasets()?;
+  let cache = state.cache.stats()?;
+
+  let mut page_elements: Vec<PreEscaped<String>> = Vec::new();
+
+  page_elements.push(html!({
+    article {
+      h1 { "Datasets" }
+      table .dataframe-table {
+        thead {
+          tr { th { "Name" } th { "Format" } th { "Source" } th { "Path" } th { "Profile" } }
+        }
+        tbody {
+          @for dataset in &datasets {
+            tr {
+              td { (dataset.name) }
+              td { (dataset.format) }
+              td { (dataset.source) }
+              td { code { (dataset.path) } }
+              td { a href={ "/profile/" (dataset.name) } { "Profile" } }
+            }
+          }
+        }
+      }
+
+      p {
+        "Cached dataframes: " (cache.entries) ", hits: " (cache.hits) ", misses: " (cache.misses)
+        ". A dataframe is rebuilt when the content of its file changes."
+      }
+
+      h2 { "Upload a dataset" }
+      p { "CSV files need a header. The file is read before it is registered, so a malformed file is rejected." }
+      form method="post" action="/datasets" enctype="multipart/form-data" {
+        p {
+          label for="name" { "Name " }
+          input type="text" id="name" name="name" required pattern="[a-z0-9_-]{1,64}" placeholder="market-2017";
+        }
+        p {
+          label for="file" { "File (.csv or .parquet) " }
+          input type="file" id="file" name="file" required accept=".csv,.parquet";
+        }
+        p { input type="submit" value="Upload"; }
+      }
+    }
+  }));
+
+  Ok((StatusCode::OK, create_html_page("Datasets", page_elements)?).into_response())
+}
+
+/// Registers a dataset uploaded as a multipart form with a `name` field and a `file` field,
+/// then redirects to the listing page. An invalid upload or form is a 400, a form over
+/// `[UPLOAD_LIMIT_BYTES]` a 413, an existing name a 409, and a failure to save a valid upload a
+/// 500, see `[UploadError]`.
+///
+pub async fn post_dataset_upload(
+  State(state): State<AppState>,
+  multipart: Multipart,
+) -> GenericResult<Response> {
+  let (name, file) = match read_upload_form(multipart).await {
+    Ok(form) => form,
+    Err(error) => return Ok(multipart_error(error)),
+  };
+
+  let (Some(name), Some((file_name, content))) = (name, file) else {
+    return Ok(bad_request("The upload needs a \"name\" field and a \"file\" field"));
+  };
+
+  match state.upload_dataset(name, file_name, content).await {
+    Ok(_) => Ok(Redirect::to("/datasets").into_response()),
+    Err(error) => Ok(error.into_response()),
+  }
+}
+
+/// Builds the response of an invalid upload.
+fn bad_request(message: &str) -> Response {
+  (StatusCode::BAD_REQUEST, message.to_string()).into_response()
+}
+
+/// Reads the `name` field and the `file` field (original name and content) of an upload form.
+async fn read_upload_form(
+  mut multipart: Multipart,
+) -> Result<(Option<String>, Option<(String, Vec<u8>)>), MultipartError> {
+  let mut name: Option<String> = None;
+  let mut file: Option<(String, Vec<u8>)> = None;
+
+  while let Some(field) = multipart.next_field().await? {
+    match field.name() {
+      Some("name") => name = Some(field.text().await?.trim().to_string()),
+      Some("file") => {
+        let file_name = field.file_name().unwrap_or_default().to_string();
+        file = Some((file_name, field.bytes().await?.to_vec()));
+      }
+      _ => {}
+    }
+  }
+
+  Ok((name, file))
+}
+
+/// Builds the response of a multipart form that cannot be read: a 413 when it is larger than
+/// `[UPLOAD_LIMIT_BYTES]`, and a 400 when it is malformed or its body is cut.
+fn multipart_error(error: MultipartError) -> Response {
+  let status = match error.status() {
+    StatusCode::PAYLOAD_TOO_LARGE => StatusCode::PAYLOAD_TOO_LARGE,
+    _ => StatusCode::BAD_REQUEST,
+  };
+  (status, format!("The upload form cannot be read: {}", error.body_text())).into_response()
+}
@@ -0,0 +1,92 @@
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use linear_regression::application_error::GenericResult;
+use linear_regression::cross_validation::TrainTestSplit;
+use linear_regression::dataset_export::{write_dataset, EXPORT_FORMATS};
+use linear_regression::dataset_loader::DatasetFormat;
+use linear_regression::hyperparameter_search::{search_folds, SearchStrategy};
+
+use crate::lessons::l3_linear_regression::{
+  all_features_pumpkins, encodings_split, pie_pumpkins, prepare_lesson_3, regression_rows,
+  search_options, search_parameter_space, SMARTCORE_SEED, SMARTCORE_TEST_SIZE, TRAIN_RATIO,
+};
+use crate::lessons::state::AppState;
+
+/// Gets the URL of an exported table of the lesson 3, without extension, e.g.
+/// `/lesson-3/export/pie-pumpkins`.
+pub fn export_url(table: &str) -> String {
+  format!("/lesson-3/export/{table}")
+}
+
+/// Gets a table of the lesson 3 as a file, so the results can be reproduced elsewhere on the
+/// same rows. The file is `<table>.<extension>` with one of the `[EXPORT_FORMATS]`:
+///
+/// * `prepared-pumpkins`: The bushels with the price per bushel, the month and the day of year.
+/// * `pie-pumpkins`: The pie type pumpkins used by the regressions.
+/// * `split-sequential`: The sequential 80/20 split of the regression rows, shared by Linfa,
+///   Matrix Math, the trees and KNN, see `[TrainTestSplit::label_rows]`.
+/// * `split-smartcore`: The shuffled split of SmartCore on the same rows.
+/// * `split-encodings`: The shuffled 80/20 split of the encodings comparison.
+/// * `search-folds`: The splits and folds of the regression rows for every split ratio of the
+///   hyperparameter search, the same for the grid and the random search, see `[search_folds]`.
+///
+pub async fn get_lesson_3_export(
+  State(state): State<AppState>,
+  Path(file): Path<String>,
+) -> GenericResult<Response> {
+  let format = file
+    .rsplit_once('.')
+    .and_then(|(_, extension)| DatasetFormat::from_extension(extension))
+    .filter(|format| EXPORT_FORMATS.contains(format));
+  let (Some((table, _)), Some(format)) = (file.rsplit_once('.'), format) else {
+    return Ok((StatusCode::NOT_FOUND, format!("Unknown export \"{file}\": use a .csv, .parquet or .json file")).into_response());
+  };
+
+  // Preparing the rows and serializing them takes a while: it runs on the blocking threads
+  let table_name = table.to_string();
+  let content = state
+    .run_blocking(move |state| {
+      let pumpkins = prepare_lesson_3(state)?.pumpkins;
+      let df = match table_name.as_str() {
+        "prepared-pumpkins" => pumpkins,
+        "pie-pumpkins" => pie_pumpkins(&pumpkins)?,
+        "split-sequential" => {
+          let rows = regression_rows(&pie_pumpkins(&pumpkins)?)?;
+          TrainTestSplit::sequential(rows.height(), TRAIN_RATIO).label_rows(&rows)?
+        }
+        "split-smartcore" => {
+          let rows = regression_rows(&pie_pumpkins(&pumpkins)?)?;
+          TrainTestSplit::smartcore(rows.height(), SMARTCORE_TEST_SIZE, SMARTCORE_SEED).label_rows(&rows)?
+        }
+        "split-encodings" => {
+          let rows = all_features_pumpkins(&pumpkins)?;
+          encodings_split(rows.height()).label_rows(&rows)?
+        }
+        "search-folds" => search_folds(
+          &regression_rows(&pie_pumpkins(&pumpkins)?)?,
+          "Price",
+          &search_parameter_space(),
+          &search_options(SearchStrategy::Grid),
+        )?,
+        _ => return Ok(None),
+      };
+      Ok(Some(write_dataset(&df, format)?))
+    })
+    .await?;
+
+  let Some(content) = content else {
+    return Ok((StatusCode::NOT_FOUND, format!("Unknown table \"{table}\"")).into_response());
+  };
+
+  Ok(
+    (
+      [
+        (header::CONTENT_TYPE, format.media_type().to_string()),
+        (header::CONTENT_DISPOSITION, format!("attachment; filename=\"lesson-3-{file}\"")),
+      ],
+      content,
+    )
+      .into_response(),
+  )
+}
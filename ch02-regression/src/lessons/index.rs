@@ -1,8 +1,12 @@
-use axum::{http::StatusCode, response::IntoResponse};
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use linear_regression::{application_error::GenericResult, partials::create_html_page};
 use maud::{html, PreEscaped};
 
-pub async fn get_index() -> GenericResult<impl IntoResponse> {
+use crate::lessons::state::AppState;
+
+pub async fn get_index(State(state): State<AppState>) -> GenericResult<impl IntoResponse> {
+  let datasets = state.datasets()?;
+
   let mut page_elements: Vec<PreEscaped<String>> = Vec::new();
 
   page_elements.push(html!({
@@ -13,6 +17,14 @@ pub async fn get_index() -> GenericResult<impl IntoResponse> {
         li { a href="/lesson-2" { "Lesson 2" }  }
         li { a href="/lesson-3" { "Lesson 3" }  }
       }
+      h2 { "Datasets" }
+      ul {
+        li { a href="/data-quality" { "Data quality of the US pumpkins" }  }
+        @for dataset in &datasets {
+          li { a href={ "/profile/" (dataset.name) } { "Profile of " (dataset.name) }  }
+        }
+        li { a href="/datasets" { "All datasets and upload" }  }
+      }
     }
 
   }));
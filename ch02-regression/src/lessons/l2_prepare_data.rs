@@ -1,25 +1,45 @@
 
-use axum::{response::IntoResponse, http::StatusCode};
+use axum::{extract::State, response::IntoResponse, http::StatusCode};
 use linear_regression::{
   application_error::GenericResult, html_dataframe::html_dataframe,
   html_plot_figure::html_plot_figure, sample_options::SampleOptions,
 };
+use linear_regression::outliers::OutlierAction;
 use maud::{html, PreEscaped};
 use plotly::{common::{Mode, Title}, Scatter, Trace, Layout, layout::Axis, Bar};
 use polars::prelude::*;
 use linear_regression::partials::create_html_notebook;
+use linear_regression::provenance::Provenance;
+use crate::lessons::state::AppState;
+use linear_regression::pumpkins::{
+  package_conversion_table, pumpkins_imputers, pumpkins_outlier_rules, unparsed_packages,
+  PreparationOptions, CUBIC_INCHES_PER_BUSHEL, PUMPKINS_DATASET,
+};
 
 /// Gets the notebook for the lesson 2 Preparing Data.
 /// 
-pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
+pub async fn get_lesson_2(State(state): State<AppState>) -> GenericResult<impl IntoResponse> {
   // List containing the sections and elements of a HTML article fof data analysis.
   let mut article_elements: Vec<PreEscaped<String>> = Vec::new();
 
-  // Load the dataset
-  let df: DataFrame = CsvReader::from_path("data/US-pumpkins.csv")?
-    .has_header(true)
-    .infer_schema(Some(2000))
-    .finish()?;
+  // Prepare the pumpkins like every lesson, but filling the nulls and dropping the outliers of
+  // the price too. The null values are filled with an imputer per attribute (column), fitted on
+  // the whole dataset once the dates are parsed: this lesson never splits the rows. Loading and
+  // preparing the dataset takes a while the first time: it runs on the blocking threads.
+  let preparation_options = PreparationOptions::builder()
+    .imputers(pumpkins_imputers())
+    .outlier_rules(pumpkins_outlier_rules())
+    .build();
+  let (df, dataset_provenance, prepared) = state
+    .run_blocking({
+      let preparation_options = preparation_options.clone();
+      move |state| {
+        let (df, dataset_provenance) = state.load_with_provenance(PUMPKINS_DATASET)?;
+        let prepared = state.prepare_pumpkins(&preparation_options)?;
+        Ok((df, dataset_provenance, prepared))
+      }
+    })
+    .await?;
 
   // Describe the dataset and explore some samples
   article_elements.push(html! {
@@ -37,24 +57,33 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
     ( html_dataframe(&df.null_count(), None)? )
   });
 
-  // Select the attributes of packages, prices and date
-  let pumpkins = df.select(["Package", "Low Price", "High Price", "Date"])?;
-
-  // Options for converting a string into a datetime
-  let dt_options = StrptimeOptions {
-    format: Some("%m/%d/%y".to_string()),
-    strict: true,
-    exact: true,
-    cache: true,
-    use_earliest: Some(true),
-  };
-
-  // Ensure the date attribute (column) is a date properly.
-  let pumpkins = pumpkins
-    .lazy()
-    .with_column(col("Date").str().strptime(DataType::Date, dt_options))
-    .with_columns([(col("Low Price").alias("Price") + col("High Price")) / lit(2)])
-    .collect()?;
+  // The null values of the prepared pumpkins are filled with the imputers
+  let imputers = &prepared.imputers;
+  let imputed = prepared.stage("impute")?;
+  let imputer_table = df!(
+    "Column" => imputers.iter().map(|imputer| imputer.column.clone()).collect::<Vec<String>>(),
+    "Strategy" => imputers.iter().map(|imputer| imputer.strategy.name()).collect::<Vec<String>>(),
+    "Fill Value" => imputers
+      .iter()
+      .map(|imputer| imputer.fill_value().map(|value| value.to_string()))
+      .collect::<Vec<Option<String>>>(),
+    "Indicator" => imputers.iter().map(|imputer| imputer.add_indicator).collect::<Vec<bool>>(),
+  )?;
+
+  article_elements.push(html! {
+    h3 { "Fill the null values" }
+    p { "Every attribute (column) gets its own imputation strategy, fitted once and then applied to any data with the same columns. The next steps of this lesson work on the imputed data; the other lessons keep the nulls, and fit their imputers on their training rows:" }
+    ( html_dataframe(&imputer_table, None)? )
+    p { "Null values after the imputation. Forward fill leaves null the first rows of a city, and KNN the rows without prices." }
+    ( html_dataframe(&imputed.null_count(), None)? )
+  });
+
+  // Show the attributes of packages, prices and date after the next steps: the date attribute
+  // (column) is a date properly, and the prices are averaged.
+  let df_packages = df.select(["Package"])?;
+  let pumpkins = prepared
+    .stage("average_price")?
+    .select(["Package", "Low Price", "High Price", "Date", "Price"])?;
 
   article_elements.push(html! {
     h3 { "Select the attributes of packages, average price, and date" }
@@ -62,10 +91,8 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
   });
 
   // Extract the month from the date and create a new dataframe.
-  let pumpkins = pumpkins
-    .lazy()
-    .with_column(col("Date").alias("Month").dt().month())
-    .collect()?
+  let pumpkins = prepared
+    .stage("extract_month")?
     .select(["Package", "Low Price", "High Price", "Price", "Month"])?;
 
   article_elements.push(html!{
@@ -73,11 +100,40 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
       ( html_dataframe(&pumpkins, Some( SampleOptions::builder().sample_size(10).shuffle(true).build() ) )? )
     });
 
+  // Drop the outliers of the price. Bins are priced per bin and cartons per carton, so fences
+  // over every package are too wide to catch anything: the fences should be per package.
+  let outlier_rules = &preparation_options.outlier_rules;
+  let outlier_report = &prepared.outlier_report;
+  let pumpkins = prepared
+    .stage("extract_day_of_year")?
+    .select(["Package", "Low Price", "High Price", "Price", "Month"])?;
+
+  let flag_rule = outlier_rules[1].clone().action(OutlierAction::Flag);
+  let flagged = flag_rule.apply(pumpkins.clone().lazy()).collect()?;
+  let mut traces: Vec<Box<dyn Trace>> = Vec::new();
+  for (outlier, name) in [(false, "Kept"), (true, "Outlier")] {
+    let points = flagged.clone().lazy().filter(col(&flag_rule.flag_name()).eq(lit(outlier))).collect()?;
+    let months: Vec<Option<f64>> = points["Month"].f64()?.into_iter().collect();
+    let prices: Vec<Option<f64>> = points["Price"].f64()?.into_iter().collect();
+    traces.push(Scatter::new(months, prices).mode(Mode::Markers).name(name));
+  }
+
+  let layout = Layout::new()
+    .title(Title::new("Price vs Month of every package"))
+    .x_axis(Axis::new().title(Title::new("Month")))
+    .y_axis(Axis::new().title(Title::new("Price")));
+
+  article_elements.push(html! {
+    h3 { "Drop the outliers of the price" }
+    p { "Rules applied in order, each one dropping its outliers before the next one. The next steps of this lesson work on the remaining rows; the other lessons keep every row:" }
+    ( html_dataframe(outlier_report, None)? )
+    ( html_plot_figure(traces, &layout, "Prices flagged by the modified z-score per package.")? )
+  });
+
   // Filter the pumpkins packaged in bushels
-  let pumpkins = pumpkins
-    .lazy()
-    .filter(col("Package").str().contains(lit("bushel"), true))
-    .collect()?;
+  let pumpkins = prepared
+    .stage("filter_bushels")?
+    .select(["Package", "Low Price", "High Price", "Price", "Month"])?;
 
   article_elements.push(html! {
     h3 { "Filter the pumpkins packaged in bushels" }
@@ -85,35 +141,38 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
   });
 
   // Adjust the price according to the size of the bushel
-  let pumpkins = pumpkins
-    .lazy()
-    .rename(["Price"], ["OldPrice"])
-    .with_column(
-      when(col("Package").str().contains(lit("1 1/9"), false))
-        .then(col("OldPrice") / lit(1.0 + 1.0 / 9.0))
-        .when(col("Package").str().contains(lit("1/2"), false))
-        .then(col("OldPrice") / lit(1.0 / 2.0))
-        .otherwise(col("OldPrice"))
-        .alias("Price"),
-    )
-    .drop_columns(["OldPrice"])
-    .select([
-      col("Package"),
-      col("Low Price"),
-      col("High Price"),
-      col("Price"),
-      col("Month"),
-    ])
-    .collect()?;
+  let plotted_columns = ["Package", "Low Price", "High Price", "Price", "Month"];
+  let pumpkins = prepared.df().select(plotted_columns)?;
 
   article_elements.push(html! {
     h3 { "Adjust the price according to the size of the bushel" }
     ( html_dataframe(&pumpkins, Some( SampleOptions::builder().sample_size(15).shuffle(true).build() ) )? ) 
   });
 
+  // Explain how every package of the dataset converts into bushels
+  let conversions = &preparation_options.conversions;
+  let package_table = package_conversion_table(&df_packages, conversions)?;
+  let unparsed = unparsed_packages(&df_packages)?;
+
+  article_elements.push(html! {
+    h3 { "Conversion of every package to bushels" }
+    p {
+      "A US bushel is " (CUBIC_INCHES_PER_BUSHEL) " cubic inches (NIST Handbook 44, Appendix C). The other factors are assumptions, not figures of the USDA reports: "
+      "bins are converted by volume with a base of " (conversions.bin_base_square_inches) " square inches (a 48 x 40 inch GMA pallet), weights with "
+      (conversions.pounds_per_bushel) " lb per bushel, and single pumpkins with " (conversions.pounds_per_each) " lb each."
+    }
+    p { "The lessons keep only the packages sold by the bushel, so only the bushel fractions change their prices: this table shows how the other packages would convert." }
+    ( html_dataframe(&package_table, None)? )
+    @if unparsed.is_empty() {
+      p { "Every package was understood." }
+    } @else {
+      p { "Packages that could not be parsed, whose prices become null: " (unparsed.join(", ")) }
+    }
+  });
+
   // Add a Scatter Plot
   let prices: Vec<Option<f64>> = pumpkins["Price"].f64()?.into_iter().collect();
-  let months: Vec<Option<u32>> = pumpkins["Month"].u32()?.into_iter().collect();
+  let months: Vec<Option<f64>> = pumpkins["Month"].f64()?.into_iter().collect();
 
   let trace = Scatter::new(prices, months).mode(Mode::Markers);
   let traces: Vec<Box<dyn Trace>> = vec![trace];
@@ -131,7 +190,7 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
   let pumpkins = pumpkins.lazy().groupby(["Month"]).agg([col("Price").median()]).sort("Month", SortOptions { descending: false, nulls_last: true, maintain_order: true, multithreaded: true }).collect()?;
 
   let prices = pumpkins["Price"].f64()?.into_iter().collect();
-  let months = pumpkins["Month"].u32()?.into_iter().collect();
+  let months = pumpkins["Month"].f64()?.into_iter().collect();
 
   let trace = Bar::new(months, prices);
   let traces: Vec<Box<dyn Trace>> = vec![trace];
@@ -145,5 +204,11 @@ pub async fn get_lesson_2() -> GenericResult<impl IntoResponse> {
     ( html_plot_figure(traces, &layout, "Bar plot for the pumpkins.")? ) 
   });
 
-  Ok((StatusCode::OK, create_html_notebook("Lesson 2: Preparing Source Data", article_elements)?).into_response())
+  // The plots show the prepared pumpkins with the selected attributes (columns)
+  let provenance = Provenance::new()
+    .dataset(dataset_provenance)
+    .steps(&prepared.steps())
+    .steps(&[format!("select({})", plotted_columns.join(", "))]);
+
+  Ok((StatusCode::OK, create_html_notebook("Lesson 2: Preparing Source Data", article_elements, &provenance)?).into_response())
 }
@@ -1,13 +1,45 @@
+use axum::extract::State;
 use axum::http::StatusCode;
 use axum::response::IntoResponse;
-use linear_regression::application_error::GenericResult;
+use linear_regression::application_error::{GenericError, GenericResult};
+use linear_regression::categorical_encoders::{
+  CategoricalEncoder, FrequencyEncoder, OneHotEncoder, OrdinalEncoder, TargetEncoder,
+  UnseenCategory,
+};
+use linear_regression::cross_validation::{shuffled_k_fold, TrainTestSplit};
+use linear_regression::dataset_export::EXPORT_FORMATS;
+use linear_regression::date_features::seasonal_features;
+use linear_regression::formula::{DesignMatrices, Formula};
+use linear_regression::generalized_linear_model::{Family, GeneralizedLinearModel, Link};
 use linear_regression::html_dataframe::html_dataframe;
 use linear_regression::html_plot_figure::html_plot_figure;
-use linear_regression::partials::create_html_notebook;
+use linear_regression::hyperparameter_search::{
+  hyperparameter_search, ParameterSpace, SearchOptions, SearchStrategy,
+};
+use linear_regression::learning_curves::{
+  learning_curve, learning_curve_figure, LearningCurveOptions,
+};
+use linear_regression::nonlinear_least_squares::{LevenbergMarquardtOptions, NonlinearModel};
+use linear_regression::partials::{create_html_notebook, html_download_links};
+use linear_regression::provenance::{Provenance, ProvenanceRecord};
+use linear_regression::pumpkins::{PreparationOptions, PUMPKINS_DATASET};
 use linear_regression::regression_functions::RegressionModel;
+use linear_regression::isotonic_regression::{isotonic_figure, IsotonicRegression, Monotonicity};
+use linear_regression::kernel_regression::{
+  GaussianProcess, Kernel, KernelRidge, MaternSmoothness,
+};
+use linear_regression::knn_regression::{Distance, KnnOptions, KnnRegressor, Weighting};
+use linear_regression::least_squares::{
+  solve_gradient_descent, solve_least_squares, SolverOptions,
+};
 use linear_regression::sample_options::SampleOptions;
+use linear_regression::sparse_matrix::{CsrMatrix, DesignMatrix};
+use linear_regression::tree_regressors::{TreeModelKind, TreeRegressor, TreeRegressorOptions};
+use linear_regression::temporal_split::{
+  forecast_horizon_errors, temporal_split, RollingOriginOptions, TrainingWindow,
+};
 use maud::{html, PreEscaped};
-use ndarray::Array1;
+use ndarray::{Array1, Array2};
 use plotly::color::NamedColor;
 use plotly::common::{Marker, Title};
 use plotly::layout::Axis;
@@ -16,146 +48,290 @@ use plotly::Trace;
 use plotly::{Bar, Layout};
 use polars::export::chrono::*;
 use polars::prelude::*;
-use polars::prelude::{CsvReader, DataFrame, SerReader};
 use std::collections::HashMap;
 
+use crate::lessons::exports::export_url;
+use crate::lessons::state::AppState;
+
+/// Ratio of the training rows of the sequential splits (Linfa, Matrix Math, trees and KNN).
+pub const TRAIN_RATIO: f32 = 0.8;
+
+/// Ratio of the test rows of the shuffled SmartCore split.
+pub const SMARTCORE_TEST_SIZE: f32 = 0.2;
+
+/// Seed of the shuffled SmartCore split.
+pub const SMARTCORE_SEED: u64 = 1;
+
+/// Seed of the shuffled folds of the encodings comparison and of the hyperparameter search.
+pub const CROSS_VALIDATION_SEED: u64 = 42;
+
+/// Represents the pumpkins of the lesson at every stage, with the columns of the lesson.
+pub struct Lesson3Pumpkins {
+  /// The bushels with a proper date.
+  pub bushels: DataFrame,
+  /// The bushels with the average price, the month and the day of year.
+  pub unadjusted: DataFrame,
+  /// The bushels with the price per bushel.
+  pub pumpkins: DataFrame,
+  /// Steps that prepared the pumpkins, in order.
+  pub steps: Vec<String>,
+}
+
+/// Prepares the pumpkins of the lesson from the shared preparation, see
+/// `[AppState::prepare_pumpkins]`, and selects the columns of the lesson.
+///
+/// # Arguments
+///
+/// * `state`: State with the registered US pumpkins.
+pub fn prepare_lesson_3(state: &AppState) -> GenericResult<Lesson3Pumpkins> {
+  let prepared = state.prepare_pumpkins(&PreparationOptions::default())?;
+
+  let bushels_columns = [
+    "Package",
+    "Variety",
+    "City Name",
+    "Item Size",
+    "Low Price",
+    "High Price",
+    "Date",
+  ];
+  let columns = [&bushels_columns[..], &["Price", "Month", "DayOfYear"]].concat();
+
+  // The bushels with the desired attributes and a proper date datatype
+  let bushels = prepared.stage("filter_bushels")?.select(bushels_columns)?;
+
+  // The average price, the month and the day of year, before the price per bushel
+  let unadjusted = prepared.stage("filter_bushels")?.select(&columns)?;
+
+  // The price adjusted to the bushel size
+  let pumpkins = prepared.df().select(&columns)?;
+
+  let mut steps = prepared.steps();
+  steps.push(format!("select({})", columns.join(", ")));
+
+  Ok(Lesson3Pumpkins {
+    bushels,
+    unadjusted,
+    pumpkins,
+    steps,
+  })
+}
+
+/// Gets the pie type pumpkins used by the linear regressions.
+///
+/// # Arguments
+///
+/// * `pumpkins`: Pumpkins with the price per bushel, see `[prepare_lesson_3]`.
+pub fn pie_pumpkins(pumpkins: &DataFrame) -> GenericResult<DataFrame> {
+  Ok(
+    pumpkins
+      .clone()
+      .lazy()
+      .filter(col("Variety").eq(lit("PIE TYPE")))
+      .select([
+        col("Package"),
+        col("Variety"),
+        col("City Name"),
+        col("Low Price"),
+        col("High Price"),
+        col("Date"),
+        col("Month"),
+        col("DayOfYear"),
+        col("Price"),
+      ])
+      .collect()?,
+  )
+}
+
+/// Gets the rows of the pie type pumpkins that the regressions on the day of year use: the
+/// ones with a day of year and a price, in order, like the rows of the design matrix.
+pub fn regression_rows(pie_pumpkins: &DataFrame) -> GenericResult<DataFrame> {
+  Ok(pie_pumpkins.drop_nulls(Some(&["DayOfYear", "Price"]))?)
+}
+
+/// Gets the bushels of the encodings comparison: the ones with a Variety, City Name, Package,
+/// Item Size and Price.
+///
+/// # Arguments
+///
+/// * `pumpkins`: Pumpkins with the price per bushel, see `[prepare_lesson_3]`.
+pub fn all_features_pumpkins(pumpkins: &DataFrame) -> GenericResult<DataFrame> {
+  Ok(
+    pumpkins
+      .clone()
+      .lazy()
+      .select([
+        col("Variety"),
+        col("City Name"),
+        col("Package"),
+        col("Item Size"),
+        col("Price"),
+      ])
+      .drop_nulls(None)
+      .collect()?,
+  )
+}
+
+/// Gets the shuffled 80/20 split of the encodings comparison: the first of 5 shuffled folds,
+/// its validation rows being the test rows.
+///
+/// # Arguments
+///
+/// * `n_rows`: Number of rows of the `[all_features_pumpkins]`.
+pub fn encodings_split(n_rows: usize) -> TrainTestSplit {
+  let fold = shuffled_k_fold(n_rows, 5, CROSS_VALIDATION_SEED).swap_remove(0);
+  TrainTestSplit {
+    train_indexes: fold.train_indexes,
+    test_indexes: fold.validation_indexes,
+  }
+}
+
+/// Gets the space of the hyperparameter search over the pie type pumpkins.
+pub fn search_parameter_space() -> ParameterSpace {
+  ParameterSpace {
+    degrees: vec![1, 2, 3],
+    ridge_lambdas: vec![0.0, 0.1, 1.0, 10.0],
+    split_ratios: vec![0.7, 0.8],
+    feature_subsets: vec![
+      vec!["DayOfYear".to_string()],
+      vec!["Month".to_string()],
+      vec!["DayOfYear".to_string(), "Month".to_string()],
+    ],
+  }
+}
+
+/// Gets the options of the hyperparameter search with the given strategy. Every strategy has
+/// the same 5 folds.
+pub fn search_options(strategy: SearchStrategy) -> SearchOptions {
+  SearchOptions::builder().strategy(strategy).cv_folds(5).seed(CROSS_VALIDATION_SEED).build()
+}
+
 /// Gets the notebook for the lesson 3 Linear Regression
 ///
-pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
-  // List containing the sections and elements of a HTML article fof data analysis.
-  let mut article_elements: Vec<PreEscaped<String>> = Vec::new();
+pub async fn get_lesson_3(State(state): State<AppState>) -> GenericResult<impl IntoResponse> {
+  // Fitting every model takes seconds: the article is built on the blocking threads once, and
+  // cached until the file of the dataset changes. Only its shuffled samples are rendered per
+  // request, with the seed of the request.
+  let (elements, record, dataset_provenance) = state
+    .run_blocking(|state| {
+      let (_, dataset_provenance) = state.load_with_provenance(PUMPKINS_DATASET)?;
+      let article = state.prepare(PUMPKINS_DATASET, "lesson-3/article", || lesson_3_article(state))?;
+      let elements = article
+        .elements
+        .iter()
+        .map(ArticleElement::render)
+        .collect::<GenericResult<Vec<PreEscaped<String>>>>()?;
+      Ok((elements, article.record, dataset_provenance))
+    })
+    .await?;
+
+  let provenance = Provenance::new()
+    .dataset(dataset_provenance)
+    .record(&record);
 
-  // Load the dataset
-  let mut pumpkins: DataFrame = CsvReader::from_path("data/US-pumpkins.csv")?
-    .has_header(true)
-    .infer_schema(Some(2000))
-    .finish()?;
+  Ok(
+    (
+      StatusCode::OK,
+      create_html_notebook("Lesson 3: Linear Regression", elements, &provenance)?,
+    )
+      .into_response(),
+  )
+}
 
-  // Filter only the packages by bushel.
-  pumpkins = pumpkins
-    .lazy()
-    .filter(col("Package").str().contains(lit("bushel"), true))
-    .collect()?;
+/// Represents an element of the article of the lesson 3.
+#[derive(Clone)]
+enum ArticleElement {
+  /// Markup rendered once, e.g. the results of a model.
+  Rendered(PreEscaped<String>),
+  /// Shuffled sample of a dataframe with the given size, rendered per request with its seed.
+  Sample(DataFrame, usize),
+}
 
-  // Select the desired attributes
-  pumpkins = pumpkins
-    .lazy()
-    .select([
-      col("Package"),
-      col("Variety"),
-      col("City Name"),
-      col("Low Price"),
-      col("High Price"),
-      col("Date"),
-    ])
-    .collect()?;
+impl ArticleElement {
+  /// Renders the element, with the seed of the current request for a sample.
+  fn render(&self) -> GenericResult<PreEscaped<String>> {
+    match self {
+      Self::Rendered(markup) => Ok(markup.clone()),
+      Self::Sample(df, sample_size) => html_dataframe(
+        df,
+        Some(SampleOptions::builder().sample_size(*sample_size).shuffle(true).build()),
+      ),
+    }
+  }
+}
 
-  // Options for converting a string into a datetime
-  let dt_options = StrptimeOptions {
-    format: Some("%m/%d/%y".to_string()),
-    strict: true,
-    exact: true,
-    cache: true,
-    use_earliest: Some(true),
-  };
+/// Represents the elements of the article of the lesson 3, in order.
+#[derive(Default)]
+struct ArticleElements(Vec<ArticleElement>);
+
+impl ArticleElements {
+  /// Adds markup rendered once.
+  fn push(
+    &mut self,
+    markup: PreEscaped<String>,
+  ) {
+    self.0.push(ArticleElement::Rendered(markup));
+  }
 
-  // Transform into proper date datatype.
-  pumpkins = pumpkins
-    .lazy()
-    .with_columns([col("Date").str().strptime(DataType::Date, dt_options)])
-    .collect()?;
+  /// Adds a shuffled sample of a dataframe, rendered per request.
+  fn push_sample(
+    &mut self,
+    df: &DataFrame,
+    sample_size: usize,
+  ) {
+    self.0.push(ArticleElement::Sample(df.clone(), sample_size));
+  }
+}
+
+/// Represents the sections of the lesson 3, with the steps and the seeds that computed them.
+#[derive(Clone)]
+struct Lesson3Article {
+  elements: Vec<ArticleElement>,
+  record: ProvenanceRecord,
+}
+
+/// Builds the sections of the lesson 3: prepares the pumpkins and fits every model.
+///
+/// # Arguments
+///
+/// * `state`: State with the registered US pumpkins.
+fn lesson_3_article(state: &AppState) -> GenericResult<Lesson3Article> {
+  // List containing the sections and elements of a HTML article fof data analysis.
+  let mut article_elements = ArticleElements::default();
+  // Steps and seeds of the results, recorded as they are used
+  let mut record = ProvenanceRecord::new();
+
+  let Lesson3Pumpkins {
+    bushels,
+    unadjusted,
+    pumpkins,
+    steps,
+  } = prepare_lesson_3(state)?;
+  record.steps(&steps);
 
   article_elements.push(html! {
     h1 { "Lesson 3: Linear and Polynomial Regression for Pumpkin Pricing" }
     h2 { "Prepare the Dataset" }
     h3 { "Load and convert the source data" }
-    ( html_dataframe(&pumpkins, Some(
-    SampleOptions::builder().sample_size(10).shuffle(true).build() ) )? )
   });
-
-  // Calculate average price
-  pumpkins = pumpkins
-    .lazy()
-    .with_columns([((col("Low Price") + col("High Price")) / lit(2.0)).alias("Price")])
-    .collect()?;
-
-  // Extract the month
-  let months = pumpkins
-    .clone()
-    .lazy()
-    .select([col("Date").dt().month().alias("Month")])
-    .collect()?;
-
-  // See: https://stackoverflow.com/questions/76059689/convert-str-to-f64-using-a-rust-polars-custom-function
-  let days_of_year = pumpkins
-    .clone()
-    .lazy()
-    .select([col("Date")
-      .map(
-        |dates_col| {
-          let days_col = dates_col
-            .date()?
-            .as_date_iter()
-            .map(|date_op| {
-              // If the original value is unavailable (NULL or None) there is no need
-              // to calculate the number of days, so return None
-              let current_date = match date_op {
-                Some(value) => value,
-                None => return None,
-              };
-
-              // There is a date value, so try to calculate the number of days.
-              // Otherwise, if an error ocurrs return None
-              let year_start = match NaiveDate::from_ymd_opt(current_date.year(), 1, 1) {
-                Some(value) => value,
-                None => return None,
-              };
-
-              // Finally, return the number of days encapsulated in an Option enum due to we are considering returning None values as mentioned above
-              Some((current_date - year_start).num_days())
-            })
-            .collect();
-
-          Ok(Some(days_col))
-        },
-        GetOutput::from_type(DataType::Int32),
-      )
-      .alias("DayOfYear")])
-    .collect()?;
-
-  pumpkins = polars::functions::hor_concat_df(&[pumpkins, months, days_of_year])?;
+  article_elements.push_sample(&bushels, 10);
 
   article_elements.push(html!( {
                                                   h3 { "Get average price, month,
                                                     and day of year" }
-                                                    ( html_dataframe(&pumpkins,
-                                                                     Some(
-  SampleOptions::builder().sample_size(15).shuffle(true).build() ) )? )
       }));
-
-  // Adjust the price based on  the bushel size
-  pumpkins = pumpkins
-    .lazy()
-    .rename(["Price"], ["OldPrice"])
-    .select([
-      all(),
-      when(col("Package").str().contains(lit("1 1/9"), false))
-        .then(col("OldPrice") / lit(1.0 + 1.0 / 9.0))
-        .when(col("Package").str().contains(lit("1/2"), false))
-        .then(col("OldPrice") / lit(1.0 / 2.0))
-        .otherwise(col("OldPrice"))
-        .alias("Price"),
-    ])
-    .drop_columns(["OldPrice"])
-    .collect()?;
+  article_elements.push_sample(&unadjusted, 15);
 
   article_elements.push(html!( {
     h3 { "Adjust price based on  the bushel size" }
-    ( html_dataframe(&pumpkins, Some( SampleOptions::builder().sample_size(15).shuffle(true).build() ) )? )
+  }));
+  article_elements.push_sample(&pumpkins, 15);
+  article_elements.push(html!( {
+    ( html_download_links(&export_url("prepared-pumpkins"), &EXPORT_FORMATS) )
   }));
 
   // Plot price and month
-  let months: Vec<Option<u32>> = pumpkins["Month"].u32()?.into_iter().collect();
+  let months: Vec<Option<f64>> = pumpkins["Month"].f64()?.into_iter().collect();
   let prices: Vec<Option<f64>> = pumpkins["Price"].f64()?.into_iter().collect();
 
   let trace = Scatter::new(months, prices.clone()).mode(plotly::common::Mode::Markers);
@@ -174,7 +350,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   });
 
   // Plot price and day of the year
-  let days_of_year: Vec<Option<i64>> = pumpkins["DayOfYear"].i64()?.into_iter().collect();
+  let days_of_year: Vec<Option<f64>> = pumpkins["DayOfYear"].f64()?.into_iter().collect();
   let trace = Scatter::new(days_of_year, prices).mode(plotly::common::Mode::Markers);
   let traces: Vec<Box<dyn Trace>> = vec![trace];
 
@@ -190,17 +366,8 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   }));
 
   // Calculate the correlation
-  let correlation_source_data = pumpkins
+  let correlation_month_price = pumpkins
     .clone()
-    .lazy()
-    .select([
-      col("Price"),
-      col("Month").cast(DataType::Float64),
-      col("DayOfYear").cast(DataType::Float64),
-    ])
-    .collect()?;
-
-  let correlation_month_price = correlation_source_data
     .lazy()
     .select([
       pearson_corr(col("Month"), col("Price"), 1).alias("Correlation Month vs Price"),
@@ -214,6 +381,37 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
     ( html_dataframe(&correlation_month_price, None )? )
   }));
 
+  // Correlation of the calendar and seasonal features with the price
+  let seasonal = pumpkins
+    .clone()
+    .lazy()
+    .select([col("Price")].into_iter().chain(seasonal_features("Date")).collect::<Vec<Expr>>())
+    .collect()?;
+  let seasonal_names: Vec<String> = seasonal.get_column_names()[1..].iter().map(|name| name.to_string()).collect();
+  let seasonal_correlations = seasonal
+    .lazy()
+    .select(
+      seasonal_names
+        .iter()
+        .map(|name| pearson_corr(col(name), col("Price"), 1))
+        .collect::<Vec<Expr>>(),
+    )
+    .collect()?;
+  let seasonal_correlations = df!(
+    "Feature" => &seasonal_names,
+    "Correlation vs Price" => seasonal_correlations
+      .get_columns()
+      .iter()
+      .map(|correlation| correlation.f64().ok().and_then(|values| values.get(0)))
+      .collect::<Vec<Option<f64>>>(),
+  )?;
+
+  article_elements.push(html!( {
+    h3 { "Calendar and seasonal features" }
+    p { "Days are counted to the next and from the last Halloween (October 31st) and Thanksgiving (fourth Thursday of November). The day of year is also encoded as a sine and a cosine, so December 31st is close to January 1st." }
+    ( html_dataframe(&seasonal_correlations, None )? )
+  }));
+
   // Plot a scatter plot of day of year vs price and variety
   let varieties = HashMap::<&str, NamedColor>::from([
     ("PIE TYPE", NamedColor::Red),
@@ -232,7 +430,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
       .collect()?;
 
     let x_values = variety_data.clone()["DayOfYear"]
-      .i64()?
+      .f64()?
       .into_iter()
       .collect();
     let y_values = variety_data.clone()["Price"].f64()?.into_iter().collect();
@@ -290,7 +488,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
     .lazy()
     .groupby([col("Variety")])
     .agg([
-      pearson_corr(col("DayOfYear").cast(DataType::Float64), col("Price"), 1)
+      pearson_corr(col("DayOfYear"), col("Price"), 1)
         .alias("Correlation DayOfYear vs Variety"),
     ])
     .collect()?;
@@ -301,43 +499,28 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   }));
 
   // Prepare data for Linear Regresion
-  let pie_pumpkins = pumpkins
-    .lazy()
-    .filter(col("Variety").eq(lit("PIE TYPE")))
-    .select([
-      col("Package"),
-      col("Variety"),
-      col("City Name"),
-      col("Low Price"),
-      col("High Price"),
-      col("Date"),
-      col("Month").cast(DataType::Float64),
-      col("DayOfYear").cast(DataType::Float64),
-      col("Price"),
-    ])
-    .collect()?;
+  let pie_pumpkins = pie_pumpkins(&pumpkins)?;
+  record.step("pie_pumpkins");
 
   article_elements.push(html! {
     h2 { "Linear Regression" }
     h3 { "Data for the Linear Regression" }
-    ( html_dataframe(&pie_pumpkins, Some( SampleOptions::builder().sample_size(12).shuffle(true).build() ) )? )
+  });
+  article_elements.push_sample(&pie_pumpkins, 12);
+  article_elements.push(html! {
+    ( html_download_links(&export_url("pie-pumpkins"), &EXPORT_FORMATS) )
+    p { "The train/test split of every library below can be downloaded too: its rows are the training rows and then the test rows in the order the library uses them, with their index in the rows with a day of year and a price (Row) and their split (Split)." }
   });
 
   // Linear Regression
-  // The shape will be [n, 1]
-  let x_values = pie_pumpkins
-    .clone()
-    .lazy()
-    .select([col("DayOfYear")])
-    .collect()?
-    .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
-
-  // The shape will be [n, 1]
-  let y_values = pie_pumpkins
-    .lazy()
-    .select([col("Price")])
-    .collect()?
-    .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+  // Both shapes will be [n, 1]; the libraries add their own intercept
+  let day_of_year_formula = Formula::parse("Price ~ DayOfYear - 1")?;
+  let DesignMatrices {
+    x: x_values,
+    y: y_values,
+    ..
+  } = day_of_year_formula.design_matrices(&pie_pumpkins)?;
+  record.step(&format!("design_matrices({day_of_year_formula})"));
 
   let col_parameters = "Parameters (β)";
   let col_r2 = "Coef Determination\n(r²)";
@@ -358,17 +541,11 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   {
     use linfa::prelude::*;
     use linfa_linear::LinearRegression;
-    use ndarray::s;
-
-    // This is trick of using slice() instead of column() because, currently, linfa linear regression requires a Array2 [usize, 2].
-    let x_values = x_values.slice(s![.., 0..1]).to_owned();
 
-    let y_values = y_values.column(0).to_owned();
-
-    let dataset = Dataset::new(x_values, y_values);
+    let dataset = Dataset::new(x_values.clone(), y_values.column(0).to_owned());
 
     // Split dataset into training/test (80%/20%)
-    let (dataset_train, dataset_test) = dataset.split_with_ratio(0.8);
+    let (dataset_train, dataset_test) = dataset.split_with_ratio(TRAIN_RATIO);
 
     let model = LinearRegression::new().fit(&dataset_train)?;
 
@@ -389,7 +566,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
       Series::new(
         col_parameters,
         &[RegressionModel::β_to_string(
-          vec![vec![line_intercept], correlation_coefficients].concat(),
+          [vec![line_intercept], correlation_coefficients].concat(),
         )],
       ),
       Series::new(col_r2, &[score]),
@@ -409,6 +586,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
     // Draw the linear regression model
     article_elements.push(html!( {
       h3 { "Linear Regression with Linfa" }
+      ( html_download_links(&export_url("split-sequential"), &EXPORT_FORMATS) )
     }));
 
     let days_of_year: Vec<f64> = dataset_test
@@ -445,7 +623,8 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
 
     // Split dataset into training/test (80%/20%)
     let (x_train, x_test, y_train, y_test) =
-      train_test_split(&x_values, &y_values.column(0).to_vec(), 0.2, true, Some(1));
+      train_test_split(&x_values, &y_values.column(0).to_vec(), SMARTCORE_TEST_SIZE, true, Some(SMARTCORE_SEED));
+    record.seed("SmartCore split", SMARTCORE_SEED);
 
     let model =
       LinearRegression::fit(&x_train, &y_train, LinearRegressionParameters::default())?;
@@ -468,7 +647,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
       Series::new(
         col_parameters,
         &[RegressionModel::β_to_string(
-          vec![vec![line_intercept], correlation_coefficients].concat(),
+          [vec![line_intercept], correlation_coefficients].concat(),
         )],
       ),
       Series::new(col_r2, &[score]),
@@ -489,6 +668,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
     // Draw the linear regression model
     article_elements.push(html!( {
       h3 { "Linear Regression with SmartCore" }
+      ( html_download_links(&export_url("split-smartcore"), &EXPORT_FORMATS) )
     }));
 
     let days_of_year: Vec<f64> = x_test.column(0).into_iter().copied().collect();
@@ -512,7 +692,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   }
 
   // Linear Regression using Matrix Math
-  let mut model = RegressionModel::new(x_values.clone(), y_values.clone(), 0.8);
+  let mut model = RegressionModel::new(x_values.clone(), y_values.clone(), TRAIN_RATIO);
 
   model.solve(1);
   let r2 = model.r2(&model.x_test, &model.y_test);
@@ -546,6 +726,7 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   // Draw the linear regression model
   article_elements.push(html!( {
     h3 { "Linear Regression with Matrix Math" }
+    ( html_download_links(&export_url("split-sequential"), &EXPORT_FORMATS) )
   }));
 
   // The days of year are in the column 1, because it is an expansion matrix X = [1, x].
@@ -568,16 +749,847 @@ pub async fn get_lesson_3() -> GenericResult<impl IntoResponse> {
   (html_plot_figure(traces, &layout, "Scatter plot price vs day of year.")?)
   });
 
+  // Tree-based regressors on Day of Year and Month, same sequential 80/20 split as Matrix Math
+  let tree_features = ["DayOfYear", "Month"];
+  let tree_rows = regression_rows(&pie_pumpkins)?;
+  record.step("regression_rows");
+  let tree_train_height = (tree_rows.height() as f32 * TRAIN_RATIO).ceil() as usize;
+  let tree_train_df = tree_rows.slice(0, tree_train_height);
+  let tree_test_df =
+    tree_rows.slice(tree_train_height as i64, tree_rows.height() - tree_train_height);
+  let tree_y_test = non_null_values(&tree_test_df, "Price")?;
+  let tree_test_days = non_null_values(&tree_test_df, "DayOfYear")?;
+
+  let mut tree_importance_elements: Vec<maud::Markup> = Vec::new();
+  let mut tree_traces: Vec<Box<dyn Trace>> = vec![Scatter::new(
+    tree_test_days.clone(),
+    tree_y_test.clone(),
+  )
+  .mode(plotly::common::Mode::Markers)
+  .name("Test Prices")];
+
+  for (kind, options) in [
+    (
+      TreeModelKind::DecisionTree,
+      TreeRegressorOptions::builder().max_depth(Some(4)).min_samples_leaf(5).build(),
+    ),
+    (
+      TreeModelKind::RandomForest,
+      TreeRegressorOptions::builder()
+        .max_depth(Some(6))
+        .n_estimators(100)
+        .max_features(Some(2))
+        .min_samples_leaf(3)
+        .build(),
+    ),
+  ] {
+    record.seed(&format!("{} random state", kind.name()), options.seed);
+    let tree = TreeRegressor::fit(&tree_train_df, &tree_features, "Price", kind, options)?;
+    let predictions = tree.predict(&tree_test_df)?;
+
+    let predictions_array = Array2::from_shape_vec((predictions.len(), 1), predictions.clone())?;
+    let y_test_array = Array2::from_shape_vec((tree_y_test.len(), 1), tree_y_test.clone())?;
+    let mse = (&y_test_array - &predictions_array).mapv(|e| e.powi(2)).mean().unwrap_or(0.0);
+    let mean_error = mse.sqrt();
+    let y_test_mean = tree_y_test.iter().sum::<f64>() / tree_y_test.len().max(1) as f64;
+    let tss: f64 = tree_y_test.iter().map(|y| (y - y_test_mean).powi(2)).sum();
+    let r2 = 1.0 - mse * tree_y_test.len() as f64 / tss;
+
+    regression_results_df.vstack_mut(&DataFrame::new(vec![
+      Series::new(col_library, &[format!("SmartCore {}", kind.name())]),
+      Series::new(col_parameters, &[tree.parameters_to_string()]),
+      Series::new(col_r2, &[r2]),
+      Series::new(col_mse, &[mse]),
+      Series::new(
+        col_mean_error,
+        &[format!(
+          "{:.3} ({:.3} %)",
+          mean_error,
+          mean_error / predictions_array.mean().unwrap_or(0.0) * 100.0
+        )],
+      ),
+    ])?)?;
+
+    tree_importance_elements.push(html! {
+      h4 { (kind.name()) " (" (tree.parameters_to_string()) ")" }
+      ( html_dataframe(&tree.feature_importances(&tree_test_df, 10)?, None)? )
+    });
+    tree_traces.push(
+      Scatter::new(
+        tree_test_days.clone(),
+        predictions,
+      )
+      .mode(plotly::common::Mode::Markers)
+      .name(kind.name()),
+    );
+  }
+
+  article_elements.push(html! {
+    h3 { "Tree-based Regressors" }
+    p { "Decision tree and random forest on Day of Year and Month. Feature importances are the mean increase of the test MSE when the feature is shuffled (10 repeats)." }
+    ( html_download_links(&export_url("split-sequential"), &EXPORT_FORMATS) )
+    @for element in &tree_importance_elements {
+      (element)
+    }
+    ( html_plot_figure(
+      tree_traces,
+      &Layout::new()
+        .title(Title::new("Tree Predictions vs Day of Year"))
+        .x_axis(Axis::new().title(Title::new("Day of Year")))
+        .y_axis(Axis::new().title(Title::new("Price"))),
+      "Test prices and the piecewise-constant predictions of the trees.",
+    )? )
+  });
+
+  // k-nearest-neighbours regression on Day of Year, same split as Matrix Math
+  let (knn_x_train, knn_x_test) = RegressionModel::split_data(&x_values, TRAIN_RATIO);
+  let (knn_y_train, knn_y_test) = RegressionModel::split_data(&y_values, TRAIN_RATIO);
+  let knn_days: Vec<f64> = (200..=365).map(f64::from).collect();
+  let knn_x_curve = Array2::from_shape_vec((knn_days.len(), 1), knn_days.clone())?;
+
+  let mut knn_traces: Vec<Box<dyn Trace>> = vec![Scatter::new(
+    knn_x_train.column(0).to_vec(),
+    knn_y_train.column(0).to_vec(),
+  )
+  .mode(plotly::common::Mode::Markers)
+  .name("Train Prices")];
+
+  for options in [
+    KnnOptions::builder().k(5).build(),
+    KnnOptions::builder().k(10).weighting(Weighting::Distance).build(),
+    KnnOptions::builder()
+      .k(10)
+      .distance(Distance::Manhattan)
+      .weighting(Weighting::Uniform)
+      .build(),
+  ] {
+    let mut knn = KnnRegressor::new(options);
+    knn.fit(&knn_x_train, &knn_y_train)?;
+
+    let predictions = knn.predict(&knn_x_test);
+    let mse = knn.mse(&knn_x_test, &knn_y_test);
+    let mean_error = mse.sqrt();
+    let y_test = knn_y_test.column(0);
+    let y_test_mean = y_test.mean().unwrap_or(0.0);
+    let r2 = 1.0 - mse * y_test.len() as f64 / y_test.mapv(|y| (y - y_test_mean).powi(2)).sum();
+
+    regression_results_df.vstack_mut(&DataFrame::new(vec![
+      Series::new(col_library, &["KNN (KD-tree)"]),
+      Series::new(col_parameters, &[knn.parameters_to_string()]),
+      Series::new(col_r2, &[r2]),
+      Series::new(col_mse, &[mse]),
+      Series::new(
+        col_mean_error,
+        &[format!(
+          "{:.3} ({:.3} %)",
+          mean_error,
+          mean_error / predictions.column(0).mean().unwrap_or(0.0) * 100.0
+        )],
+      ),
+    ])?)?;
+
+    knn_traces.push(
+      Scatter::new(knn_days.clone(), knn.predict(&knn_x_curve).column(0).to_vec())
+        .mode(plotly::common::Mode::Lines)
+        .name(knn.parameters_to_string()),
+    );
+  }
+
+  article_elements.push(html! {
+    h3 { "k-Nearest-Neighbours Regression" }
+    p { "The price of a day is the mean price of the k closest training days, found with a KD-tree. Distance weighting lets the closest days count more." }
+    ( html_download_links(&export_url("split-sequential"), &EXPORT_FORMATS) )
+    ( html_plot_figure(
+      knn_traces,
+      &Layout::new()
+        .title(Title::new("KNN Predictions vs Day of Year"))
+        .x_axis(Axis::new().title(Title::new("Day of Year")))
+        .y_axis(Axis::new().title(Title::new("Price"))),
+      "Training prices and the KNN predictions over the season.",
+    )? )
+  });
+
   article_elements.push(html! {
     h3 { "Linear Regression Results" }
     ( html_dataframe(&regression_results_df, None)?  )
   });
 
-  Ok(
+  // Formula interface: the design matrix of a richer model over all the varieties
+  let formula =
+    Formula::parse("Price ~ DayOfYear + poly(Month, 2) + C(Variety) + DayOfYear:C(City Name)")?;
+  let formula_design = formula.design_matrices(&pumpkins)?;
+  let formula_solution = solve_least_squares(
+    &formula_design.x,
+    &formula_design.y.column(0).to_owned(),
+    &SolverOptions::builder().max_iterations(5000).build(),
+  )?;
+
+  let formula_df = DataFrame::new(vec![
+    Series::new("Term", formula_design.column_terms.clone()),
+    Series::new("Column", formula_design.column_names.clone()),
+    Series::new("β", formula_solution.β.to_vec()),
+  ])?;
+
+  article_elements.push(html! {
+    h3 { "Formula Interface" }
+    p {
+      "The design matrix of " code { (formula.to_string()) } " built straight from the dataframe of all the varieties: "
+      (formula_design.x.nrows()) " rows (" (formula_design.dropped_rows) " dropped for nulls), "
+      (formula_design.x.ncols()) " columns, r² = "
+      (format!("{:.3}", ols_model_r2(&formula_design.x, &formula_design.y, &formula_solution.β))) "."
+    }
+    ( html_dataframe(&formula_df, Some( SampleOptions::builder().sample_size(20).build() ) )? )
+  });
+
+  // All features model: compare the categorical encodings on a shuffled 80/20 split
+  let all_features_pumpkins = all_features_pumpkins(&pumpkins)?;
+  let all_features_split = encodings_split(all_features_pumpkins.height());
+  record.seed("Encodings comparison split", CROSS_VALIDATION_SEED);
+  let all_features_train = all_features_pumpkins.take(&IdxCa::from_vec(
+    "",
+    all_features_split.train_indexes.iter().map(|index| *index as IdxSize).collect(),
+  ))?;
+  let all_features_test = all_features_pumpkins.take(&IdxCa::from_vec(
+    "",
+    all_features_split.test_indexes.iter().map(|index| *index as IdxSize).collect(),
+  ))?;
+  let item_size_order = ["sml", "med", "med-lge", "lge", "xlge", "jbo"];
+
+  let encodings: Vec<(&str, Vec<Box<dyn CategoricalEncoder>>)> = vec![
     (
-      StatusCode::OK,
-      create_html_notebook("Lesson 3: Linear Regression", article_elements)?,
-    )
-      .into_response(),
+      "One-hot (drop first)",
+      ["Variety", "City Name", "Package", "Item Size"]
+        .iter()
+        .map(|column| -> Box<dyn CategoricalEncoder> {
+          Box::new(OneHotEncoder::new(column).drop_first(true).unseen(UnseenCategory::Default))
+        })
+        .collect(),
+    ),
+    (
+      "One-hot + ordinal Item Size",
+      vec![
+        Box::new(OneHotEncoder::new("Variety").drop_first(true).unseen(UnseenCategory::Default)),
+        Box::new(OneHotEncoder::new("City Name").drop_first(true).unseen(UnseenCategory::Default)),
+        Box::new(OneHotEncoder::new("Package").drop_first(true).unseen(UnseenCategory::Default)),
+        Box::new(OrdinalEncoder::new("Item Size").order(&item_size_order)),
+      ],
+    ),
+    (
+      "Target (smoothing 10)",
+      ["Variety", "City Name", "Package", "Item Size"]
+        .iter()
+        .map(|column| -> Box<dyn CategoricalEncoder> {
+          Box::new(TargetEncoder::new(column, "Price").smoothing(10.0))
+        })
+        .collect(),
+    ),
+    (
+      "Frequency",
+      ["Variety", "City Name", "Package", "Item Size"]
+        .iter()
+        .map(|column| -> Box<dyn CategoricalEncoder> { Box::new(FrequencyEncoder::new(column)) })
+        .collect(),
+    ),
+  ];
+
+  let mut encoding_names: Vec<&str> = Vec::new();
+  let mut encoding_columns: Vec<u32> = Vec::new();
+  let mut encoding_r2: Vec<f64> = Vec::new();
+  let mut encoding_mse: Vec<f64> = Vec::new();
+
+  for (name, mut encoders) in encodings {
+    let mut train = all_features_train.clone();
+    let mut test = all_features_test.clone();
+    for encoder in encoders.iter_mut() {
+      train = encoder.fit_transform(&train)?;
+      test = encoder.transform(&test)?;
+    }
+
+    // Design matrices with an intercept column first
+    let design = |df: &DataFrame| -> GenericResult<(Array2<f64>, Array2<f64>)> {
+      let features = df.drop("Price")?.to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+      let x = ndarray::concatenate(
+        ndarray::Axis(1),
+        &[Array2::<f64>::ones((df.height(), 1)).view(), features.view()],
+      )?;
+      let y = df.select(["Price"])?.to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+      Ok((x, y))
+    };
+    let (x_train, y_train) = design(&train)?;
+    let (x_test, y_test) = design(&test)?;
+
+    let solution = solve_least_squares(
+      &x_train,
+      &y_train.column(0).to_owned(),
+      &SolverOptions::builder().max_iterations(5000).build(),
+    )?;
+    let residuals = &y_test.column(0) - &x_test.dot(&solution.β);
+
+    encoding_names.push(name);
+    encoding_columns.push(x_train.ncols() as u32);
+    encoding_r2.push(ols_model_r2(&x_test, &y_test, &solution.β));
+    encoding_mse.push(residuals.mapv(|e| e.powi(2)).mean().unwrap_or(0.0));
+  }
+
+  let encodings_df = DataFrame::new(vec![
+    Series::new("Encoding", encoding_names),
+    Series::new("Columns", encoding_columns),
+    Series::new(col_r2, encoding_r2),
+    Series::new(col_mse, encoding_mse),
+  ])?;
+
+  article_elements.push(html! {
+    h3 { "All Features Model" }
+    p {
+      "Price from Variety, City Name, Package and Item Size over " (all_features_pumpkins.height())
+      " bushel pumpkins with an Item Size, with every categorical encoding fitted on 80% of the rows and scored on the other 20%."
+    }
+    ( html_dataframe(&encodings_df, None)? )
+    ( html_download_links(&export_url("split-encodings"), &EXPORT_FORMATS) )
+  });
+
+  // Learning curves: would more PIE TYPE rows improve the model?
+  article_elements.push(html! {
+    h2 { "Learning Curves" }
+    p { "Errors of the Matrix Math model trained on increasing fractions of the training split." }
+  });
+
+  for degree in [1, 2] {
+    let learning_curve_options = LearningCurveOptions::builder()
+      .degree(degree)
+      .split_ratio(0.8)
+      .cv_folds(Some(5))
+      .build();
+
+    let learning_curve_df = learning_curve(&x_values, &y_values, &learning_curve_options)?;
+
+    article_elements.push(html! {
+      h3 { (format!("Polynomial of degree {degree} (5-fold cross-validation)")) }
+      ( html_dataframe(&learning_curve_df, None)? )
+      ( learning_curve_figure(&learning_curve_df, "Learning curve: training and validation MSE vs training size.")? )
+    });
+  }
+
+  // Hyperparameter search over the polynomial degree, ridge λ, split ratio and features
+  let parameter_space = search_parameter_space();
+  let grid_search_options = search_options(SearchStrategy::Grid);
+  let search_rows = regression_rows(&pie_pumpkins)?;
+  let grid_leaderboard = hyperparameter_search(&search_rows, "Price", &parameter_space, &grid_search_options)?;
+  record.seed("Grid search folds", grid_search_options.seed);
+
+  let random_search_options = search_options(SearchStrategy::Random(10));
+  let random_leaderboard = hyperparameter_search(&search_rows, "Price", &parameter_space, &random_search_options)?;
+  record.seed("Random search candidates and folds", random_search_options.seed);
+
+  article_elements.push(html! {
+    h2 { "Hyperparameter Search" }
+    p { "Candidates are scored by the mean validation MSE of a 5-fold cross-validation (seed " (CROSS_VALIDATION_SEED) ") over their training split." }
+    ( html_download_links(&export_url("search-folds"), &EXPORT_FORMATS) )
+    h3 { "Grid Search Leaderboard (top 10)" }
+    ( html_dataframe(&grid_leaderboard, Some(SampleOptions::builder().sample_size(10).build()))? )
+    h3 { "Random Search Leaderboard (10 candidates)" }
+    ( html_dataframe(&random_leaderboard, None)? )
+  });
+
+  // Time-aware evaluation: train on the past, test on the future
+  let cutoff = NaiveDate::from_ymd_opt(2016, 11, 1).unwrap_or_default();
+  let (past_pumpkins, future_pumpkins) = temporal_split(&pie_pumpkins, "Date", cutoff)?;
+
+  let mut model = RegressionModel::new(
+    past_pumpkins
+      .select(["DayOfYear"])?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+    past_pumpkins
+      .select(["Price"])?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+    1.0,
+  );
+  model.try_solve(1)?;
+
+  let x_future = RegressionModel::polyfit_data(
+    &future_pumpkins
+      .select(["DayOfYear"])?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+    1,
+  );
+  let y_future = future_pumpkins
+    .select(["Price"])?
+    .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+
+  let temporal_split_df = DataFrame::new(vec![
+    Series::new("Cutoff Date", &[cutoff.to_string()]),
+    Series::new("Training Rows", &[past_pumpkins.height() as u32]),
+    Series::new("Testing Rows", &[future_pumpkins.height() as u32]),
+    Series::new(col_parameters, &[RegressionModel::β_to_string(model.β.column(0).to_vec())]),
+    Series::new(col_mse, &[model.mse(&x_future, &y_future)]),
+  ])?;
+
+  let rolling_origin_options = RollingOriginOptions::builder()
+    .initial_dates(6)
+    .horizon(4)
+    .step(1)
+    .window(TrainingWindow::Expanding)
+    .build();
+
+  let horizon_errors_df = forecast_horizon_errors(
+    &pie_pumpkins,
+    "Date",
+    &["DayOfYear"],
+    "Price",
+    1,
+    &rolling_origin_options,
+  )?;
+
+  let horizon_steps: Vec<Option<u32>> =
+    horizon_errors_df["Horizon Step"].u32()?.into_iter().collect();
+  let horizon_rmse: Vec<Option<f64>> =
+    horizon_errors_df["RMSE"].f64()?.into_iter().collect();
+
+  let traces: Vec<Box<dyn Trace>> = vec![Bar::new(horizon_steps, horizon_rmse)];
+  let layout = Layout::new()
+    .title(Title::new("Forecast Error vs Horizon"))
+    .x_axis(Axis::new().title(Title::new("Horizon (market dates ahead)")))
+    .y_axis(Axis::new().title(Title::new("RMSE")));
+
+  article_elements.push(html! {
+    h2 { "Time-aware Evaluation" }
+    h3 { "Temporal Split" }
+    p { "Rows dated before the cutoff train the model; rows on or after it test it." }
+    ( html_dataframe(&temporal_split_df, None)? )
+    h3 { "Rolling-origin Evaluation" }
+    p { "Expanding window starting with 6 market dates, forecasting the next 4 dates from every origin." }
+    ( html_dataframe(&horizon_errors_df, None)? )
+    ( html_plot_figure(traces, &layout, "Bar plot RMSE vs forecast horizon.")? )
+  });
+
+  // Generalized linear models for strictly positive, right-skewed prices
+  let glm_degree = 2;
+  let (x_train, x_test) = RegressionModel::split_data(&x_values, TRAIN_RATIO);
+  let (y_train, y_test) = RegressionModel::split_data(&y_values, TRAIN_RATIO);
+
+  // Days of year over the whole season, to see the predictions at its edges
+  let season_days = Array2::from_shape_fn((166, 1), |(row, _)| 200.0 + row as f64);
+
+  let mut ols_model = RegressionModel::new(x_train.clone(), y_train.clone(), 1.0);
+  ols_model.try_solve(glm_degree)?;
+  let x_test_model = RegressionModel::polyfit_data(&x_test, glm_degree);
+  let ols_season_prices = ols_model.predict(&RegressionModel::polyfit_data(&season_days, glm_degree));
+
+  let mut glm_names: Vec<String> = vec!["OLS (Matrix Math)".to_string()];
+  let mut glm_parameters: Vec<String> =
+    vec![RegressionModel::β_to_string(ols_model.β.column(0).to_vec())];
+  // The Gaussian log-likelihood of OLS gives AIC = n(ln(2π RSS / n) + 1) + 2(p + 1)
+  let ols_rss = ols_model.rss(&ols_model.x_train, &ols_model.y_train);
+  let n_train = ols_model.x_train.nrows() as f64;
+  let ols_aic = n_train * ((2.0 * std::f64::consts::PI * ols_rss / n_train).ln() + 1.0)
+    + 2.0 * (ols_model.β.nrows() + 1) as f64;
+
+  let mut glm_deviances: Vec<f64> = vec![ols_rss];
+  let mut glm_aics: Vec<f64> = vec![ols_aic];
+  let mut glm_mses: Vec<f64> = vec![ols_model.mse(&x_test_model, &y_test)];
+  let mut glm_min_prices: Vec<f64> = vec![ols_season_prices.fold(f64::INFINITY, |a, b| a.min(*b))];
+
+  let mut traces: Vec<Box<dyn Trace>> = vec![
+    Scatter::new(x_values.column(0).to_vec(), y_values.column(0).to_vec())
+      .mode(plotly::common::Mode::Markers)
+      .name("Observed"),
+    Scatter::new(season_days.column(0).to_vec(), ols_season_prices.column(0).to_vec())
+      .mode(plotly::common::Mode::Lines)
+      .name("OLS (Matrix Math)"),
+  ];
+
+  for (family, link) in [
+    (Family::Gamma, Link::Log),
+    (Family::LogNormal, Link::Identity),
+    (Family::Poisson, Link::Log),
+    (Family::InverseGaussian, Link::Log),
+  ] {
+    let mut glm = GeneralizedLinearModel::new(family, link);
+    glm.fit_polynomial(&x_train, &y_train, glm_degree)?;
+    let season_prices = glm.predict_polynomial(&season_days, glm_degree);
+
+    glm_names.push(glm.name());
+    glm_parameters.push(RegressionModel::β_to_string(glm.β.column(0).to_vec()));
+    glm_deviances.push(glm.deviance);
+    glm_aics.push(glm.aic);
+    glm_mses.push(glm.mse(&x_test_model, &y_test));
+    glm_min_prices.push(season_prices.fold(f64::INFINITY, |a, b| a.min(*b)));
+
+    traces.push(
+      Scatter::new(season_days.column(0).to_vec(), season_prices.column(0).to_vec())
+        .mode(plotly::common::Mode::Lines)
+        .name(glm.name()),
+    );
+  }
+
+  let glm_results_df = DataFrame::new(vec![
+    Series::new("Model", glm_names),
+    Series::new(col_parameters, glm_parameters),
+    Series::new("Deviance", glm_deviances),
+    Series::new("AIC", glm_aics),
+    Series::new(col_mse, glm_mses),
+    Series::new("Min Predicted Price\n(days 200-365)", glm_min_prices),
+  ])?;
+
+  let layout = Layout::new()
+    .title(Title::new("Price vs Day of Year: OLS and GLMs"))
+    .x_axis(Axis::new().title(Title::new("Day of Year")))
+    .y_axis(Axis::new().title(Title::new("Price")));
+
+  article_elements.push(html! {
+    h2 { "Generalized Linear Models" }
+    p { (format!("Polynomials of degree {glm_degree} fitted by IRLS on the 80% training split. The deviance of OLS is its RSS.")) }
+    ( html_dataframe(&glm_results_df, None)? )
+    ( html_plot_figure(traces, &layout, "Predicted mean price over the whole season.")? )
+  });
+
+  // Sparse one-hot design matrix over every bushel pumpkin
+  let categorical_columns = ["City Name", "Package", "Variety", "Item Size"];
+  let sparse_pumpkins = pumpkins
+    .lazy()
+    .select([
+      col("City Name"),
+      col("Package"),
+      col("Variety"),
+      col("Item Size"),
+      (col("DayOfYear") / lit(365.0)).alias("DayOfYear / 365"),
+      col("Price"),
+    ])
+    .drop_nulls(Some(vec![col("DayOfYear / 365"), col("Price")]))
+    .collect()?;
+
+  let (sparse_x, sparse_column_names) = CsrMatrix::from_dataframe(
+    &sparse_pumpkins,
+    &["DayOfYear / 365"],
+    &categorical_columns,
+    true,
+  )?;
+  let sparse_y = sparse_pumpkins["Price"].f64()?.into_no_null_iter().collect::<Array1<f64>>();
+
+  let conjugate_gradient = solve_least_squares(&sparse_x, &sparse_y, &SolverOptions::default())?;
+  let gradient_descent = solve_gradient_descent(
+    &sparse_x,
+    &sparse_y,
+    &SolverOptions::builder().learning_rate(0.1).max_iterations(5_000).tolerance(1e-4).build(),
+  )?;
+
+  // The same solver on the dense polynomial design of the PIE TYPE model
+  let dense_x = RegressionModel::polyfit_data(&x_train, 1);
+  let dense_solution =
+    solve_least_squares(&dense_x, &y_train.column(0).to_owned(), &SolverOptions::default())?;
+
+  let sparse_r2 = |β: &Array1<f64>| {
+    let residuals = &sparse_y - &sparse_x.dot_vector(β);
+    let y_mean = sparse_y.mean().unwrap_or(0.0);
+    1.0 - residuals.dot(&residuals) / sparse_y.mapv(|y| (y - y_mean).powi(2)).sum()
+  };
+
+  let solvers_df = DataFrame::new(vec![
+    Series::new("Solver", &["Conjugate Gradient", "Gradient Descent", "Conjugate Gradient"]),
+    Series::new("Design", &["Sparse one-hot (all bushels)", "Sparse one-hot (all bushels)", "Dense polynomial (PIE TYPE)"]),
+    Series::new("Rows", &[sparse_x.nrows as u32, sparse_x.nrows as u32, dense_x.nrows() as u32]),
+    Series::new("Columns", &[sparse_x.ncols as u32, sparse_x.ncols as u32, dense_x.ncols() as u32]),
+    Series::new("Stored Values", &[sparse_x.nnz() as u32, sparse_x.nnz() as u32, dense_x.len() as u32]),
+    Series::new("Density", &[sparse_x.density(), sparse_x.density(), 1.0]),
+    Series::new("Iterations", &[conjugate_gradient.iterations as u32, gradient_descent.iterations as u32, dense_solution.iterations as u32]),
+    Series::new("Converged", &[conjugate_gradient.converged, gradient_descent.converged, dense_solution.converged]),
+    Series::new("r² (train)", &[sparse_r2(&conjugate_gradient.β), sparse_r2(&gradient_descent.β), ols_model_r2(&dense_x, &y_train, &dense_solution.β)]),
+  ])?;
+
+  let sparse_parameters_df = DataFrame::new(vec![
+    Series::new("Column", sparse_column_names),
+    Series::new("β (Conjugate Gradient)", conjugate_gradient.β.to_vec()),
+    Series::new("β (Gradient Descent)", gradient_descent.β.to_vec()),
+  ])?;
+
+  article_elements.push(html! {
+    h2 { "Sparse Design Matrices" }
+    p { (format!("One-hot encoding of {} stored in CSR format.", categorical_columns.join(", "))) }
+    ( html_dataframe(&solvers_df, None)? )
+    p { (format!("Dense polynomial solution for comparison: {}", RegressionModel::β_to_string(dense_solution.β.to_vec()))) }
+    h3 { "Parameters of the One-hot Model" }
+    ( html_dataframe(&sparse_parameters_df, None)? )
+  });
+
+  // Kernel ridge and Gaussian-process regression on the PIE TYPE prices
+  let price_variance = y_train.var(1.0);
+  let curve_days = Array2::from_shape_fn((131, 1), |(row, _)| 230.0 + row as f64);
+
+  let mut kernel_names: Vec<String> = vec!["Matrix Math (degree 1)".to_string()];
+  let mut linear_model = RegressionModel::new(x_train.clone(), y_train.clone(), 1.0);
+  linear_model.try_solve(1)?;
+  let mut kernel_hyperparameters: Vec<String> =
+    vec![RegressionModel::β_to_string(linear_model.β.column(0).to_vec())];
+  let mut kernel_noises: Vec<Option<f64>> = vec![None];
+  let mut kernel_likelihoods: Vec<Option<f64>> = vec![None];
+  let mut kernel_mses: Vec<f64> =
+    vec![linear_model.mse(&RegressionModel::polyfit_data(&x_test, 1), &y_test)];
+
+  let mut kernel_ridge = KernelRidge::new(
+    Kernel::Rbf { length_scale: 15.0, variance: 1.0 },
+    1.0,
+  );
+  kernel_ridge.fit(&x_train, &y_train)?;
+  kernel_names.push("Kernel Ridge (λ = 1)".to_string());
+  kernel_hyperparameters.push(kernel_ridge.kernel.name());
+  kernel_noises.push(None);
+  kernel_likelihoods.push(None);
+  kernel_mses.push({
+    let e = &y_test - &kernel_ridge.predict(&x_test);
+    e.iter().map(|e| e.powi(2)).sum::<f64>() / e.len() as f64
+  });
+
+  let mut traces: Vec<Box<dyn Trace>> = vec![
+    Scatter::new(x_values.column(0).to_vec(), y_values.column(0).to_vec())
+      .mode(plotly::common::Mode::Markers)
+      .name("Observed"),
+    Scatter::new(curve_days.column(0).to_vec(), kernel_ridge.predict(&curve_days).column(0).to_vec())
+      .mode(plotly::common::Mode::Lines)
+      .name("Kernel Ridge"),
+  ];
+
+  for (index, kernel) in [
+    Kernel::Rbf { length_scale: 15.0, variance: price_variance },
+    Kernel::Matern { length_scale: 15.0, variance: price_variance, smoothness: MaternSmoothness::FiveHalves },
+    Kernel::Periodic { length_scale: 1.0, period: 30.0, variance: price_variance },
+  ]
+  .into_iter()
+  .enumerate()
+  {
+    let mut gp = GaussianProcess::new(kernel, price_variance / 2.0);
+    gp.fit_optimized(&x_train, &y_train, 100)?;
+
+    kernel_names.push("Gaussian Process".to_string());
+    kernel_hyperparameters.push(gp.kernel.name());
+    kernel_noises.push(Some(gp.noise_variance));
+    kernel_likelihoods.push(Some(gp.log_marginal_likelihood));
+    kernel_mses.push(gp.mse(&x_test, &y_test));
+
+    // Draw the posterior mean ± 2 standard deviations of the first (RBF) process
+    if index == 0 {
+      let (mean, variance) = gp.predict(&curve_days);
+      let days = curve_days.column(0).to_vec();
+      let upper: Vec<f64> = mean.iter().zip(variance.iter()).map(|(m, v)| m + 2.0 * v.sqrt()).collect();
+      let lower: Vec<f64> = mean.iter().zip(variance.iter()).map(|(m, v)| m - 2.0 * v.sqrt()).collect();
+
+      traces.push(
+        Scatter::new(days.clone(), lower)
+          .mode(plotly::common::Mode::Lines)
+          .line(plotly::common::Line::new().width(0.0))
+          .show_legend(false)
+          .name("GP - 2σ"),
+      );
+      traces.push(
+        Scatter::new(days.clone(), upper)
+          .mode(plotly::common::Mode::Lines)
+          .line(plotly::common::Line::new().width(0.0))
+          .fill(plotly::common::Fill::ToNextY)
+          .name("GP ± 2σ"),
+      );
+      traces.push(
+        Scatter::new(days, mean.column(0).to_vec())
+          .mode(plotly::common::Mode::Lines)
+          .name("GP posterior mean (RBF)"),
+      );
+    }
+  }
+
+  let kernel_results_df = DataFrame::new(vec![
+    Series::new("Model", kernel_names),
+    Series::new("Kernel / Parameters", kernel_hyperparameters),
+    Series::new("Noise Variance σₙ²", kernel_noises),
+    Series::new("Log Marginal Likelihood", kernel_likelihoods),
+    Series::new(col_mse, kernel_mses),
+  ])?;
+
+  let layout = Layout::new()
+    .title(Title::new("Seasonal Price Curve"))
+    .x_axis(Axis::new().title(Title::new("Day of Year")))
+    .y_axis(Axis::new().title(Title::new("Price")));
+
+  article_elements.push(html! {
+    h2 { "Kernel Ridge and Gaussian-process Regression" }
+    p { "The Gaussian-process hyperparameters maximize the log marginal likelihood of the 80% training split." }
+    ( html_dataframe(&kernel_results_df, None)? )
+    ( html_plot_figure(traces, &layout, "Posterior mean and 95% band of the Gaussian process.")? )
+  });
+
+  // Isotonic regression: before Halloween (day 304) prices should only go up
+  let pre_halloween_pumpkins = regression_rows(&pie_pumpkins)?
+    .lazy()
+    .filter(col("DayOfYear").lt_eq(lit(304.0)))
+    .select([col("DayOfYear"), col("Price")])
+    .collect()?;
+  let pre_halloween_days = non_null_values(&pre_halloween_pumpkins, "DayOfYear")?;
+  let pre_halloween_prices = non_null_values(&pre_halloween_pumpkins, "Price")?;
+
+  let mut isotonic_monotonicity: Vec<&str> = Vec::new();
+  let mut isotonic_steps: Vec<u32> = Vec::new();
+  let mut isotonic_mse: Vec<f64> = Vec::new();
+  let mut isotonic_increasing = IsotonicRegression::new(Monotonicity::Increasing);
+
+  for monotonicity in [Monotonicity::Increasing, Monotonicity::Decreasing] {
+    let mut isotonic = IsotonicRegression::new(monotonicity);
+    isotonic.fit(&pre_halloween_days, &pre_halloween_prices, None)?;
+
+    let mut levels = isotonic.y_thresholds.clone();
+    levels.dedup();
+
+    isotonic_monotonicity.push(monotonicity.name());
+    isotonic_steps.push(levels.len() as u32);
+    isotonic_mse.push(isotonic.mse(&pre_halloween_days, &pre_halloween_prices));
+
+    if monotonicity == Monotonicity::Increasing {
+      isotonic_increasing = isotonic;
+    }
+  }
+
+  let isotonic_df = DataFrame::new(vec![
+    Series::new("Monotonicity", isotonic_monotonicity),
+    Series::new("Steps", isotonic_steps),
+    Series::new("MSE", isotonic_mse),
+  ])?;
+
+  article_elements.push(html! {
+    h2 { "Isotonic Regression" }
+    p { "PIE TYPE prices up to Halloween (day 304) fitted with a monotone step function (pool-adjacent-violators). Predictions interpolate linearly between the steps. Fewer steps and a higher MSE than the opposite direction mean the data does not follow the expected trend." }
+    ( html_dataframe(&isotonic_df, None)? )
+    ( isotonic_figure(
+      &pre_halloween_days,
+      &pre_halloween_prices,
+      &isotonic_increasing,
+      "Day of Year",
+      "Price",
+      "Increasing isotonic fit of the price before Halloween.",
+    )? )
+  });
+
+  // Nonlinear least squares: curves that are not linear in their parameters
+  let nonlinear_x = x_values.clone();
+  let nonlinear_days: Vec<f64> = (200..=365).map(f64::from).collect();
+  let nonlinear_x_curve = Array2::from_shape_vec((nonlinear_days.len(), 1), nonlinear_days.clone())?;
+
+  // Gaussian bump: f(day) = base + height exp(-(day - peak)² / (2 width²)), analytic Jacobian
+  let gaussian_bump = NonlinearModel::new(&["base", "height", "peak", "width"], |x, β| {
+    x.column(0)
+      .mapv(|day| β[0] + β[1] * (-(day - β[2]).powi(2) / (2.0 * β[3].powi(2))).exp())
+  })
+  .jacobian(|x, β| {
+    let mut jacobian = Array2::<f64>::zeros((x.nrows(), 4));
+    for (i, day) in x.column(0).iter().enumerate() {
+      let bump = (-(day - β[2]).powi(2) / (2.0 * β[3].powi(2))).exp();
+      jacobian[(i, 0)] = 1.0;
+      jacobian[(i, 1)] = bump;
+      jacobian[(i, 2)] = β[1] * bump * (day - β[2]) / β[3].powi(2);
+      jacobian[(i, 3)] = β[1] * bump * (day - β[2]).powi(2) / β[3].powi(3);
+    }
+    jacobian
+  });
+
+  // Logistic decay after October: f(day) = floor + drop / (1 + exp(rate (day - midpoint))),
+  // Jacobian by finite differences
+  let logistic_decay = NonlinearModel::new(&["floor", "drop", "rate", "midpoint"], |x, β| {
+    x.column(0)
+      .mapv(|day| β[0] + β[1] / (1.0 + (β[2] * (day - β[3])).exp()))
+  });
+
+  let mut nonlinear_traces: Vec<Box<dyn Trace>> = vec![Scatter::new(
+    nonlinear_x.column(0).to_vec(),
+    y_values.column(0).to_vec(),
   )
+  .mode(plotly::common::Mode::Markers)
+  .name("Prices")];
+  let mut nonlinear_results_df = DataFrame::new(vec![
+    Series::new_empty("Model", &DataType::Utf8),
+    Series::new_empty("Jacobian", &DataType::Utf8),
+    Series::new_empty("Parameters (± std. error)", &DataType::Utf8),
+    Series::new_empty("Iterations", &DataType::UInt32),
+    Series::new_empty("Status", &DataType::Utf8),
+    Series::new_empty(col_r2, &DataType::Float64),
+    Series::new_empty(col_mse, &DataType::Float64),
+  ])?;
+
+  for (name, nonlinear_model, initial_β) in [
+    ("Gaussian bump", &gaussian_bump, Array1::from_vec(vec![15.0, 5.0, 270.0, 20.0])),
+    ("Logistic decay", &logistic_decay, Array1::from_vec(vec![15.0, 5.0, 0.1, 305.0])),
+  ] {
+    let fit = nonlinear_model.fit(
+      &nonlinear_x,
+      &y_values,
+      &initial_β,
+      &LevenbergMarquardtOptions::new(),
+    )?;
+
+    nonlinear_results_df.vstack_mut(&DataFrame::new(vec![
+      Series::new("Model", &[name]),
+      Series::new(
+        "Jacobian",
+        &[if nonlinear_model.has_jacobian() { "Analytic" } else { "Finite differences" }],
+      ),
+      Series::new(
+        "Parameters (± std. error)",
+        &[fit.parameters_to_string(&nonlinear_model.parameter_names)],
+      ),
+      Series::new("Iterations", &[fit.iterations as u32]),
+      Series::new("Status", &[fit.status.name()]),
+      Series::new(col_r2, &[fit.r2(&y_values)]),
+      Series::new(col_mse, &[fit.mse()]),
+    ])?)?;
+
+    nonlinear_traces.push(
+      Scatter::new(
+        nonlinear_days.clone(),
+        nonlinear_model.predict(&nonlinear_x_curve, &fit.β).column(0).to_vec(),
+      )
+      .mode(plotly::common::Mode::Lines)
+      .name(name),
+    );
+  }
+
+  article_elements.push(html! {
+    h2 { "Nonlinear Least Squares" }
+    p { "Curves that are not linear in their parameters, fitted with the Levenberg–Marquardt algorithm on all PIE TYPE prices." }
+    ( html_dataframe(&nonlinear_results_df, None)? )
+    ( html_plot_figure(
+      nonlinear_traces,
+      &Layout::new()
+        .title(Title::new("Nonlinear Fits of Price vs Day of Year"))
+        .x_axis(Axis::new().title(Title::new("Day of Year")))
+        .y_axis(Axis::new().title(Title::new("Price"))),
+      "Gaussian bump and logistic decay fitted by Levenberg–Marquardt.",
+    )? )
+  });
+
+  Ok(Lesson3Article {
+    elements: article_elements.0,
+    record,
+  })
+}
+
+/// Gets the values of a numeric column without nulls, e.g. one of the `[regression_rows]`, or an
+/// error if it has a null.
+fn non_null_values(
+  df: &DataFrame,
+  column: &str,
+) -> GenericResult<Vec<f64>> {
+  df.column(column)?
+    .f64()?
+    .into_iter()
+    .map(|value| {
+      value.ok_or_else(|| GenericError::from(format!("\"{column}\" has a null value")).into())
+    })
+    .collect()
+}
+
+/// Calculates the coefficient of determination r² of the parameters β on a dense design.
+#[allow(non_snake_case)]
+fn ols_model_r2(
+  X: &Array2<f64>,
+  y: &Array2<f64>,
+  β: &Array1<f64>,
+) -> f64 {
+  let y = y.column(0);
+  let residuals = &y - &X.dot(β);
+  let y_mean = y.mean().unwrap_or(0.0);
+  1.0 - residuals.dot(&residuals) / y.mapv(|y| (y - y_mean).powi(2)).sum()
 }
@@ -1,4 +1,10 @@
 pub mod routes;
+pub mod state;
+pub mod sample_seed;
+pub mod datasets;
+pub mod exports;
 pub mod index;
 pub mod l2_prepare_data;
 pub mod l3_linear_regression;
+pub mod data_quality;
+pub mod profile;
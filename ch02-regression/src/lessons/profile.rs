@@ -0,0 +1,78 @@
+use axum::{extract::{Path, State}, http::StatusCode, response::IntoResponse, response::Response};
+use linear_regression::{
+  application_error::GenericResult, html_dataframe::html_dataframe,
+  html_plot_figure::html_plot_figure,
+};
+use linear_regression::data_profile::{DataProfile, ProfileOptions};
+use linear_regression::partials::create_html_notebook;
+use linear_regression::provenance::Provenance;
+use maud::{html, PreEscaped};
+
+use crate::lessons::state::AppState;
+
+/// Gets the profiling report of a dataset: a summary per column with a thumbnail of its values,
+/// the correlations of the numeric columns, and the duplicated rows.
+///
+pub async fn get_profile(
+  State(state): State<AppState>,
+  Path(dataset): Path<String>,
+) -> GenericResult<Response> {
+  if state.find_dataset(&dataset)?.is_none() {
+    return Ok((StatusCode::NOT_FOUND, format!("Unknown dataset \"{dataset}\"")).into_response());
+  }
+  let (df, dataset_provenance) = state.load_with_provenance(&dataset)?;
+
+  let options = ProfileOptions::default();
+  let profile = DataProfile::new(&df, &options)?;
+
+  let mut article_elements: Vec<PreEscaped<String>> = Vec::new();
+
+  article_elements.push(html! {
+    h2 { "1. Overview" }
+    p {
+      "Rows: " (profile.rows) ", columns: " (profile.columns.len())
+      ", duplicated rows: " (profile.duplicate_rows) "."
+    }
+    h3 { "Summary per column" }
+    ( html_dataframe(&profile.summary()?, None)? )
+  });
+
+  let mut thumbnails: Vec<PreEscaped<String>> = Vec::new();
+  for column in &profile.columns {
+    if let Some((traces, layout)) = column.thumbnail(&options) {
+      let caption = match column.numeric {
+        Some(_) => format!("Histogram of {}.", column.name),
+        None => format!("Most frequent values of {}.", column.name),
+      };
+      thumbnails.push(html_plot_figure(traces, &layout, &caption)?);
+    }
+  }
+
+  article_elements.push(html! {
+    h2 { "2. Distribution of every column" }
+    @for thumbnail in &thumbnails {
+      (thumbnail)
+    }
+  });
+
+  let (traces, layout) = profile.correlation_heat_map();
+  article_elements.push(html! {
+    h2 { "3. Correlations" }
+    ( html_dataframe(&profile.correlation_table()?, None)? )
+    ( html_plot_figure(traces, &layout, "Pearson correlation of the numeric columns, over the rows where both have values.")? )
+  });
+
+  article_elements.push(html! {
+    h2 { "4. Duplicated rows" }
+    @if profile.duplicate_rows == 0 {
+      p { "No row is duplicated." }
+    } @else {
+      p { (profile.duplicate_rows) " rows repeat an earlier row. Examples, every copy included:" }
+      ( html_dataframe(&profile.duplicate_examples, None)? )
+    }
+  });
+
+  let provenance = Provenance::new().dataset(dataset_provenance).steps(&["DataProfile::new"]);
+
+  Ok((StatusCode::OK, create_html_notebook(&format!("Profile: {dataset}"), article_elements, &provenance)?).into_response())
+}
@@ -1,20 +1,39 @@
+use crate::lessons::data_quality::get_data_quality;
+use crate::lessons::datasets::{get_datasets, post_dataset_upload, UPLOAD_LIMIT_BYTES};
+use crate::lessons::exports::get_lesson_3_export;
 use crate::lessons::index::get_index;
 use crate::lessons::l2_prepare_data::get_lesson_2;
 use crate::lessons::l3_linear_regression::get_lesson_3;
-use axum::{routing::get, Router};
+use crate::lessons::profile::get_profile;
+use crate::lessons::sample_seed::override_sample_seed;
+use crate::lessons::state::AppState;
+use axum::{extract::DefaultBodyLimit, middleware, routing::get, Router};
 use tower_http::services::ServeDir;
 
 /// Creates the lesson routes of the web application.
 ///
 /// # Arguments
 ///
-/// * `config`: Configuration of the web service.
-pub fn lesson_routes() -> Router {
+/// * `state`: State shared by the handlers, e.g. the dataset registry.
+///
+/// Every page takes an optional `seed` query parameter for its shuffled samples.
+pub fn lesson_routes(state: AppState) -> Router {
   let app = Router::new();
 
   app
     .route("/", get(get_index))
     .route("/lesson-2", get(get_lesson_2))
     .route("/lesson-3", get(get_lesson_3))
-    .nest_service("/public", ServeDir::new("public"))
+    .route("/lesson-3/export/:file", get(get_lesson_3_export))
+    .route("/data-quality", get(get_data_quality))
+    .route("/profile/:dataset", get(get_profile))
+    .route(
+      "/datasets",
+      get(get_datasets)
+        .post(post_dataset_upload)
+        .layer(DefaultBodyLimit::max(UPLOAD_LIMIT_BYTES)),
+    )
+    .nest_service("/public", ServeDir::new(concat!(env!("CARGO_MANIFEST_DIR"), "/public")))
+    .layer(middleware::from_fn(override_sample_seed))
+    .with_state(state)
 }
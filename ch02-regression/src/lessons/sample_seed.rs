@@ -0,0 +1,30 @@
+use std::collections::HashMap;
+
+use axum::extract::Query;
+use axum::http::{Request, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use linear_regression::display_options::with_request_sample_seed;
+
+/// Name of the query parameter that overrides the seed of the shuffled samples of a page,
+/// e.g. `/lesson-3?seed=7`.
+pub const SAMPLE_SEED_PARAMETER: &str = "seed";
+
+/// Handles a request with the seed of its `seed` query parameter, if any, so the samples of a
+/// page can be reproduced with another seed than the one of the process.
+///
+pub async fn override_sample_seed<B>(
+  Query(parameters): Query<HashMap<String, String>>,
+  request: Request<B>,
+  next: Next<B>,
+) -> Response {
+  let sample_seed = match parameters.get(SAMPLE_SEED_PARAMETER).map(|seed| seed.parse::<u64>()) {
+    None => None,
+    Some(Ok(seed)) => Some(seed),
+    Some(Err(_)) => {
+      return (StatusCode::BAD_REQUEST, format!("The {SAMPLE_SEED_PARAMETER} must be an unsigned integer")).into_response()
+    }
+  };
+
+  with_request_sample_seed(sample_seed, next.run(request)).await
+}
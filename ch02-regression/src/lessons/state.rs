@@ -0,0 +1,157 @@
+use std::sync::{Arc, RwLock};
+
+use linear_regression::application_error::{ApplicationError, GenericError, GenericResult};
+use linear_regression::dataframe_cache::{DataFrameCache, FileFingerprint};
+use linear_regression::dataset_registry::{
+  save_upload, DatasetEntry, DatasetRegistry, UploadError,
+};
+use linear_regression::display_options::{with_sample_seed, DisplayOptions};
+use linear_regression::provenance::DatasetProvenance;
+use linear_regression::pumpkins::{prepare, PreparationOptions, PreparedPumpkins, PUMPKINS_DATASET};
+use polars::prelude::*;
+
+/// Represents the state shared by the handlers of the web application.
+#[derive(Clone)]
+pub struct AppState {
+  /// Registry of the datasets, shared with the upload handler.
+  pub registry: Arc<RwLock<DatasetRegistry>>,
+  /// Cache of the loaded and prepared dataframes.
+  pub cache: Arc<DataFrameCache>,
+}
+
+impl AppState {
+  /// Creates a new instance of `[AppState]`.
+  pub fn new(registry: DatasetRegistry) -> Self {
+    Self {
+      registry: Arc::new(RwLock::new(registry)),
+      cache: Arc::new(DataFrameCache::new()),
+    }
+  }
+
+  /// Loads a registered dataset, from the cache unless its file changed.
+  pub fn load(
+    &self,
+    name: &str,
+  ) -> GenericResult<DataFrame> {
+    let dataset = self.dataset(name)?;
+    self.cache.get_or_build(&dataset.path, "", || dataset.load())
+  }
+
+  /// Loads a registered dataset, like `[AppState::load]`, and records its provenance: the file,
+  /// the hash of its content and the shape of the loaded dataframe.
+  pub fn load_with_provenance(
+    &self,
+    name: &str,
+  ) -> GenericResult<(DataFrame, DatasetProvenance)> {
+    let dataset = self.dataset(name)?;
+    let df = self.cache.get_or_build(&dataset.path, "", || dataset.load())?;
+    let fingerprint = match self.cache.fingerprint(&dataset.path, "")? {
+      Some(fingerprint) => fingerprint,
+      None => FileFingerprint::read(&dataset.path)?,
+    };
+    let provenance = DatasetProvenance::new(&dataset, &fingerprint, &df);
+    Ok((df, provenance))
+  }
+
+  /// Gets the result of a preparation pipeline over a registered dataset, e.g. a dataframe or
+  /// the fitted models of a notebook, from the cache unless the file of the dataset changed.
+  ///
+  /// # Arguments
+  ///
+  /// * `name`: Name of the dataset.
+  /// * `pipeline`: Name of the pipeline with its parameters; the key of the result.
+  /// * `build`: Runs the pipeline, e.g. over `[AppState::load]`.
+  pub fn prepare<T: Clone + Send + Sync + 'static>(
+    &self,
+    name: &str,
+    pipeline: &str,
+    build: impl FnOnce() -> GenericResult<T>,
+  ) -> GenericResult<T> {
+    let dataset = self.dataset(name)?;
+    self.cache.get_or_build(&dataset.path, pipeline, build)
+  }
+
+  /// Runs a blocking computation, e.g. the fitting of the models of a notebook, on the blocking
+  /// threads of the runtime instead of a worker of the async handlers. The computation gets the
+  /// seed of the shuffled samples of the current request.
+  pub async fn run_blocking<T: Send + 'static>(
+    &self,
+    compute: impl FnOnce(&AppState) -> GenericResult<T> + Send + 'static,
+  ) -> GenericResult<T> {
+    let state = self.clone();
+    let sample_seed = DisplayOptions::current().sample_seed;
+    tokio::task::spawn_blocking(move || with_sample_seed(sample_seed, || compute(&state)))
+      .await
+      .map_err(|error| GenericError::from(format!("The computation failed: {error}")))?
+  }
+
+  /// Prepares the registered US pumpkins with `[prepare]`, from the cache unless the file of the
+  /// dataset changed. Every lesson starts from it.
+  pub fn prepare_pumpkins(
+    &self,
+    options: &PreparationOptions,
+  ) -> GenericResult<PreparedPumpkins> {
+    self.prepare(PUMPKINS_DATASET, &format!("pumpkins/prepare {options:?}"), || {
+      prepare(&self.load(PUMPKINS_DATASET)?, options)
+    })
+  }
+
+  /// Gets a registered dataset by name, or an error if it is not registered.
+  pub fn dataset(
+    &self,
+    name: &str,
+  ) -> GenericResult<DatasetEntry> {
+    Ok(self.read_registry()?.entry(name)?.clone())
+  }
+
+  /// Gets a registered dataset by name, if registered.
+  pub fn find_dataset(
+    &self,
+    name: &str,
+  ) -> GenericResult<Option<DatasetEntry>> {
+    Ok(self.read_registry()?.get(name).cloned())
+  }
+
+  /// Gets every registered dataset, sorted by name.
+  pub fn datasets(&self) -> GenericResult<Vec<DatasetEntry>> {
+    Ok(self.read_registry()?.entries().cloned().collect())
+  }
+
+  /// Validates, saves and registers an uploaded dataset. The file is parsed and saved on the
+  /// blocking threads without locking the registry, which is only locked to register it.
+  pub async fn upload_dataset(
+    &self,
+    name: String,
+    file_name: String,
+    content: Vec<u8>,
+  ) -> Result<DatasetEntry, UploadError> {
+    let upload_dir = {
+      let registry = self.read_registry()?;
+      registry.check_upload(&name)?;
+      registry.upload_dir.clone()
+    };
+
+    let upload = tokio::task::spawn_blocking(move || {
+      save_upload(&upload_dir, &name, &file_name, &content)
+    })
+    .await
+    .map_err(|error| GenericError::from(format!("The upload failed: {error}")))
+    .map_err(ApplicationError::from)??;
+
+    let mut registry = self
+      .registry
+      .write()
+      .map_err(|_| ApplicationError::from(GenericError::from("The dataset registry is poisoned")))?;
+    Ok(registry.register_upload(upload)?.clone())
+  }
+
+  /// Locks the registry for reading.
+  fn read_registry(&self) -> GenericResult<std::sync::RwLockReadGuard<'_, DatasetRegistry>> {
+    Ok(
+      self
+        .registry
+        .read()
+        .map_err(|_| GenericError::from("The dataset registry is poisoned"))?,
+    )
+  }
+}
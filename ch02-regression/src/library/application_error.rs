@@ -19,6 +19,8 @@ pub enum ApplicationError {
   LinfaError(linfa::error::Error),
   // An error from the Linfa linear library.
   LinfaLinearError(linfa_linear::LinearError<f64>),
+  // An error from the Linfa linear algebra library.
+  LinfaLinalgError(linfa_linalg::LinalgError),
   // An error from the SmartCore library.
   SmartCoreError(smartcore::error::Failed),
   // Shape error from the ndarray library.
@@ -38,6 +40,7 @@ impl std::error::Error for ApplicationError {
       Self::EnvVarError(err) => Some(err),
       Self::LinfaError(err) => Some(err),
       Self::LinfaLinearError(err) => Some(err),
+      Self::LinfaLinalgError(err) => Some(err),
       Self::NDArrayShapeError(err) => Some(err),
       Self::SmartCoreError(err) => Some(err),
       Self::CSVError(err) => Some(err),
@@ -58,6 +61,7 @@ impl std::fmt::Debug for ApplicationError {
       Self::EnvVarError(err) => write!(f, "Environment Variable Error: {:?}", err),
       Self::LinfaError(err) => write!(f, "Linfa Error: {:?}", err),
       Self::LinfaLinearError(err) => write!(f, "Linfa Linear Error: {:?}", err),
+      Self::LinfaLinalgError(err) => write!(f, "Linfa Linalg Error: {:?}", err),
       Self::NDArrayShapeError(err) => write!(f, "Ndarray Shape Error: {:?}", err),
       Self::CSVError(err) => write!(f, "CVS Library Error: {:?}", err),
       Self::SmartCoreError(err) => write!(f, "SmartCore Library Error: {:?}", err),
@@ -78,6 +82,7 @@ impl std::fmt::Display for ApplicationError {
       Self::EnvVarError(err) => write!(f, "Environment Variable Error: {}", err),
       Self::LinfaError(err) => write!(f, "Linfa Error: {}", err),
       Self::LinfaLinearError(err) => write!(f, "Linfa Error: {}", err),
+      Self::LinfaLinalgError(err) => write!(f, "Linfa Linalg Error: {}", err),
       Self::NDArrayShapeError(err) => write!(f, "Ndarray Shape Error: {:}", err),
       Self::CSVError(err) => write!(f, "CVS Error: {}", err),
       Self::SmartCoreError(err) => write!(f, "SmartCore Library Error: {}", err),
@@ -128,6 +133,12 @@ impl From<linfa_linear::LinearError<f64>> for ApplicationError {
   }
 }
 
+impl From<linfa_linalg::LinalgError> for ApplicationError {
+  fn from(value: linfa_linalg::LinalgError) -> Self {
+    Self::LinfaLinalgError(value)
+  }
+}
+
 impl From<ndarray::ShapeError> for ApplicationError {
   fn from(value: ndarray::ShapeError) -> Self {
     Self::NDArrayShapeError(value)
@@ -174,6 +185,9 @@ impl IntoResponse for ApplicationError {
       ApplicationError::LinfaLinearError(err) => {
         (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
       }
+      ApplicationError::LinfaLinalgError(err) => {
+        (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+      }
       ApplicationError::NDArrayShapeError(err) => {
         (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
       }
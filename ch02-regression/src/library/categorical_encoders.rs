@@ -0,0 +1,543 @@
+use std::collections::HashMap;
+
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Represents how an encoder handles a category that was not seen at fit time.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UnseenCategory {
+  /// Fails the transformation.
+  Error,
+  /// Encodes the category as null.
+  Null,
+  /// Encodes the category with the neutral value of the encoder: all-zero indicators for
+  /// one-hot, the given value for ordinal, the prior mean for target, and 0 for frequency.
+  Default,
+}
+
+/// Represents an encoder of a categorical column into numeric (Float64) columns. Encoders are
+/// fitted on training data and then transform any data, e.g. the test split. Null values are
+/// always encoded as null.
+pub trait CategoricalEncoder {
+  /// Learns the categories (and their statistics) of the column.
+  fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()>;
+
+  /// Replaces the column by its encoded columns, keeping the rest of the dataframe.
+  fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame>;
+
+  /// Fits the encoder and transforms the same data.
+  fn fit_transform(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    self.fit(df)?;
+    self.transform(df)
+  }
+}
+
+/// Represents a one-hot encoder: one indicator column per category, named `"Column=category"`.
+#[derive(Clone, Debug)]
+pub struct OneHotEncoder {
+  /// Name of the encoded column.
+  pub column: String,
+  /// Whether the first category (in sorted order) gets no column, for models with intercept.
+  pub drop_first: bool,
+  /// Handling of unseen categories.
+  pub unseen: UnseenCategory,
+  /// Categories seen at fit time, sorted.
+  pub categories: Vec<String>,
+}
+
+impl OneHotEncoder {
+  /// Creates a new unfitted one-hot encoder of a column.
+  pub fn new(column: &str) -> Self {
+    Self {
+      column: column.to_string(),
+      drop_first: false,
+      unseen: UnseenCategory::Error,
+      categories: Vec::new(),
+    }
+  }
+
+  /// Sets whether the first category gets no column.
+  pub fn drop_first(
+    mut self,
+    drop_first: bool,
+  ) -> Self {
+    self.drop_first = drop_first;
+    self
+  }
+
+  /// Sets the handling of unseen categories.
+  pub fn unseen(
+    mut self,
+    unseen: UnseenCategory,
+  ) -> Self {
+    self.unseen = unseen;
+    self
+  }
+
+  /// Gets the names of the encoded columns.
+  pub fn column_names(&self) -> Vec<String> {
+    self
+      .encoded_categories()
+      .iter()
+      .map(|category| format!("{}={}", self.column, category))
+      .collect()
+  }
+
+  /// Gets the categories that have an indicator column.
+  fn encoded_categories(&self) -> &[String] {
+    if self.drop_first && !self.categories.is_empty() {
+      &self.categories[1..]
+    } else {
+      &self.categories
+    }
+  }
+}
+
+impl CategoricalEncoder for OneHotEncoder {
+  fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()> {
+    self.categories = sorted_categories(df, &self.column)?;
+    Ok(())
+  }
+
+  fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let values = category_values(df, &self.column)?;
+    check_unseen(&values, &self.column, self.unseen, |value| {
+      self.categories.binary_search(&value.to_string()).is_ok()
+    })?;
+
+    let columns: Vec<Series> = self
+      .encoded_categories()
+      .iter()
+      .zip(self.column_names())
+      .map(|(category, name)| {
+        let indicators: Float64Chunked = values
+          .iter()
+          .map(|value| {
+            let value = (*value)?;
+            let seen = self.categories.binary_search(&value.to_string()).is_ok();
+            match (seen, self.unseen) {
+              (false, UnseenCategory::Null) => None,
+              _ => Some(if value == category { 1.0 } else { 0.0 }),
+            }
+          })
+          .collect();
+        let mut series = indicators.into_series();
+        series.rename(&name);
+        series
+      })
+      .collect();
+
+    replace_column(df, &self.column, columns)
+  }
+}
+
+/// Represents an ordinal encoder: every category becomes its position (0, 1, 2…) in an
+/// explicit order, e.g. Item Size: sml < med < lge < xlge < jbo, or else in sorted order.
+#[derive(Clone, Debug)]
+pub struct OrdinalEncoder {
+  /// Name of the encoded column.
+  pub column: String,
+  /// Explicit order of the categories, from lowest to highest.
+  pub order: Option<Vec<String>>,
+  /// Handling of unseen categories.
+  pub unseen: UnseenCategory,
+  /// Value of the unseen categories with `[UnseenCategory::Default]`.
+  pub unseen_value: f64,
+  /// Categories seen at fit time, in order.
+  pub categories: Vec<String>,
+}
+
+impl OrdinalEncoder {
+  /// Creates a new unfitted ordinal encoder of a column.
+  pub fn new(column: &str) -> Self {
+    Self {
+      column: column.to_string(),
+      order: None,
+      unseen: UnseenCategory::Error,
+      unseen_value: -1.0,
+      categories: Vec::new(),
+    }
+  }
+
+  /// Sets the explicit order of the categories. Fitting fails if the data has a category that
+  /// is not in the order.
+  pub fn order(
+    mut self,
+    order: &[&str],
+  ) -> Self {
+    self.order = Some(order.iter().map(|category| category.to_string()).collect());
+    self
+  }
+
+  /// Sets the handling of unseen categories.
+  pub fn unseen(
+    mut self,
+    unseen: UnseenCategory,
+  ) -> Self {
+    self.unseen = unseen;
+    self
+  }
+
+  /// Sets the value of the unseen categories with `[UnseenCategory::Default]`.
+  pub fn unseen_value(
+    mut self,
+    unseen_value: f64,
+  ) -> Self {
+    self.unseen_value = unseen_value;
+    self
+  }
+}
+
+impl CategoricalEncoder for OrdinalEncoder {
+  fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()> {
+    let seen = sorted_categories(df, &self.column)?;
+
+    self.categories = match &self.order {
+      Some(order) => {
+        if let Some(missing) = seen.iter().find(|category| !order.contains(category)) {
+          return Err(
+            GenericError::from(format!(
+              "The category \"{missing}\" of \"{}\" is not in the explicit order",
+              self.column
+            ))
+            .into(),
+          );
+        }
+        order.clone()
+      }
+      None => seen,
+    };
+
+    Ok(())
+  }
+
+  fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let values = category_values(df, &self.column)?;
+    let positions: HashMap<&str, f64> = self
+      .categories
+      .iter()
+      .enumerate()
+      .map(|(position, category)| (category.as_str(), position as f64))
+      .collect();
+
+    let encoded = encode_with(
+      &values,
+      &self.column,
+      self.unseen,
+      self.unseen_value,
+      |value| positions.get(value).copied(),
+    )?;
+
+    replace_column(df, &self.column, vec![encoded])
+  }
+}
+
+/// Represents a target encoder: every category becomes the mean target of its rows, shrunk
+/// towards the global mean: (n·mean + m·prior) / (n + m), where m is the smoothing.
+#[derive(Clone, Debug)]
+pub struct TargetEncoder {
+  /// Name of the encoded column.
+  pub column: String,
+  /// Name of the numeric target column.
+  pub target: String,
+  /// Smoothing m: the weight of the prior mean, in number of rows.
+  pub smoothing: f64,
+  /// Handling of unseen categories.
+  pub unseen: UnseenCategory,
+  /// Mean target of all the rows at fit time.
+  pub prior: f64,
+  /// Encoded value of every category seen at fit time.
+  pub encodings: HashMap<String, f64>,
+}
+
+impl TargetEncoder {
+  /// Creates a new unfitted target encoder of a column.
+  pub fn new(
+    column: &str,
+    target: &str,
+  ) -> Self {
+    Self {
+      column: column.to_string(),
+      target: target.to_string(),
+      smoothing: 10.0,
+      unseen: UnseenCategory::Default,
+      prior: 0.0,
+      encodings: HashMap::new(),
+    }
+  }
+
+  /// Sets the smoothing m, the weight of the prior mean in number of rows.
+  pub fn smoothing(
+    mut self,
+    smoothing: f64,
+  ) -> Self {
+    self.smoothing = smoothing;
+    self
+  }
+
+  /// Sets the handling of unseen categories.
+  pub fn unseen(
+    mut self,
+    unseen: UnseenCategory,
+  ) -> Self {
+    self.unseen = unseen;
+    self
+  }
+}
+
+impl CategoricalEncoder for TargetEncoder {
+  fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()> {
+    let values = category_values(df, &self.column)?;
+    let targets = df.column(&self.target)?.cast(&DataType::Float64)?;
+    let targets: Vec<Option<f64>> = targets.f64()?.into_iter().collect();
+
+    let observed: Vec<(&str, f64)> = values
+      .iter()
+      .zip(targets.iter())
+      .filter_map(|(value, target)| Some(((*value)?, (*target)?)))
+      .collect();
+    if observed.is_empty() {
+      return Err(
+        GenericError::from(format!(
+          "\"{}\" has no rows with a target value",
+          self.column
+        ))
+        .into(),
+      );
+    }
+
+    self.prior =
+      observed.iter().map(|(_, target)| target).sum::<f64>() / observed.len() as f64;
+
+    let mut sums: HashMap<&str, (f64, f64)> = HashMap::new();
+    for (value, target) in &observed {
+      let entry = sums.entry(value).or_insert((0.0, 0.0));
+      entry.0 += target;
+      entry.1 += 1.0;
+    }
+
+    self.encodings = sums
+      .into_iter()
+      .map(|(category, (sum, count))| {
+        let encoding = (sum + self.smoothing * self.prior) / (count + self.smoothing);
+        (category.to_string(), encoding)
+      })
+      .collect();
+
+    Ok(())
+  }
+
+  fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let values = category_values(df, &self.column)?;
+    let encoded = encode_with(&values, &self.column, self.unseen, self.prior, |value| {
+      self.encodings.get(value).copied()
+    })?;
+
+    replace_column(df, &self.column, vec![encoded])
+  }
+}
+
+/// Represents a frequency encoder: every category becomes the fraction of the fitted rows
+/// that have it.
+#[derive(Clone, Debug)]
+pub struct FrequencyEncoder {
+  /// Name of the encoded column.
+  pub column: String,
+  /// Handling of unseen categories.
+  pub unseen: UnseenCategory,
+  /// Fraction of the rows of every category seen at fit time.
+  pub frequencies: HashMap<String, f64>,
+}
+
+impl FrequencyEncoder {
+  /// Creates a new unfitted frequency encoder of a column.
+  pub fn new(column: &str) -> Self {
+    Self {
+      column: column.to_string(),
+      unseen: UnseenCategory::Default,
+      frequencies: HashMap::new(),
+    }
+  }
+
+  /// Sets the handling of unseen categories.
+  pub fn unseen(
+    mut self,
+    unseen: UnseenCategory,
+  ) -> Self {
+    self.unseen = unseen;
+    self
+  }
+}
+
+impl CategoricalEncoder for FrequencyEncoder {
+  fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()> {
+    let values = category_values(df, &self.column)?;
+    let total = values.len().max(1) as f64;
+
+    let mut counts: HashMap<&str, f64> = HashMap::new();
+    for value in values.iter().flatten() {
+      *counts.entry(value).or_insert(0.0) += 1.0;
+    }
+
+    self.frequencies = counts
+      .into_iter()
+      .map(|(category, count)| (category.to_string(), count / total))
+      .collect();
+
+    Ok(())
+  }
+
+  fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let values = category_values(df, &self.column)?;
+    let encoded = encode_with(&values, &self.column, self.unseen, 0.0, |value| {
+      self.frequencies.get(value).copied()
+    })?;
+
+    replace_column(df, &self.column, vec![encoded])
+  }
+}
+
+/// Gets the values of a categorical column as text.
+fn category_values<'a>(
+  df: &'a DataFrame,
+  column: &str,
+) -> GenericResult<Vec<Option<&'a str>>> {
+  let series = df.column(column)?;
+  if series.dtype() != &DataType::Utf8 {
+    return Err(
+      GenericError::from(format!(
+        "The categorical column \"{column}\" must be text but it is {}",
+        series.dtype()
+      ))
+      .into(),
+    );
+  }
+  Ok(series.utf8()?.into_iter().collect())
+}
+
+/// Gets the distinct non-null categories of a column, sorted.
+fn sorted_categories(
+  df: &DataFrame,
+  column: &str,
+) -> GenericResult<Vec<String>> {
+  let mut categories: Vec<String> = category_values(df, column)?
+    .into_iter()
+    .flatten()
+    .map(|category| category.to_string())
+    .collect();
+  categories.sort_unstable();
+  categories.dedup();
+  Ok(categories)
+}
+
+/// Fails when a value was not seen at fit time and unseen categories are errors.
+fn check_unseen(
+  values: &[Option<&str>],
+  column: &str,
+  unseen: UnseenCategory,
+  is_seen: impl Fn(&str) -> bool,
+) -> GenericResult<()> {
+  if unseen != UnseenCategory::Error {
+    return Ok(());
+  }
+
+  match values.iter().flatten().find(|value| !is_seen(value)) {
+    Some(value) => Err(
+      GenericError::from(format!(
+        "The category \"{value}\" of \"{column}\" was not seen at fit time"
+      ))
+      .into(),
+    ),
+    None => Ok(()),
+  }
+}
+
+/// Encodes every value with a lookup, handling the unseen categories.
+///
+/// # Arguments
+///
+/// * `values`: Values of the column.
+/// * `column`: Name of the column, also the name of the encoded series.
+/// * `unseen`: Handling of unseen categories.
+/// * `default`: Value of the unseen categories with `[UnseenCategory::Default]`.
+/// * `lookup`: Encoded value of a category, `None` when it was not seen at fit time.
+fn encode_with(
+  values: &[Option<&str>],
+  column: &str,
+  unseen: UnseenCategory,
+  default: f64,
+  lookup: impl Fn(&str) -> Option<f64>,
+) -> GenericResult<Series> {
+  check_unseen(values, column, unseen, |value| lookup(value).is_some())?;
+
+  let encoded: Float64Chunked = values
+    .iter()
+    .map(|value| {
+      let value = (*value)?;
+      match (lookup(value), unseen) {
+        (Some(encoding), _) => Some(encoding),
+        (None, UnseenCategory::Default) => Some(default),
+        (None, _) => None,
+      }
+    })
+    .collect();
+
+  let mut series = encoded.into_series();
+  series.rename(column);
+  Ok(series)
+}
+
+/// Replaces a column of a dataframe by the given columns, at the same position.
+fn replace_column(
+  df: &DataFrame,
+  column: &str,
+  columns: Vec<Series>,
+) -> GenericResult<DataFrame> {
+  let position = df
+    .get_column_names()
+    .iter()
+    .position(|name| *name == column)
+    .ok_or_else(|| {
+      GenericError::from(format!("The column \"{column}\" does not exist"))
+    })?;
+
+  let mut result: Vec<Series> = df.get_columns()[..position].to_vec();
+  result.extend(columns);
+  result.extend_from_slice(&df.get_columns()[position + 1..]);
+
+  Ok(DataFrame::new(result)?)
+}
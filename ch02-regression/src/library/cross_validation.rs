@@ -0,0 +1,205 @@
+use ndarray::{Array1, Array2, Axis};
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+
+use crate::application_error::GenericResult;
+
+/// Represents the row indexes of one cross-validation fold.
+#[derive(Clone, Debug)]
+pub struct Fold {
+  /// Indexes of the rows used for training the model.
+  pub train_indexes: Vec<usize>,
+  /// Indexes of the rows used for validating the model.
+  pub validation_indexes: Vec<usize>,
+}
+
+/// Creates the folds for a k-fold cross-validation over `n_rows` rows.
+///
+/// The rows are assigned to the folds in sequential order, so every fold holds a contiguous
+/// block of rows and the first `n_rows % k` folds have one extra row.
+///
+/// # Arguments
+///
+/// * `n_rows`: Number of rows of the dataset.
+/// * `k`: Number of folds. It is clamped to the range `[2, n_rows]`.
+pub fn k_fold(
+  n_rows: usize,
+  k: usize,
+) -> Vec<Fold> {
+  let indexes: Vec<usize> = (0..n_rows).collect();
+  k_fold_from_indexes(&indexes, k)
+}
+
+/// Creates the folds for a k-fold cross-validation over `n_rows` rows shuffled with the
+/// given seed, so the same seed always produces the same folds.
+///
+/// # Arguments
+///
+/// * `n_rows`: Number of rows of the dataset.
+/// * `k`: Number of folds. It is clamped to the range `[2, n_rows]`.
+/// * `seed`: Seed of the random number generator used to shuffle the rows.
+pub fn shuffled_k_fold(
+  n_rows: usize,
+  k: usize,
+  seed: u64,
+) -> Vec<Fold> {
+  let mut indexes: Vec<usize> = (0..n_rows).collect();
+  indexes.shuffle(&mut StdRng::seed_from_u64(seed));
+  k_fold_from_indexes(&indexes, k)
+}
+
+/// Creates the folds for a k-fold cross-validation over the given row indexes.
+///
+/// # Arguments
+///
+/// * `indexes`: Row indexes in the order they will be assigned to the folds.
+/// * `k`: Number of folds. It is clamped to the range `[2, indexes.len()]`.
+pub fn k_fold_from_indexes(
+  indexes: &[usize],
+  k: usize,
+) -> Vec<Fold> {
+  let n_rows = indexes.len();
+  let k = k.clamp(2, n_rows.max(2));
+
+  let mut folds: Vec<Fold> = Vec::with_capacity(k);
+  let mut start: usize = 0;
+
+  for fold_index in 0..k {
+    // Distribute the remaining rows among the first folds
+    let fold_size = n_rows / k + usize::from(fold_index < n_rows % k);
+    let end = (start + fold_size).min(n_rows);
+
+    folds.push(Fold {
+      train_indexes: [&indexes[..start], &indexes[end..]].concat(),
+      validation_indexes: indexes[start..end].to_vec(),
+    });
+
+    start = end;
+  }
+
+  folds
+}
+
+/// Represents the row indexes of a train/test split, in the order the model sees the rows.
+#[derive(Clone, Debug)]
+pub struct TrainTestSplit {
+  /// Indexes of the rows used for training the model.
+  pub train_indexes: Vec<usize>,
+  /// Indexes of the rows used for testing the model.
+  pub test_indexes: Vec<usize>,
+}
+
+impl TrainTestSplit {
+  /// Creates the sequential split of linfa's `split_with_ratio` and `[RegressionModel]`: the
+  /// first `ceil(n_rows * train_ratio)` rows for training and the rest for testing.
+  ///
+  /// # Arguments
+  ///
+  /// * `n_rows`: Number of rows of the dataset.
+  /// * `train_ratio`: Ratio of the rows used for training, e.g. 0.8.
+  ///
+  /// [RegressionModel]: crate::regression_functions::RegressionModel
+  pub fn sequential(
+    n_rows: usize,
+    train_ratio: f32,
+  ) -> Self {
+    let split_index = ((n_rows as f32 * train_ratio).ceil() as usize).min(n_rows);
+    Self {
+      train_indexes: (0..split_index).collect(),
+      test_indexes: (split_index..n_rows).collect(),
+    }
+  }
+
+  /// Creates the split of smartcore's `train_test_split` with shuffling, by splitting the row
+  /// indexes themselves with it, so the rows are the same as the ones of the model.
+  ///
+  /// # Arguments
+  ///
+  /// * `n_rows`: Number of rows of the dataset; at least enough for one test row.
+  /// * `test_size`: Ratio of the rows used for testing, e.g. 0.2.
+  /// * `seed`: Seed given to `train_test_split`.
+  pub fn smartcore(
+    n_rows: usize,
+    test_size: f32,
+    seed: u64,
+  ) -> Self {
+    let indexes: Vec<f64> = (0..n_rows).map(|index| index as f64).collect();
+    let x = Array1::from(indexes.clone()).insert_axis(Axis(1));
+    let (_, _, train, test) = smartcore::model_selection::train_test_split(
+      &x,
+      &indexes,
+      test_size,
+      true,
+      Some(seed),
+    );
+    Self {
+      train_indexes: train.into_iter().map(|index| index as usize).collect(),
+      test_indexes: test.into_iter().map(|index| index as usize).collect(),
+    }
+  }
+
+  /// Gets the rows of a dataframe as the model sees them: the training rows and then the test
+  /// rows, with a `Row` column (index in the dataframe) and a `Split` column (`"train"` or
+  /// `"test"`) first.
+  ///
+  /// # Arguments
+  ///
+  /// * `df`: Dataframe whose rows were split; its height must be the `n_rows` of the split.
+  pub fn label_rows(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let indexes: Vec<u32> = self
+      .train_indexes
+      .iter()
+      .chain(&self.test_indexes)
+      .map(|index| *index as u32)
+      .collect();
+    let splits: Vec<&str> = [
+      vec!["train"; self.train_indexes.len()],
+      vec!["test"; self.test_indexes.len()],
+    ]
+    .concat();
+
+    let rows = df.take(&IdxCa::from_vec("Row", indexes.clone()))?;
+    let mut columns = vec![Series::new("Row", indexes), Series::new("Split", splits)];
+    columns.extend(rows.get_columns().iter().cloned());
+    Ok(DataFrame::new(columns)?)
+  }
+}
+
+/// Selects the given rows of a matrix.
+///
+/// # Arguments
+///
+/// * `source_data`: Matrix from which the rows are taken.
+/// * `indexes`: Indexes of the rows to select.
+pub fn select_rows(
+  source_data: &Array2<f64>,
+  indexes: &[usize],
+) -> Array2<f64> {
+  source_data.select(Axis(0), indexes)
+}
+
+/// Calculates the mean and the sample standard deviation of the given values.
+pub fn mean_std(values: &[f64]) -> (f64, f64) {
+  if values.is_empty() {
+    return (f64::NAN, f64::NAN);
+  }
+
+  let n = values.len() as f64;
+  let mean = values.iter().sum::<f64>() / n;
+
+  if values.len() < 2 {
+    return (mean, 0.0);
+  }
+
+  let variance = values
+    .iter()
+    .fold(0.0_f64, |sum, value| sum + (value - mean).powi(2))
+    / (n - 1.0);
+
+  (mean, variance.sqrt())
+}
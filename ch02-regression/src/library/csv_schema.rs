@@ -0,0 +1,435 @@
+use std::fmt;
+use std::sync::Arc;
+
+use polars::export::chrono::NaiveDate;
+use polars::prelude::*;
+
+use crate::application_error::GenericResult;
+
+/// Prefix of the names given to the columns whose header is empty, e.g. `"Unnamed: 24"`.
+pub const UNNAMED_PREFIX: &str = "Unnamed: ";
+
+/// Represents the declaration of a CSV column.
+#[derive(Clone, Debug)]
+pub struct ColumnSchema {
+  /// Name of the column. A name starting with `[UNNAMED_PREFIX]` expects an empty header.
+  pub name: String,
+  /// Data type of the loaded column; the text values are cast to it.
+  pub dtype: DataType,
+  /// Whether the column may have missing values.
+  pub nullable: bool,
+  /// Values the column may have, if restricted.
+  pub allowed_values: Option<Vec<String>>,
+  /// Inclusive range of a numeric column, if restricted.
+  pub range: Option<(f64, f64)>,
+  /// Format a text date column must have, e.g. `"%m/%d/%y"`.
+  pub date_format: Option<String>,
+}
+
+impl ColumnSchema {
+  /// Creates the declaration of a nullable column without restrictions.
+  pub fn new(
+    name: &str,
+    dtype: DataType,
+  ) -> Self {
+    Self {
+      name: name.to_string(),
+      dtype,
+      nullable: true,
+      allowed_values: None,
+      range: None,
+      date_format: None,
+    }
+  }
+
+  /// Declares that the column has no missing values.
+  pub fn required(mut self) -> Self {
+    self.nullable = false;
+    self
+  }
+
+  /// Restricts the values of the column.
+  pub fn allowed_values(
+    mut self,
+    values: &[&str],
+  ) -> Self {
+    self.allowed_values = Some(values.iter().map(|value| value.to_string()).collect());
+    self
+  }
+
+  /// Restricts a numeric column to an inclusive range.
+  pub fn range(
+    mut self,
+    min: f64,
+    max: f64,
+  ) -> Self {
+    self.range = Some((min, max));
+    self
+  }
+
+  /// Requires a text column to hold dates of a format.
+  pub fn date_format(
+    mut self,
+    format: &str,
+  ) -> Self {
+    self.date_format = Some(format.to_string());
+    self
+  }
+
+  /// Gets the header expected in the file: empty for the unnamed columns.
+  pub fn header(&self) -> &str {
+    if self.name.starts_with(UNNAMED_PREFIX) {
+      ""
+    } else {
+      &self.name
+    }
+  }
+
+  /// Checks a present value and its cast to the declared type.
+  fn check(
+    &self,
+    value: &str,
+    cast: AnyValue,
+  ) -> Option<Violation> {
+    if matches!(cast, AnyValue::Null) {
+      return Some(Violation::Type);
+    }
+    if let Some(allowed_values) = &self.allowed_values {
+      if !allowed_values.iter().any(|allowed| allowed == value) {
+        return Some(Violation::NotAllowed);
+      }
+    }
+    if let Some((min, max)) = self.range {
+      let number = cast.extract::<f64>()?;
+      if number < min || number > max {
+        return Some(Violation::OutOfRange);
+      }
+    }
+    if let Some(format) = &self.date_format {
+      if NaiveDate::parse_from_str(value, format).is_err() {
+        return Some(Violation::DateFormat);
+      }
+    }
+    None
+  }
+}
+
+/// Represents a rule broken by a value of the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Violation {
+  /// The header has other columns than the declared ones.
+  Header,
+  /// A required value is missing.
+  Missing,
+  /// The value cannot be read as the declared type.
+  Type,
+  /// The value is not one of the allowed values.
+  NotAllowed,
+  /// The numeric value is out of the declared range.
+  OutOfRange,
+  /// The text does not have the declared date format.
+  DateFormat,
+}
+
+impl fmt::Display for Violation {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let name = match self {
+      Self::Header => "Unexpected header",
+      Self::Missing => "Missing value",
+      Self::Type => "Invalid type",
+      Self::NotAllowed => "Value not allowed",
+      Self::OutOfRange => "Out of range",
+      Self::DateFormat => "Invalid date",
+    };
+    write!(f, "{name}")
+  }
+}
+
+/// Represents a CSV file loaded with a schema.
+#[derive(Clone, Debug)]
+pub struct ValidatedCsv {
+  /// Loaded data with the declared names and types. Values that break the type, the allowed
+  /// values or the range are null.
+  pub df: DataFrame,
+  /// Violations found, one per row: Line (where the record starts in the file, 1 is the
+  /// header), Row (of the data, from 0), Column, Value and Violation.
+  pub report: DataFrame,
+}
+
+impl ValidatedCsv {
+  /// Whether the file follows the schema.
+  pub fn is_valid(&self) -> bool {
+    self.report.height() == 0
+  }
+
+  /// Counts the violations per column and kind, with the columns Column, Violation and Count.
+  pub fn summary(&self) -> GenericResult<DataFrame> {
+    Ok(
+      self
+        .report
+        .clone()
+        .lazy()
+        .groupby_stable([col("Column"), col("Violation")])
+        .agg([count().alias("Count")])
+        .collect()?,
+    )
+  }
+}
+
+/// Represents the declared schema of a CSV file with a header.
+#[derive(Clone, Debug)]
+pub struct CsvSchema {
+  /// Declarations of the columns, in the order of the file.
+  pub columns: Vec<ColumnSchema>,
+}
+
+impl CsvSchema {
+  /// Creates a new schema from the declarations of the columns in file order.
+  pub fn new(columns: Vec<ColumnSchema>) -> Self {
+    Self { columns }
+  }
+
+  /// Describes the schema as a dataframe with the columns Column, Type, Nullable, Allowed
+  /// Values, Range and Date Format.
+  pub fn describe(&self) -> GenericResult<DataFrame> {
+    let columns = &self.columns;
+    Ok(df!(
+      "Column" => columns.iter().map(|c| c.name.clone()).collect::<Vec<String>>(),
+      "Type" => columns.iter().map(|c| c.dtype.to_string()).collect::<Vec<String>>(),
+      "Nullable" => columns.iter().map(|c| c.nullable).collect::<Vec<bool>>(),
+      "Allowed Values" => columns
+        .iter()
+        .map(|c| c.allowed_values.as_ref().map(|values| values.join(", ")))
+        .collect::<Vec<Option<String>>>(),
+      "Range" => columns
+        .iter()
+        .map(|c| c.range.map(|(min, max)| format!("[{min}, {max}]")))
+        .collect::<Vec<Option<String>>>(),
+      "Date Format" => columns
+        .iter()
+        .map(|c| c.date_format.clone())
+        .collect::<Vec<Option<String>>>(),
+    )?)
+  }
+
+  /// Conforms a dataframe read from a typed format (Parquet, Arrow IPC, JSON) to the schema:
+  /// the declared columns in order, cast to their types, and null for the missing ones.
+  pub fn conform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let mut columns: Vec<Series> = Vec::with_capacity(self.columns.len());
+    for column in &self.columns {
+      columns.push(match df.column(&column.name) {
+        Ok(series) => series.cast(&column.dtype)?,
+        Err(_) => Series::full_null(&column.name, df.height(), &column.dtype),
+      });
+    }
+    Ok(DataFrame::new(columns)?)
+  }
+
+  /// Loads a CSV file with the schema. Every value is read as text and then checked and cast
+  /// to its declared type, so a malformed value anywhere in the file is reported instead of
+  /// mistyping its column or failing the load.
+  ///
+  /// # Arguments
+  ///
+  /// * `path`: Path of the CSV file.
+  pub fn load(
+    &self,
+    path: &str,
+  ) -> GenericResult<ValidatedCsv> {
+    let mut violations: Vec<(Option<usize>, String, Option<String>, Violation)> =
+      Vec::new();
+
+    // The header is checked apart because the columns are named by the schema. The records
+    // are read with their quotes, so a quoted field may hold commas and line breaks: the line
+    // of every record is where it starts in the file.
+    let mut reader = csv::ReaderBuilder::new()
+      .has_headers(false)
+      .flexible(true)
+      .from_path(path)?;
+    let mut records = reader.records();
+    let headers = records.next().transpose()?.unwrap_or_default();
+    let mut lines: Vec<u64> = Vec::new();
+    for record in records {
+      lines.push(record?.position().map_or(0, csv::Position::line));
+    }
+
+    for index in 0..headers.len().max(self.columns.len()) {
+      let expected = self.columns.get(index).map(ColumnSchema::header);
+      let found = headers.get(index);
+      if expected != found {
+        violations.push((
+          None,
+          expected.unwrap_or_default().to_string(),
+          found.map(str::to_string),
+          Violation::Header,
+        ));
+      }
+    }
+
+    let text_schema = Schema::from_iter(
+      self
+        .columns
+        .iter()
+        .map(|column| Field::new(&column.name, DataType::Utf8)),
+    );
+    let text = CsvReader::from_path(path)?
+      .has_header(true)
+      .with_schema(Arc::new(text_schema))
+      .finish()?;
+
+    let mut typed: Vec<Series> = Vec::with_capacity(self.columns.len());
+    for column in &self.columns {
+      let values = text.column(&column.name)?;
+      let mut cast = values.cast(&column.dtype)?;
+      let texts = values.utf8()?;
+      let mut invalid = vec![false; values.len()];
+
+      for (row, value) in texts.into_iter().enumerate() {
+        let violation = match value {
+          None if !column.nullable => Some(Violation::Missing),
+          None => None,
+          Some(value) => column.check(value, cast.get(row)?),
+        };
+        if let Some(violation) = violation {
+          invalid[row] = violation != Violation::Missing;
+          violations.push((
+            Some(row),
+            column.name.clone(),
+            value.map(str::to_string),
+            violation,
+          ));
+        }
+      }
+
+      if invalid.iter().any(|invalid| *invalid) {
+        let valid: BooleanChunked = invalid.iter().map(|invalid| !invalid).collect();
+        cast = cast.zip_with(
+          &valid,
+          &Series::full_null(&column.name, cast.len(), &column.dtype),
+        )?;
+      }
+      typed.push(cast);
+    }
+
+    let report = df!(
+      "Line" => violations
+        .iter()
+        .map(|(row, ..)| row.map_or(Some(1), |row| lines.get(row).copied()))
+        .collect::<Vec<Option<u64>>>(),
+      "Row" => violations
+        .iter()
+        .map(|(row, ..)| row.map(|row| row as u32))
+        .collect::<Vec<Option<u32>>>(),
+      "Column" => violations.iter().map(|(_, column, ..)| column.clone()).collect::<Vec<String>>(),
+      "Value" => violations
+        .iter()
+        .map(|(_, _, value, _)| value.clone())
+        .collect::<Vec<Option<String>>>(),
+      "Violation" => violations
+        .iter()
+        .map(|(.., violation)| violation.to_string())
+        .collect::<Vec<String>>(),
+    )?
+    .sort(["Line"], false, true)?;
+
+    Ok(ValidatedCsv {
+      df: DataFrame::new(typed)?,
+      report,
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Writes a CSV file to the temporary directory and loads it with a schema.
+  fn load(
+    file_name: &str,
+    content: &str,
+    schema: &CsvSchema,
+  ) -> ValidatedCsv {
+    let path = std::env::temp_dir().join(file_name);
+    std::fs::write(&path, content).unwrap();
+    let validated = schema.load(path.to_str().unwrap()).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    validated
+  }
+
+  fn schema() -> CsvSchema {
+    CsvSchema::new(vec![
+      ColumnSchema::new("City, State", DataType::Utf8).required(),
+      ColumnSchema::new("Price", DataType::Float64).range(0.0, 100.0),
+    ])
+  }
+
+  #[test]
+  fn quoted_header_with_a_comma_is_expected() {
+    let validated = load(
+      "csv_schema_quoted_header.csv",
+      "\"City, State\",Price\nBOSTON,10\n",
+      &schema(),
+    );
+    assert!(validated.is_valid());
+    assert_eq!(validated.df.shape(), (1, 2));
+  }
+
+  #[test]
+  fn lines_follow_the_quoted_line_breaks() {
+    let validated = load(
+      "csv_schema_quoted_line_breaks.csv",
+      "\"City, State\",Price\n\"BOSTON\nMA\",10\n,500\nDALLAS,x\n",
+      &schema(),
+    );
+    let report = &validated.report;
+    let lines: Vec<u64> = report
+      .column("Line")
+      .unwrap()
+      .u64()
+      .unwrap()
+      .into_no_null_iter()
+      .collect();
+    let rows: Vec<u32> = report
+      .column("Row")
+      .unwrap()
+      .u32()
+      .unwrap()
+      .into_no_null_iter()
+      .collect();
+    let violations: Vec<&str> = report
+      .column("Violation")
+      .unwrap()
+      .utf8()
+      .unwrap()
+      .into_no_null_iter()
+      .collect();
+    assert_eq!(lines, [4, 4, 5]);
+    assert_eq!(rows, [1, 1, 2]);
+    assert_eq!(
+      violations,
+      ["Missing value", "Out of range", "Invalid type"]
+    );
+  }
+
+  #[test]
+  fn header_violations_are_on_the_first_line() {
+    let validated = load(
+      "csv_schema_header.csv",
+      "City,Price\nBOSTON,10\n",
+      &schema(),
+    );
+    let report = &validated.report;
+    assert_eq!(report.height(), 1);
+    assert_eq!(
+      report.column("Line").unwrap().u64().unwrap().get(0),
+      Some(1)
+    );
+    assert_eq!(report.column("Row").unwrap().u32().unwrap().get(0), None);
+  }
+}
@@ -0,0 +1,380 @@
+use plotly::{common::Title, layout::Axis, Bar, HeatMap, Histogram, Layout, Trace};
+use polars::prelude::*;
+
+use crate::application_error::GenericResult;
+
+/// Quantiles reported for every numeric column.
+pub const PROFILE_QUANTILES: [f64; 5] = [0.05, 0.25, 0.5, 0.75, 0.95];
+
+/// Represents options for profiling a dataframe.
+#[derive(Clone)]
+pub struct ProfileOptions {
+  /// Number of most frequent values reported per column.
+  pub top_k: usize,
+  /// Number of bins of the histograms of the numeric columns.
+  pub histogram_bins: usize,
+  /// Maximum number of duplicated rows kept as examples.
+  pub duplicate_examples: usize,
+}
+
+impl ProfileOptions {
+  /// Creates a new instance of `[ProfileOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these profile options.
+  pub fn builder() -> ProfileOptionsBuilder {
+    ProfileOptionsBuilder::default()
+  }
+}
+
+impl Default for ProfileOptions {
+  fn default() -> Self {
+    ProfileOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[ProfileOptions]`.
+pub struct ProfileOptionsBuilder {
+  /// Number of most frequent values reported per column.
+  pub top_k: usize,
+  /// Number of bins of the histograms of the numeric columns.
+  pub histogram_bins: usize,
+  /// Maximum number of duplicated rows kept as examples.
+  pub duplicate_examples: usize,
+}
+
+impl ProfileOptionsBuilder {
+  /// Creates a new instance of `[ProfileOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the number of most frequent values reported per column.
+  pub fn top_k(
+    mut self,
+    top_k: usize,
+  ) -> Self {
+    self.top_k = top_k;
+    self
+  }
+
+  /// Sets the number of bins of the histograms.
+  pub fn histogram_bins(
+    mut self,
+    histogram_bins: usize,
+  ) -> Self {
+    self.histogram_bins = histogram_bins;
+    self
+  }
+
+  /// Sets the maximum number of duplicated rows kept as examples.
+  pub fn duplicate_examples(
+    mut self,
+    duplicate_examples: usize,
+  ) -> Self {
+    self.duplicate_examples = duplicate_examples;
+    self
+  }
+
+  /// Builds the instance of `[ProfileOptions]`.
+  pub fn build(self) -> ProfileOptions {
+    ProfileOptions {
+      top_k: self.top_k,
+      histogram_bins: self.histogram_bins,
+      duplicate_examples: self.duplicate_examples,
+    }
+  }
+}
+
+impl Default for ProfileOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      top_k: 5,
+      histogram_bins: 20,
+      duplicate_examples: 10,
+    }
+  }
+}
+
+/// Represents the statistics of a numeric column.
+#[derive(Clone, Debug)]
+pub struct NumericProfile {
+  /// Smallest value.
+  pub min: f64,
+  /// Largest value.
+  pub max: f64,
+  /// Mean of the values.
+  pub mean: f64,
+  /// Values of the `[PROFILE_QUANTILES]`, in order.
+  pub quantiles: Vec<f64>,
+  /// Non-null values, for the histogram.
+  pub values: Vec<f64>,
+}
+
+/// Represents the profile of a column.
+#[derive(Clone, Debug)]
+pub struct ColumnProfile {
+  /// Name of the column.
+  pub name: String,
+  /// Data type of the column.
+  pub dtype: DataType,
+  /// Number of null values.
+  pub null_count: usize,
+  /// Number of distinct non-null values.
+  pub distinct_count: usize,
+  /// Most frequent non-null values with their frequencies, most frequent first.
+  pub top_values: Vec<(String, u32)>,
+  /// Statistics of a numeric column with values; `None` otherwise.
+  pub numeric: Option<NumericProfile>,
+}
+
+impl ColumnProfile {
+  /// Profiles a column.
+  pub fn new(
+    series: &Series,
+    options: &ProfileOptions,
+  ) -> GenericResult<Self> {
+    let values = series.drop_nulls();
+
+    let counts = values.cast(&DataType::Utf8)?.value_counts(true, true)?;
+    let top = counts.head(Some(options.top_k));
+    let top_values = top[0]
+      .utf8()?
+      .into_iter()
+      .zip(top[1].u32()?)
+      .filter_map(|(value, count)| Some((value?.to_string(), count?)))
+      .collect();
+
+    let numeric = if series.dtype().is_numeric() && !values.is_empty() {
+      let values = values.cast(&DataType::Float64)?;
+      let values = values.f64()?;
+      let mut quantiles = Vec::with_capacity(PROFILE_QUANTILES.len());
+      for quantile in PROFILE_QUANTILES {
+        quantiles.push(
+          values
+            .quantile(quantile, QuantileInterpolOptions::Linear)?
+            .unwrap_or(f64::NAN),
+        );
+      }
+      Some(NumericProfile {
+        min: values.min().unwrap_or(f64::NAN),
+        max: values.max().unwrap_or(f64::NAN),
+        mean: values.mean().unwrap_or(f64::NAN),
+        quantiles,
+        values: values.into_no_null_iter().collect(),
+      })
+    } else {
+      None
+    };
+
+    Ok(Self {
+      name: series.name().to_string(),
+      dtype: series.dtype().clone(),
+      null_count: series.null_count(),
+      distinct_count: counts.height(),
+      top_values,
+      numeric,
+    })
+  }
+
+  /// Gets the thumbnail of the column: a histogram of a numeric column, or a bar chart of the
+  /// most frequent values otherwise. `None` for a column without values.
+  pub fn thumbnail(
+    &self,
+    options: &ProfileOptions,
+  ) -> Option<(Vec<Box<dyn Trace>>, Layout)> {
+    let trace: Box<dyn Trace> = match &self.numeric {
+      Some(numeric) => Histogram::new(numeric.values.clone())
+        .n_bins_x(options.histogram_bins)
+        .name(&self.name),
+      None if !self.top_values.is_empty() => Bar::new(
+        self
+          .top_values
+          .iter()
+          .map(|(value, _)| value.clone())
+          .collect(),
+        self.top_values.iter().map(|(_, count)| *count).collect(),
+      )
+      .name(&self.name),
+      None => return None,
+    };
+
+    let layout = Layout::new()
+      .height(250)
+      .title(Title::new(&self.name))
+      .y_axis(Axis::new().title(Title::new("Count")));
+
+    Some((vec![trace], layout))
+  }
+}
+
+/// Represents the profile of a dataframe.
+#[derive(Clone, Debug)]
+pub struct DataProfile {
+  /// Number of rows.
+  pub rows: usize,
+  /// Profile of every column, in order.
+  pub columns: Vec<ColumnProfile>,
+  /// Number of rows equal to an earlier row.
+  pub duplicate_rows: usize,
+  /// Examples of the duplicated rows, every copy included.
+  pub duplicate_examples: DataFrame,
+  /// Names of the numeric columns with values, in order.
+  pub numeric_columns: Vec<String>,
+  /// Pearson correlation of every pair of numeric columns over the rows where both have values;
+  /// `NaN` when a column is constant over those rows.
+  pub correlations: Vec<Vec<f64>>,
+}
+
+impl DataProfile {
+  /// Profiles a dataframe.
+  ///
+  /// # Arguments
+  ///
+  /// * `df`: Dataframe to profile.
+  /// * `options`: Options of the profile.
+  pub fn new(
+    df: &DataFrame,
+    options: &ProfileOptions,
+  ) -> GenericResult<Self> {
+    let columns = df
+      .get_columns()
+      .iter()
+      .map(|series| ColumnProfile::new(series, options))
+      .collect::<GenericResult<Vec<ColumnProfile>>>()?;
+
+    let duplicated = df.is_duplicated()?;
+    let duplicate_rows =
+      df.height() - df.unique(None, UniqueKeepStrategy::First, None)?.height();
+    let duplicate_examples = df
+      .filter(&duplicated)?
+      .head(Some(options.duplicate_examples));
+
+    let numeric: Vec<&ColumnProfile> =
+      columns.iter().filter(|c| c.numeric.is_some()).collect();
+    let numeric_columns: Vec<String> = numeric.iter().map(|c| c.name.clone()).collect();
+    let mut values: Vec<Vec<Option<f64>>> = Vec::with_capacity(numeric_columns.len());
+    for name in &numeric_columns {
+      values.push(
+        df.column(name)?
+          .cast(&DataType::Float64)?
+          .f64()?
+          .into_iter()
+          .collect(),
+      );
+    }
+    let correlations = values
+      .iter()
+      .map(|a| values.iter().map(|b| pearson(a, b)).collect())
+      .collect();
+
+    Ok(Self {
+      rows: df.height(),
+      columns,
+      duplicate_rows,
+      duplicate_examples,
+      numeric_columns,
+      correlations,
+    })
+  }
+
+  /// Gets the summary of every column, with the columns Column, Type, Nulls, Distinct, Min,
+  /// Mean, Max, one column per quantile, and Top Values.
+  pub fn summary(&self) -> GenericResult<DataFrame> {
+    let numeric = |statistic: &dyn Fn(&NumericProfile) -> f64| -> Vec<Option<f64>> {
+      self
+        .columns
+        .iter()
+        .map(|c| c.numeric.as_ref().map(statistic))
+        .collect()
+    };
+
+    let mut summary = df!(
+      "Column" => self.columns.iter().map(|c| c.name.clone()).collect::<Vec<String>>(),
+      "Type" => self.columns.iter().map(|c| c.dtype.to_string()).collect::<Vec<String>>(),
+      "Nulls" => self.columns.iter().map(|c| c.null_count as u32).collect::<Vec<u32>>(),
+      "Distinct" => self.columns.iter().map(|c| c.distinct_count as u32).collect::<Vec<u32>>(),
+      "Min" => numeric(&|n| n.min),
+      "Mean" => numeric(&|n| n.mean),
+      "Max" => numeric(&|n| n.max),
+    )?;
+
+    for (index, quantile) in PROFILE_QUANTILES.iter().enumerate() {
+      summary.with_column(Series::new(
+        &format!("Q{}", (quantile * 100.0).round()),
+        numeric(&|n| n.quantiles[index]),
+      ))?;
+    }
+
+    summary.with_column(Series::new(
+      "Top Values",
+      self
+        .columns
+        .iter()
+        .map(|c| {
+          c.top_values
+            .iter()
+            .map(|(value, count)| format!("{value} ({count})"))
+            .collect::<Vec<String>>()
+            .join(", ")
+        })
+        .collect::<Vec<String>>(),
+    ))?;
+
+    Ok(summary)
+  }
+
+  /// Gets the correlation matrix as a dataframe: a Column column and one column per numeric
+  /// column.
+  pub fn correlation_table(&self) -> GenericResult<DataFrame> {
+    let mut columns = vec![Series::new("Column", self.numeric_columns.clone())];
+    for (index, name) in self.numeric_columns.iter().enumerate() {
+      let values: Vec<f64> = self.correlations.iter().map(|row| row[index]).collect();
+      columns.push(Series::new(name, values));
+    }
+    Ok(DataFrame::new(columns)?)
+  }
+
+  /// Gets the heat map of the correlations.
+  pub fn correlation_heat_map(&self) -> (Vec<Box<dyn Trace>>, Layout) {
+    let trace: Box<dyn Trace> = HeatMap::new(
+      self.numeric_columns.clone(),
+      self.numeric_columns.clone(),
+      self.correlations.clone(),
+    );
+
+    (
+      vec![trace],
+      Layout::new().title(Title::new("Pearson correlation")),
+    )
+  }
+}
+
+/// Calculates the Pearson correlation of two columns over the rows where both have values.
+fn pearson(
+  a: &[Option<f64>],
+  b: &[Option<f64>],
+) -> f64 {
+  let pairs: Vec<(f64, f64)> = a
+    .iter()
+    .zip(b.iter())
+    .filter_map(|(a, b)| Some(((*a)?, (*b)?)))
+    .collect();
+  if pairs.len() < 2 {
+    return f64::NAN;
+  }
+
+  let n = pairs.len() as f64;
+  let mean_a = pairs.iter().map(|(a, _)| a).sum::<f64>() / n;
+  let mean_b = pairs.iter().map(|(_, b)| b).sum::<f64>() / n;
+  let (mut covariance, mut variance_a, mut variance_b) = (0.0, 0.0, 0.0);
+  for (a, b) in &pairs {
+    covariance += (a - mean_a) * (b - mean_b);
+    variance_a += (a - mean_a).powi(2);
+    variance_b += (b - mean_b).powi(2);
+  }
+
+  covariance / (variance_a * variance_b).sqrt()
+}
@@ -0,0 +1,203 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::SystemTime;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Calculates the 64-bit FNV-1a hash of some content. It is stable across runs and platforms,
+/// unlike the hasher of the standard library.
+pub fn content_hash(content: &[u8]) -> u64 {
+  const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+  const PRIME: u64 = 0x100000001b3;
+
+  content.iter().fold(OFFSET_BASIS, |hash, byte| {
+    (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+  })
+}
+
+/// Represents the state of a file when it was read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileFingerprint {
+  /// Last modification time, if the platform has it.
+  pub modified: Option<SystemTime>,
+  /// Size in bytes.
+  pub len: u64,
+  /// Hash of the content, see `[content_hash]`.
+  pub hash: u64,
+}
+
+impl FileFingerprint {
+  /// Reads the fingerprint of a file, hashing its whole content.
+  pub fn read(path: &str) -> GenericResult<Self> {
+    let metadata = fs::metadata(path)?;
+    Ok(Self {
+      modified: metadata.modified().ok(),
+      len: metadata.len(),
+      hash: content_hash(&fs::read(path)?),
+    })
+  }
+
+  /// Whether the modification time and the size of a file are still the ones of the
+  /// fingerprint, without reading the file.
+  fn matches_metadata(
+    &self,
+    metadata: &fs::Metadata,
+  ) -> bool {
+    self.modified.is_some()
+      && self.modified == metadata.modified().ok()
+      && self.len == metadata.len()
+  }
+}
+
+/// Represents the counters of a `[DataFrameCache]`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CacheStats {
+  /// Number of values cached.
+  pub entries: usize,
+  /// Number of requests served from the cache.
+  pub hits: u64,
+  /// Number of requests that built their value.
+  pub misses: u64,
+}
+
+/// Represents a cached value and the fingerprint of the file it was built from.
+struct CacheEntry {
+  fingerprint: FileFingerprint,
+  value: Arc<dyn Any + Send + Sync>,
+}
+
+/// Represents a cache of dataframes built from files, e.g. a loaded dataset and the results
+/// of its preparation pipelines, and of the values computed from them, e.g. the fitted models
+/// of a notebook. An entry is rebuilt when the modification time or the size of
+/// its file changes and so does the hash of the content, so touching a file does not rebuild it.
+///
+/// The content is only hashed once the modification time or the size changed: a rewrite that
+/// keeps both, e.g. within the timestamp resolution of the file system or with a restored
+/// modification time, is not seen. Call `[DataFrameCache::invalidate]` after such a rewrite.
+///
+/// Dataframes share their columns when cloned, so a hit costs no copy of the data. Other values
+/// are cloned on every hit: cache them behind an `Arc` when they are large.
+#[derive(Default)]
+pub struct DataFrameCache {
+  entries: Mutex<HashMap<(String, String), CacheEntry>>,
+  hits: AtomicU64,
+  misses: AtomicU64,
+}
+
+impl DataFrameCache {
+  /// Creates an empty cache.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets a cached value, e.g. a dataframe, or builds and caches it when missing, when its file
+  /// changed, or when the value cached under the key has another type.
+  ///
+  /// A hit only compares the modification time and the size of the file with the ones of the
+  /// cached fingerprint, without reading the file, so a rewrite with the same size and the same
+  /// modification time still returns the cached value. The content is hashed when either of
+  /// them changed, and the value is rebuilt only if the hash changed too.
+  ///
+  /// # Arguments
+  ///
+  /// * `path`: File the value is built from.
+  /// * `key`: Key of the value among the ones built from the file, e.g. the name of the
+  ///   pipeline and its parameters.
+  /// * `build`: Builds the value. It runs without holding the cache lock.
+  pub fn get_or_build<T: Clone + Send + Sync + 'static>(
+    &self,
+    path: &str,
+    key: &str,
+    build: impl FnOnce() -> GenericResult<T>,
+  ) -> GenericResult<T> {
+    let cache_key = (path.to_string(), key.to_string());
+    let metadata = fs::metadata(path)?;
+
+    let cached_hash = match self.lock()?.get(&cache_key) {
+      Some(entry) => match entry.value.downcast_ref::<T>() {
+        Some(value) if entry.fingerprint.matches_metadata(&metadata) => {
+          self.hits.fetch_add(1, Ordering::Relaxed);
+          return Ok(value.clone());
+        }
+        Some(_) => Some(entry.fingerprint.hash),
+        None => None,
+      },
+      None => None,
+    };
+
+    // The fingerprint is read before building, so a change during the build is seen next time
+    let fingerprint = FileFingerprint::read(path)?;
+    if cached_hash == Some(fingerprint.hash) {
+      if let Some(entry) = self.lock()?.get_mut(&cache_key) {
+        if let Some(value) = entry.value.downcast_ref::<T>() {
+          let value = value.clone();
+          entry.fingerprint = fingerprint;
+          self.hits.fetch_add(1, Ordering::Relaxed);
+          return Ok(value);
+        }
+      }
+    }
+
+    let value = build()?;
+    self.misses.fetch_add(1, Ordering::Relaxed);
+    self.lock()?.insert(
+      cache_key,
+      CacheEntry {
+        fingerprint,
+        value: Arc::new(value.clone()),
+      },
+    );
+    Ok(value)
+  }
+
+  /// Gets the fingerprint of the file of a cached value, as it was when the value was built or
+  /// last checked; `None` when it is not cached.
+  ///
+  /// # Arguments
+  ///
+  /// * `path`: File the value is built from.
+  /// * `key`: Key of the value, see `[DataFrameCache::get_or_build]`.
+  pub fn fingerprint(
+    &self,
+    path: &str,
+    key: &str,
+  ) -> GenericResult<Option<FileFingerprint>> {
+    Ok(
+      self
+        .lock()?
+        .get(&(path.to_string(), key.to_string()))
+        .map(|entry| entry.fingerprint),
+    )
+  }
+
+  /// Removes every value built from a file.
+  pub fn invalidate(
+    &self,
+    path: &str,
+  ) -> GenericResult<()> {
+    self.lock()?.retain(|(entry_path, _), _| entry_path != path);
+    Ok(())
+  }
+
+  /// Gets the counters of the cache.
+  pub fn stats(&self) -> GenericResult<CacheStats> {
+    Ok(CacheStats {
+      entries: self.lock()?.len(),
+      hits: self.hits.load(Ordering::Relaxed),
+      misses: self.misses.load(Ordering::Relaxed),
+    })
+  }
+
+  /// Locks the entries.
+  fn lock(&self) -> GenericResult<MutexGuard<'_, HashMap<(String, String), CacheEntry>>> {
+    Ok(
+      self
+        .entries
+        .lock()
+        .map_err(|_| GenericError::from("The dataframe cache is poisoned"))?,
+    )
+  }
+}
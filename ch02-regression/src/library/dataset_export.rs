@@ -0,0 +1,46 @@
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::dataset_loader::DatasetFormat;
+
+/// Formats of the exported datasets.
+pub const EXPORT_FORMATS: [DatasetFormat; 3] = [
+  DatasetFormat::Csv,
+  DatasetFormat::Parquet,
+  DatasetFormat::Json,
+];
+
+/// Writes a dataframe in a format, e.g. for a download. The result reads back with
+/// `[crate::dataset_loader::load_dataset_as]`.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe to write.
+/// * `format`: Format of the result.
+pub fn write_dataset(
+  df: &DataFrame,
+  format: DatasetFormat,
+) -> GenericResult<Vec<u8>> {
+  let mut df = df.clone();
+  let mut content: Vec<u8> = Vec::new();
+  let written = match format {
+    DatasetFormat::Csv => CsvWriter::new(&mut content)
+      .has_header(true)
+      .finish(&mut df),
+    DatasetFormat::Parquet => {
+      ParquetWriter::new(&mut content).finish(&mut df).map(|_| ())
+    }
+    DatasetFormat::Ipc => IpcWriter::new(&mut content).finish(&mut df),
+    DatasetFormat::Json => JsonWriter::new(&mut content)
+      .with_json_format(JsonFormat::Json)
+      .finish(&mut df),
+    DatasetFormat::NdJson => JsonWriter::new(&mut content)
+      .with_json_format(JsonFormat::JsonLines)
+      .finish(&mut df),
+  };
+
+  written.map_err(|error| {
+    GenericError::from(format!("Cannot write the data as {format}: {error}"))
+  })?;
+  Ok(content)
+}
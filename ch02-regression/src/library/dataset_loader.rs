@@ -0,0 +1,156 @@
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Number of rows used to infer the types of the columns of a CSV file.
+pub const CSV_INFER_SCHEMA_ROWS: usize = 2000;
+
+/// Represents the format of a dataset file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatasetFormat {
+  /// Comma-separated values with a header.
+  Csv,
+  /// Apache Parquet.
+  Parquet,
+  /// Arrow IPC file, also known as Feather (version 2).
+  Ipc,
+  /// JSON array of records, e.g. `[{"a": 1}, {"a": 2}]`. A key that is null in every record
+  /// gives no column.
+  Json,
+  /// Newline-delimited JSON: one record per line.
+  NdJson,
+}
+
+impl DatasetFormat {
+  /// Gets the format of a file extension, ignoring its case.
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    match extension.to_ascii_lowercase().as_str() {
+      "csv" => Some(Self::Csv),
+      "parquet" | "pq" => Some(Self::Parquet),
+      "arrow" | "ipc" | "feather" => Some(Self::Ipc),
+      "json" => Some(Self::Json),
+      "ndjson" | "jsonl" => Some(Self::NdJson),
+      _ => None,
+    }
+  }
+
+  /// Gets the format of the first bytes of a file: the Parquet and Arrow magic numbers, a JSON
+  /// array, a JSON object per line, or CSV otherwise.
+  pub fn from_content(head: &[u8]) -> Self {
+    if head.starts_with(b"PAR1") {
+      return Self::Parquet;
+    }
+    if head.starts_with(b"ARROW1") {
+      return Self::Ipc;
+    }
+    match head.iter().find(|byte| !byte.is_ascii_whitespace()) {
+      Some(b'[') => Self::Json,
+      Some(b'{') => Self::NdJson,
+      _ => Self::Csv,
+    }
+  }
+
+  /// Detects the format of a file from its extension, or from its content when the extension
+  /// is missing or unknown.
+  pub fn detect(path: &str) -> GenericResult<Self> {
+    let extension = Path::new(path)
+      .extension()
+      .and_then(|extension| extension.to_str())
+      .and_then(Self::from_extension);
+    if let Some(format) = extension {
+      return Ok(format);
+    }
+
+    let mut head = Vec::with_capacity(64);
+    File::open(path)?.take(64).read_to_end(&mut head)?;
+    Ok(Self::from_content(&head))
+  }
+
+  /// Gets the display name of the format.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Csv => "CSV",
+      Self::Parquet => "Parquet",
+      Self::Ipc => "Arrow IPC",
+      Self::Json => "JSON",
+      Self::NdJson => "NDJSON",
+    }
+  }
+
+  /// Gets the usual file extension of the format, without the dot.
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Self::Csv => "csv",
+      Self::Parquet => "parquet",
+      Self::Ipc => "arrow",
+      Self::Json => "json",
+      Self::NdJson => "ndjson",
+    }
+  }
+
+  /// Gets the media type of the format, e.g. for the `Content-Type` of a download.
+  pub fn media_type(&self) -> &'static str {
+    match self {
+      Self::Csv => "text/csv",
+      Self::Parquet => "application/vnd.apache.parquet",
+      Self::Ipc => "application/vnd.apache.arrow.file",
+      Self::Json => "application/json",
+      Self::NdJson => "application/x-ndjson",
+    }
+  }
+}
+
+impl fmt::Display for DatasetFormat {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+/// Loads a dataset file into a dataframe, detecting its format with `[DatasetFormat::detect]`.
+///
+/// # Arguments
+///
+/// * `path`: Path of the file.
+pub fn load_dataset(path: &str) -> GenericResult<DataFrame> {
+  load_dataset_as(path, DatasetFormat::detect(path)?)
+}
+
+/// Loads a dataset file of a known format into a dataframe.
+///
+/// # Arguments
+///
+/// * `path`: Path of the file.
+/// * `format`: Format of the file.
+pub fn load_dataset_as(
+  path: &str,
+  format: DatasetFormat,
+) -> GenericResult<DataFrame> {
+  let df = match format {
+    DatasetFormat::Csv => CsvReader::from_path(path)?
+      .has_header(true)
+      .infer_schema(Some(CSV_INFER_SCHEMA_ROWS))
+      .finish(),
+    DatasetFormat::Parquet => ParquetReader::new(File::open(path)?).finish(),
+    DatasetFormat::Ipc => IpcReader::new(File::open(path)?).finish(),
+    DatasetFormat::Json => JsonReader::new(File::open(path)?)
+      .with_json_format(JsonFormat::Json)
+      .infer_schema_len(None)
+      .finish(),
+    DatasetFormat::NdJson => JsonReader::new(File::open(path)?)
+      .with_json_format(JsonFormat::JsonLines)
+      .infer_schema_len(None)
+      .finish(),
+  };
+
+  df.map_err(|error| {
+    GenericError::from(format!("Cannot read \"{path}\" as {format}: {error}")).into()
+  })
+}
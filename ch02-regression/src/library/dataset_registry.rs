@@ -0,0 +1,425 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+
+use polars::prelude::*;
+use serde_json::Value;
+
+use crate::application_error::{ApplicationError, GenericError, GenericResult};
+use crate::dataset_loader::{load_dataset_as, DatasetFormat};
+use crate::pumpkins::{load_pumpkins, PUMPKINS_CSV_PATH, PUMPKINS_DATASET};
+
+/// Formats accepted by `[DatasetRegistry::upload]`.
+pub const UPLOAD_FORMATS: [DatasetFormat; 2] = [DatasetFormat::Csv, DatasetFormat::Parquet];
+
+/// Represents where a dataset was registered from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DatasetSource {
+  /// Built into the application.
+  Default,
+  /// Configuration file.
+  Config,
+  /// Command line.
+  CommandLine,
+  /// HTTP upload.
+  Upload,
+}
+
+impl fmt::Display for DatasetSource {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let name = match self {
+      Self::Default => "Default",
+      Self::Config => "Configuration file",
+      Self::CommandLine => "Command line",
+      Self::Upload => "Upload",
+    };
+    write!(f, "{name}")
+  }
+}
+
+/// Represents a registered dataset.
+#[derive(Clone, Debug)]
+pub struct DatasetEntry {
+  /// Name used to look the dataset up, e.g. `"us-pumpkins"`.
+  pub name: String,
+  /// Absolute path of the file.
+  pub path: String,
+  /// Format of the file.
+  pub format: DatasetFormat,
+  /// Where the dataset was registered from.
+  pub source: DatasetSource,
+}
+
+impl DatasetEntry {
+  /// Loads the dataset. The US pumpkins are loaded with their declared schema.
+  pub fn load(&self) -> GenericResult<DataFrame> {
+    if self.name == PUMPKINS_DATASET {
+      load_pumpkins(&self.path)
+    } else {
+      load_dataset_as(&self.path, self.format)
+    }
+  }
+}
+
+/// Represents the registry that maps dataset names to files, so handlers look datasets up by
+/// name instead of by a path relative to the working directory.
+#[derive(Clone, Debug)]
+pub struct DatasetRegistry {
+  datasets: BTreeMap<String, DatasetEntry>,
+  /// Directory where the uploaded datasets are saved.
+  pub upload_dir: PathBuf,
+}
+
+impl DatasetRegistry {
+  /// Creates an empty registry.
+  pub fn new(upload_dir: PathBuf) -> Self {
+    Self {
+      datasets: BTreeMap::new(),
+      upload_dir,
+    }
+  }
+
+  /// Creates a registry with the datasets of the crate, with paths relative to the crate
+  /// directory so the server can start from any working directory.
+  pub fn with_defaults() -> GenericResult<Self> {
+    let crate_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut registry = Self::new(crate_dir.join("data").join("uploads"));
+    registry.register(
+      PUMPKINS_DATASET,
+      &crate_dir.join(PUMPKINS_CSV_PATH),
+      None,
+      DatasetSource::Default,
+    )?;
+    Ok(registry)
+  }
+
+  /// Reads a JSON configuration file on top of the default datasets. Relative paths are
+  /// relative to the directory of the file:
+  ///
+  /// ```json
+  /// {
+  ///   "upload_dir": "data/uploads",
+  ///   "datasets": {
+  ///     "us-pumpkins": "data/US-pumpkins.csv",
+  ///     "market": { "path": "/srv/market/2017.data", "format": "parquet" }
+  ///   }
+  /// }
+  /// ```
+  pub fn from_config_file(path: &Path) -> GenericResult<Self> {
+    let config: Value = serde_json::from_str(&fs::read_to_string(path)?)
+      .map_err(|error| config_error(path, &error.to_string()))?;
+    let base_dir = path.parent().unwrap_or(Path::new("."));
+
+    let mut registry = Self::with_defaults()?;
+    if let Some(upload_dir) = config.get("upload_dir") {
+      let upload_dir = upload_dir
+        .as_str()
+        .ok_or_else(|| config_error(path, "\"upload_dir\" must be a path"))?;
+      registry.upload_dir = base_dir.join(upload_dir);
+    }
+
+    let datasets = match config.get("datasets") {
+      None => return Ok(registry),
+      Some(datasets) => datasets
+        .as_object()
+        .ok_or_else(|| config_error(path, "\"datasets\" must be an object"))?,
+    };
+    for (name, dataset) in datasets {
+      let (dataset_path, format) = match dataset {
+        Value::String(dataset_path) => (dataset_path.as_str(), None),
+        Value::Object(fields) => (
+          fields.get("path").and_then(Value::as_str).ok_or_else(|| {
+            config_error(path, &format!("The dataset \"{name}\" has no \"path\""))
+          })?,
+          fields.get("format").and_then(Value::as_str),
+        ),
+        _ => {
+          return Err(config_error(
+            path,
+            &format!("The dataset \"{name}\" must be a path or an object"),
+          ))
+        }
+      };
+      let format = format.map(parse_format).transpose()?;
+      registry.register(name, &base_dir.join(dataset_path), format, DatasetSource::Config)?;
+    }
+
+    Ok(registry)
+  }
+
+  /// Registers a dataset file, replacing any dataset with the same name.
+  ///
+  /// # Arguments
+  ///
+  /// * `name`: Name of the dataset: lowercase letters, digits, `-` and `_`.
+  /// * `path`: Path of an existing file.
+  /// * `format`: Format of the file, or `None` to detect it.
+  /// * `source`: Where the dataset comes from.
+  pub fn register(
+    &mut self,
+    name: &str,
+    path: &Path,
+    format: Option<DatasetFormat>,
+    source: DatasetSource,
+  ) -> GenericResult<&DatasetEntry> {
+    validate_dataset_name(name)?;
+    let path = path.canonicalize().map_err(|error| {
+      GenericError::from(format!(
+        "The file of the dataset \"{name}\" ({}) cannot be found: {error}",
+        path.display()
+      ))
+    })?;
+    let path = path.to_string_lossy().to_string();
+    let format = match format {
+      Some(format) => format,
+      None => DatasetFormat::detect(&path)?,
+    };
+
+    let entry = DatasetEntry {
+      name: name.to_string(),
+      path,
+      format,
+      source,
+    };
+    self.datasets.insert(name.to_string(), entry);
+    Ok(&self.datasets[name])
+  }
+
+  /// Registers the datasets previously uploaded to the upload directory, named after their
+  /// files.
+  pub fn register_uploads(&mut self) -> GenericResult<()> {
+    let Ok(files) = fs::read_dir(&self.upload_dir) else {
+      return Ok(());
+    };
+    for file in files {
+      let path = file?.path();
+      let name = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string);
+      let format = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(DatasetFormat::from_extension);
+      if let (Some(name), Some(format)) = (name, format) {
+        if validate_dataset_name(&name).is_ok() && !self.datasets.contains_key(&name) {
+          self.register(&name, &path, Some(format), DatasetSource::Upload)?;
+        }
+      }
+    }
+    Ok(())
+  }
+
+  /// Checks that an upload can be registered under a name: the name is valid and no dataset
+  /// has it yet. It is checked again by `[DatasetRegistry::register_upload]`.
+  pub fn check_upload(
+    &self,
+    name: &str,
+  ) -> Result<(), UploadError> {
+    validate_dataset_name(name).map_err(|error| UploadError::Invalid(error.to_string()))?;
+    if self.datasets.contains_key(name) {
+      return Err(UploadError::Duplicate(name.to_string()));
+    }
+    Ok(())
+  }
+
+  /// Registers an upload saved by `[save_upload]`, moving its file to its final name. The file
+  /// is removed if a dataset with the same name was registered meanwhile.
+  pub fn register_upload(
+    &mut self,
+    upload: SavedUpload,
+  ) -> Result<&DatasetEntry, UploadError> {
+    if let Err(error) = self.check_upload(&upload.name) {
+      fs::remove_file(&upload.pending_path)?;
+      return Err(error);
+    }
+
+    fs::rename(&upload.pending_path, &upload.path)?;
+    Ok(self.register(&upload.name, &upload.path, Some(upload.format), DatasetSource::Upload)?)
+  }
+
+  /// Gets a dataset by name, if registered.
+  pub fn get(
+    &self,
+    name: &str,
+  ) -> Option<&DatasetEntry> {
+    self.datasets.get(name)
+  }
+
+  /// Gets a dataset by name, or an error if it is not registered.
+  pub fn entry(
+    &self,
+    name: &str,
+  ) -> GenericResult<&DatasetEntry> {
+    self
+      .get(name)
+      .ok_or_else(|| GenericError::from(format!("Unknown dataset \"{name}\"")).into())
+  }
+
+  /// Gets the registered datasets, sorted by name.
+  pub fn entries(&self) -> impl Iterator<Item = &DatasetEntry> {
+    self.datasets.values()
+  }
+}
+
+/// Represents why an upload was rejected.
+#[derive(Debug)]
+pub enum UploadError {
+  /// The name or the file of the upload is invalid.
+  Invalid(String),
+  /// A dataset with the name of the upload is already registered.
+  Duplicate(String),
+  /// The server failed to save or register a valid upload, e.g. an I/O error.
+  Server(ApplicationError),
+}
+
+impl fmt::Display for UploadError {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match self {
+      Self::Invalid(message) => write!(f, "{message}"),
+      Self::Duplicate(name) => write!(f, "The dataset \"{name}\" already exists"),
+      Self::Server(error) => write!(f, "The upload could not be saved: {error}"),
+    }
+  }
+}
+
+impl From<ApplicationError> for UploadError {
+  fn from(value: ApplicationError) -> Self {
+    Self::Server(value)
+  }
+}
+
+impl From<std::io::Error> for UploadError {
+  fn from(value: std::io::Error) -> Self {
+    Self::Server(value.into())
+  }
+}
+
+impl IntoResponse for UploadError {
+  fn into_response(self) -> axum::response::Response {
+    let status = match self {
+      Self::Invalid(_) => StatusCode::BAD_REQUEST,
+      Self::Duplicate(_) => StatusCode::CONFLICT,
+      Self::Server(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, self.to_string()).into_response()
+  }
+}
+
+/// Represents an uploaded file that was validated and saved under a hidden name, waiting for
+/// `[DatasetRegistry::register_upload]`.
+#[derive(Clone, Debug)]
+pub struct SavedUpload {
+  /// Name of the new dataset.
+  pub name: String,
+  /// Format of the file.
+  pub format: DatasetFormat,
+  /// Hidden path where the file was saved.
+  pub pending_path: PathBuf,
+  /// Final path of the file once registered.
+  pub path: PathBuf,
+}
+
+/// Validates an uploaded file and saves it under a hidden name of the upload directory, so an
+/// invalid upload never shows up as a dataset. It reads and parses the whole file, so it runs
+/// without any lock of the registry.
+///
+/// # Arguments
+///
+/// * `upload_dir`: Directory of the uploaded datasets, see `[DatasetRegistry::upload_dir]`.
+/// * `name`: Name of the new dataset.
+/// * `file_name`: Original name of the file; its extension gives the format.
+/// * `content`: Content of the file.
+pub fn save_upload(
+  upload_dir: &Path,
+  name: &str,
+  file_name: &str,
+  content: &[u8],
+) -> Result<SavedUpload, UploadError> {
+  validate_dataset_name(name).map_err(|error| UploadError::Invalid(error.to_string()))?;
+  let format = Path::new(file_name)
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .and_then(DatasetFormat::from_extension)
+    .filter(|format| UPLOAD_FORMATS.contains(format))
+    .ok_or_else(|| {
+      UploadError::Invalid(format!(
+        "The file \"{file_name}\" must have a .csv or .parquet extension"
+      ))
+    })?;
+
+  // Concurrent uploads of the same name get their own pending file
+  static PENDING_UPLOADS: AtomicUsize = AtomicUsize::new(0);
+  let pending_index = PENDING_UPLOADS.fetch_add(1, Ordering::Relaxed);
+
+  fs::create_dir_all(upload_dir)?;
+  let extension = if format == DatasetFormat::Csv { "csv" } else { "parquet" };
+  let pending_path = upload_dir.join(format!(".{name}.{pending_index}.{extension}"));
+  fs::write(&pending_path, content)?;
+
+  let validation = match load_dataset_as(&pending_path.to_string_lossy(), format) {
+    Ok(df) if df.width() == 0 || df.height() == 0 => {
+      Err(UploadError::Invalid(format!("The file \"{file_name}\" has no data")))
+    }
+    Ok(_) => Ok(()),
+    Err(error) => Err(UploadError::Invalid(format!(
+      "The file \"{file_name}\" cannot be read: {error}"
+    ))),
+  };
+  if let Err(error) = validation {
+    fs::remove_file(&pending_path)?;
+    return Err(error);
+  }
+
+  Ok(SavedUpload {
+    name: name.to_string(),
+    format,
+    pending_path,
+    path: upload_dir.join(format!("{name}.{extension}")),
+  })
+}
+
+/// Checks that a dataset name is 1 to 64 lowercase letters, digits, `-` or `_`, so it is safe
+/// in URLs and file names.
+pub fn validate_dataset_name(name: &str) -> GenericResult<()> {
+  let valid = (1..=64).contains(&name.len())
+    && name
+      .chars()
+      .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+  if valid {
+    Ok(())
+  } else {
+    Err(
+      GenericError::from(format!(
+        "Invalid dataset name \"{name}\": use 1 to 64 lowercase letters, digits, \"-\" or \"_\""
+      ))
+      .into(),
+    )
+  }
+}
+
+/// Parses the name of a format, e.g. `"parquet"`.
+fn parse_format(format: &str) -> GenericResult<DatasetFormat> {
+  DatasetFormat::from_extension(format)
+    .ok_or_else(|| GenericError::from(format!("Unknown dataset format \"{format}\"")).into())
+}
+
+/// Builds an error of a configuration file.
+fn config_error(
+  path: &Path,
+  message: &str,
+) -> ApplicationError {
+  GenericError::from(format!(
+    "Invalid dataset configuration {}: {message}",
+    path.display()
+  ))
+  .into()
+}
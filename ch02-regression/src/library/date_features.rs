@@ -0,0 +1,238 @@
+use std::f64::consts::PI;
+
+use polars::export::chrono::{Datelike, NaiveDate, Weekday};
+use polars::prelude::*;
+
+/// Average number of days of a year, the period of the cyclic encodings of the day of year.
+pub const DAYS_PER_YEAR: f64 = 365.25;
+
+/// Day of the Unix epoch in the days of the common era, where January 1st of the year 1 is day
+/// 1, to convert the days of a polars date.
+const UNIX_EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// Represents a recurring date of the year used as an anchor, e.g. Halloween.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AnchorDate {
+  /// Same month and day every year, e.g. October 31st. February 29th only occurs in leap
+  /// years.
+  Fixed {
+    /// Month, 1 to 12.
+    month: u32,
+    /// Day of the month, 1 to 31.
+    day: u32,
+  },
+  /// The nth weekday of a month, e.g. the fourth Thursday of November.
+  NthWeekday {
+    /// Month, 1 to 12.
+    month: u32,
+    /// Day of the week.
+    weekday: Weekday,
+    /// Occurrence of the weekday in the month, 1 to 5. The fifth one does not occur every
+    /// year.
+    n: u8,
+  },
+}
+
+impl AnchorDate {
+  /// Halloween: October 31st.
+  pub fn halloween() -> Self {
+    Self::Fixed { month: 10, day: 31 }
+  }
+
+  /// US Thanksgiving: the fourth Thursday of November.
+  pub fn thanksgiving() -> Self {
+    Self::NthWeekday {
+      month: 11,
+      weekday: Weekday::Thu,
+      n: 4,
+    }
+  }
+
+  /// Gets the date of the anchor in a year, if it occurs that year.
+  pub fn in_year(
+    &self,
+    year: i32,
+  ) -> Option<NaiveDate> {
+    match *self {
+      Self::Fixed { month, day } => NaiveDate::from_ymd_opt(year, month, day),
+      Self::NthWeekday { month, weekday, n } => {
+        NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+      }
+    }
+  }
+
+  /// Gets the number of days from a date to the next occurrence of the anchor, 0 on the anchor
+  /// itself. `None` when the anchor does not occur in the next 8 years.
+  pub fn days_until(
+    &self,
+    date: NaiveDate,
+  ) -> Option<i64> {
+    (date.year()..=date.year() + 8)
+      .filter_map(|year| self.in_year(year))
+      .find(|anchor| *anchor >= date)
+      .map(|anchor| (anchor - date).num_days())
+  }
+
+  /// Gets the number of days from the last occurrence of the anchor to a date, 0 on the anchor
+  /// itself. `None` when the anchor did not occur in the previous 8 years.
+  pub fn days_since(
+    &self,
+    date: NaiveDate,
+  ) -> Option<i64> {
+    (date.year() - 8..=date.year())
+      .rev()
+      .filter_map(|year| self.in_year(year))
+      .find(|anchor| *anchor <= date)
+      .map(|anchor| (date - anchor).num_days())
+  }
+}
+
+/// Gets the day of the year of a date column, counted from 0 for January 1st, as `Float64`.
+/// The date column may be a `Date` or a `Datetime`.
+pub fn day_of_year(date: Expr) -> Expr {
+  date
+    .cast(DataType::Date)
+    .dt()
+    .ordinal_day()
+    .cast(DataType::Float64)
+    - lit(1.0)
+}
+
+/// Gets the month of a date column, 1 to 12, as `Float64`.
+pub fn month(date: Expr) -> Expr {
+  date
+    .cast(DataType::Date)
+    .dt()
+    .month()
+    .cast(DataType::Float64)
+}
+
+/// Gets the ISO 8601 week of the year of a date column, 1 to 53, as `Float64`.
+pub fn week_of_year(date: Expr) -> Expr {
+  date
+    .cast(DataType::Date)
+    .dt()
+    .week()
+    .cast(DataType::Float64)
+}
+
+/// Gets the ISO 8601 day of the week of a date column, 1 for Monday to 7 for Sunday, as
+/// `Float64`.
+pub fn day_of_week(date: Expr) -> Expr {
+  date
+    .cast(DataType::Date)
+    .dt()
+    .weekday()
+    .cast(DataType::Float64)
+}
+
+/// Gets 1.0 for the dates on a Saturday or a Sunday and 0.0 otherwise, as `Float64`.
+pub fn is_weekend(date: Expr) -> Expr {
+  day_of_week(date).gt_eq(lit(6.0)).cast(DataType::Float64)
+}
+
+/// Gets the number of days from a date column to the next occurrence of an anchor, 0 on the
+/// anchor itself, as `Float64`.
+///
+/// # Arguments
+///
+/// * `date`: Date column.
+/// * `anchor`: Recurring date, e.g. `[AnchorDate::halloween]`.
+pub fn days_until(
+  date: Expr,
+  anchor: AnchorDate,
+) -> Expr {
+  map_dates(date, move |date| anchor.days_until(date))
+}
+
+/// Gets the number of days from the last occurrence of an anchor to a date column, 0 on the
+/// anchor itself, as `Float64`.
+///
+/// # Arguments
+///
+/// * `date`: Date column.
+/// * `anchor`: Recurring date, e.g. `[AnchorDate::thanksgiving]`.
+pub fn days_since(
+  date: Expr,
+  anchor: AnchorDate,
+) -> Expr {
+  map_dates(date, move |date| anchor.days_since(date))
+}
+
+/// Gets the sine encoding of a cyclic feature, so the end of a cycle is close to its start,
+/// as `Float64`.
+///
+/// # Arguments
+///
+/// * `value`: Numeric feature, e.g. `[day_of_year]`.
+/// * `period`: Length of a cycle, e.g. `[DAYS_PER_YEAR]` or 12.0 for the month.
+pub fn cyclic_sin(
+  value: Expr,
+  period: f64,
+) -> Expr {
+  (value.cast(DataType::Float64) * lit(2.0 * PI / period)).sin()
+}
+
+/// Gets the cosine encoding of a cyclic feature, as `Float64`. Use it with `[cyclic_sin]`:
+/// together they give every point of the cycle a distinct position.
+///
+/// # Arguments
+///
+/// * `value`: Numeric feature, e.g. `[day_of_year]`.
+/// * `period`: Length of a cycle, e.g. `[DAYS_PER_YEAR]` or 12.0 for the month.
+pub fn cyclic_cos(
+  value: Expr,
+  period: f64,
+) -> Expr {
+  (value.cast(DataType::Float64) * lit(2.0 * PI / period)).cos()
+}
+
+/// Gets the calendar and seasonal features of a date column, named after the column, e.g.
+/// `"Date DayOfYear"`, `"Date DaysUntilHalloween"` or `"Date DayOfYear Sin"`.
+pub fn seasonal_features(date: &str) -> Vec<Expr> {
+  let feature = |expr: Expr, name: &str| expr.alias(&format!("{date} {name}"));
+  let halloween = AnchorDate::halloween();
+  let thanksgiving = AnchorDate::thanksgiving();
+
+  vec![
+    feature(day_of_year(col(date)), "DayOfYear"),
+    feature(week_of_year(col(date)), "WeekOfYear"),
+    feature(day_of_week(col(date)), "DayOfWeek"),
+    feature(is_weekend(col(date)), "IsWeekend"),
+    feature(days_until(col(date), halloween), "DaysUntilHalloween"),
+    feature(days_since(col(date), halloween), "DaysSinceHalloween"),
+    feature(days_until(col(date), thanksgiving), "DaysUntilThanksgiving"),
+    feature(days_since(col(date), thanksgiving), "DaysSinceThanksgiving"),
+    feature(
+      cyclic_sin(day_of_year(col(date)), DAYS_PER_YEAR),
+      "DayOfYear Sin",
+    ),
+    feature(
+      cyclic_cos(day_of_year(col(date)), DAYS_PER_YEAR),
+      "DayOfYear Cos",
+    ),
+  ]
+}
+
+/// Maps every date of a column to a number of days, as `Float64`.
+fn map_dates(
+  date: Expr,
+  days: impl Fn(NaiveDate) -> Option<i64> + Send + Sync + 'static,
+) -> Expr {
+  date.cast(DataType::Date).cast(DataType::Int32).map(
+    move |dates| {
+      let dates = dates.i32()?;
+      let mut values: Float64Chunked = dates
+        .into_iter()
+        .map(|date| {
+          let date =
+            NaiveDate::from_num_days_from_ce_opt(UNIX_EPOCH_DAYS_FROM_CE + date?)?;
+          days(date).map(|days| days as f64)
+        })
+        .collect();
+      values.rename(dates.name());
+      Ok(Some(values.into_series()))
+    },
+    GetOutput::from_type(DataType::Float64),
+  )
+}
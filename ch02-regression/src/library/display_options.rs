@@ -1,8 +1,28 @@
-use crate::application_error::GenericResult;
+use std::fs;
+use std::future::Future;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use serde_json::Value;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Seed of the shuffled samples when none is configured.
+pub const DEFAULT_SAMPLE_SEED: u64 = 42;
+
+/// Display options of the process, see `[DisplayOptions::set_process_default]`.
+static PROCESS_DEFAULT: OnceLock<DisplayOptions> = OnceLock::new();
+
+tokio::task_local! {
+  /// Seed of the shuffled samples requested for the current request, if any.
+  static REQUEST_SAMPLE_SEED: u64;
+}
 
 #[derive(Clone)]
 pub struct DisplayOptions {
   pub column_width: usize,
+  /// Seed of the shuffled samples, so a page shows the same rows on every refresh.
+  pub sample_seed: u64,
 }
 
 impl DisplayOptions {
@@ -20,12 +40,115 @@ impl DisplayOptions {
       options.column_width = column_width;
     }
 
+    if let Ok(sample_seed) = std::env::var("SAMPLE_SEED") {
+      options.sample_seed = parse_sample_seed(&sample_seed, "SAMPLE_SEED")?;
+    }
+
     Ok(options)
   }
+
+  /// Reads the `"sample_seed"` of a JSON configuration file, the one of
+  /// `[crate::dataset_registry::DatasetRegistry::from_config_file]`, e.g.
+  /// `{ "sample_seed": 7 }`. The options are unchanged when the file has no seed.
+  ///
+  /// # Arguments
+  ///
+  /// * `path`: Path of the configuration file.
+  pub fn with_config_file(
+    mut self,
+    path: &Path,
+  ) -> GenericResult<Self> {
+    let config: Value = serde_json::from_str(&fs::read_to_string(path)?).map_err(|error| {
+      GenericError::from(format!(
+        "Invalid configuration {}: {error}",
+        path.display()
+      ))
+    })?;
+
+    if let Some(sample_seed) = config.get("sample_seed") {
+      self.sample_seed = sample_seed.as_u64().ok_or_else(|| {
+        GenericError::from(format!(
+          "Invalid configuration {}: \"sample_seed\" must be an unsigned integer, found {sample_seed}",
+          path.display()
+        ))
+      })?;
+    }
+
+    Ok(self)
+  }
+
+  /// Sets the display options of the whole process, e.g. the ones read with
+  /// `[DisplayOptions::create_from_environment_variables]` at startup. They can only be set
+  /// once; later calls are ignored.
+  pub fn set_process_default(self) {
+    let _ = PROCESS_DEFAULT.set(self);
+  }
+
+  /// Gets the display options in effect: the ones of the process, with the sample seed of the
+  /// current request when it has one, see `[with_request_sample_seed]`.
+  pub fn current() -> Self {
+    let mut options = PROCESS_DEFAULT.get().cloned().unwrap_or_default();
+    if let Ok(sample_seed) = REQUEST_SAMPLE_SEED.try_with(|seed| *seed) {
+      options.sample_seed = sample_seed;
+    }
+    options
+  }
 }
 
 impl std::default::Default for DisplayOptions {
   fn default() -> Self {
-    Self { column_width: 100 }
+    Self {
+      column_width: 100,
+      sample_seed: DEFAULT_SAMPLE_SEED,
+    }
+  }
+}
+
+/// Runs a blocking computation, e.g. on `tokio::task::spawn_blocking` where the seed of the
+/// request is not inherited, with the given seed for the shuffled samples.
+///
+/// # Arguments
+///
+/// * `sample_seed`: Seed of the samples, usually `DisplayOptions::current().sample_seed`.
+/// * `compute`: Computation that creates the samples.
+pub fn with_sample_seed<R>(
+  sample_seed: u64,
+  compute: impl FnOnce() -> R,
+) -> R {
+  REQUEST_SAMPLE_SEED.sync_scope(sample_seed, compute)
+}
+
+/// Parses a seed of the shuffled samples, e.g. the value of `SAMPLE_SEED` or of a command line
+/// argument.
+///
+/// # Arguments
+///
+/// * `value`: Unsigned integer.
+/// * `source`: Where the value comes from, for the error message.
+pub fn parse_sample_seed(
+  value: &str,
+  source: &str,
+) -> GenericResult<u64> {
+  value.trim().parse::<u64>().map_err(|error| {
+    GenericError::from(format!(
+      "Invalid {source} \"{value}\": expected an unsigned integer ({error})"
+    ))
+    .into()
+  })
+}
+
+/// Runs a future, e.g. the handling of a request, with its own seed for the shuffled samples.
+///
+/// # Arguments
+///
+/// * `sample_seed`: Seed of the samples, or `None` to keep the one of the process.
+/// * `future`: Future that creates the samples.
+pub async fn with_request_sample_seed<F: Future>(
+  sample_seed: Option<u64>,
+  future: F,
+) -> F::Output {
+  match sample_seed {
+    Some(sample_seed) => REQUEST_SAMPLE_SEED.scope(sample_seed, future).await,
+    None => future.await,
   }
 }
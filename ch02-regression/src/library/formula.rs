@@ -0,0 +1,624 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+
+use ndarray::{Array1, Array2};
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Represents a factor of a formula term: a single column transformation.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Factor {
+  /// Numeric column used as it is, e.g. `DayOfYear`.
+  Numeric(String),
+  /// Polynomial expansion of a numeric column up to a degree, e.g. `poly(Month, 2)`.
+  Polynomial(String, u32),
+  /// Categorical column encoded with one indicator per level, e.g. `C(City Name)`.
+  Categorical(String),
+}
+
+impl Factor {
+  /// Parses a factor: `name`, `poly(name, degree)` or `C(name)`.
+  fn parse(text: &str) -> GenericResult<Self> {
+    let text = text.trim();
+
+    if let Some(arguments) = strip_call(text, "poly") {
+      let (name, degree) = arguments.rsplit_once(',').ok_or_else(|| {
+        GenericError::from(format!(
+          "Expected poly(column, degree) but found \"{text}\""
+        ))
+      })?;
+      let degree: u32 = degree.trim().parse()?;
+      if degree == 0 {
+        return Err(
+          GenericError::from(format!("The degree of \"{text}\" must be at least 1"))
+            .into(),
+        );
+      }
+      return Ok(Self::Polynomial(column_name(name, text)?, degree));
+    }
+
+    if let Some(arguments) = strip_call(text, "C") {
+      return Ok(Self::Categorical(column_name(arguments, text)?));
+    }
+
+    Ok(Self::Numeric(column_name(text, text)?))
+  }
+
+  /// Name of the source column.
+  pub fn column(&self) -> &str {
+    match self {
+      Self::Numeric(name) | Self::Polynomial(name, _) | Self::Categorical(name) => name,
+    }
+  }
+}
+
+impl fmt::Display for Factor {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match self {
+      Self::Numeric(name) => write!(f, "{name}"),
+      Self::Polynomial(name, degree) => write!(f, "poly({name}, {degree})"),
+      Self::Categorical(name) => write!(f, "C({name})"),
+    }
+  }
+}
+
+/// Represents a term of a formula: one factor, or the interaction (product) of several
+/// factors joined by `:`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Term {
+  /// Factors multiplied in the term.
+  pub factors: Vec<Factor>,
+}
+
+impl fmt::Display for Term {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let factors: Vec<String> = self
+      .factors
+      .iter()
+      .map(|factor| factor.to_string())
+      .collect();
+    write!(f, "{}", factors.join(":"))
+  }
+}
+
+/// Represents an R-style model formula, e.g.
+/// `Price ~ DayOfYear + poly(Month, 2) + C(Variety) + DayOfYear:C(City Name)`.
+///
+/// * `name` uses a numeric column as it is.
+/// * `poly(name, d)` adds the powers 1 to d of a numeric column.
+/// * `C(name)` adds one indicator column per level of a categorical column. Levels are sorted
+///   and the first one is the reference level (no column) when the term without the factor is
+///   already in the model, e.g. the intercept for a main effect, so the design keeps full rank.
+/// * `a:b` multiplies the columns of its factors.
+/// * `- 1` or `+ 0` removes the intercept, `+ 1` keeps it (the default).
+#[derive(Clone, Debug)]
+pub struct Formula {
+  /// Name of the response (output) column.
+  pub response: String,
+  /// Terms of the right-hand side, in order, without duplicates.
+  pub terms: Vec<Term>,
+  /// Whether the design matrix starts with an intercept column of ones.
+  pub intercept: bool,
+}
+
+/// Represents how a fitted formula handles a level of a categorical factor that was not seen
+/// at fit time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnseenLevel {
+  /// Fails the transformation.
+  Error,
+  /// Encodes the level with all-zero indicators, like the reference level.
+  Zero,
+}
+
+/// Represents a formula fitted on a training dataframe: the levels of its categorical factors
+/// and the names of its design columns. Any other dataframe, e.g. the test split, is then
+/// transformed into the same columns in the same order.
+#[derive(Clone, Debug)]
+pub struct FittedFormula {
+  /// Formula that was fitted.
+  pub formula: Formula,
+  /// Sorted levels of every categorical column at fit time, including the reference level.
+  pub levels: BTreeMap<String, Vec<String>>,
+  /// Name of every column of the design matrix.
+  pub column_names: Vec<String>,
+  /// Term that produced every column of the design matrix.
+  pub column_terms: Vec<String>,
+  /// How the levels that were not seen at fit time are handled.
+  pub unseen: UnseenLevel,
+}
+
+impl FittedFormula {
+  /// Sets how the levels that were not seen at fit time are handled.
+  pub fn unseen(
+    mut self,
+    unseen: UnseenLevel,
+  ) -> Self {
+    self.unseen = unseen;
+    self
+  }
+
+  /// Builds the design matrix and the response column of a dataframe with the fitted levels.
+  /// Rows with a null in any column used by the formula are dropped.
+  pub fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DesignMatrices> {
+    self.formula.build(df, &self.levels, self.unseen)
+  }
+}
+
+/// Represents the matrices built from a formula and a dataframe.
+#[derive(Clone, Debug)]
+pub struct DesignMatrices {
+  /// Design matrix X, one row per observation without nulls.
+  pub x: Array2<f64>,
+  /// Response column y, as a matrix of shape [n, 1].
+  pub y: Array2<f64>,
+  /// Name of every column of X, e.g. `"Intercept"`, `"Month^2"` or `"DayOfYear:City Name=BOSTON"`.
+  pub column_names: Vec<String>,
+  /// Term that produced every column of X, `"Intercept"` for the intercept.
+  pub column_terms: Vec<String>,
+  /// Number of rows dropped because a column used by the formula was null.
+  pub dropped_rows: usize,
+}
+
+impl Formula {
+  /// Parses a formula of the form `response ~ term + term + …`.
+  pub fn parse(formula: &str) -> GenericResult<Self> {
+    let (response, rhs) = formula.split_once('~').ok_or_else(|| {
+      GenericError::from(format!("The formula \"{formula}\" has no \"~\""))
+    })?;
+    let response = column_name(response, formula)?;
+
+    let mut terms: Vec<Term> = Vec::new();
+    let mut intercept = true;
+
+    for (sign, text) in split_top_level(rhs)? {
+      match (sign, text.as_str()) {
+        ('+', "1") => intercept = true,
+        ('-', "1") | ('+', "0") => intercept = false,
+        ('-', _) => {
+          return Err(
+            GenericError::from(format!(
+              "Only \"- 1\" can be subtracted, found \"- {text}\""
+            ))
+            .into(),
+          )
+        }
+        _ => {
+          let factors = text
+            .split(':')
+            .map(Factor::parse)
+            .collect::<GenericResult<Vec<Factor>>>()?;
+          let term = Term { factors };
+          if !terms.contains(&term) {
+            terms.push(term);
+          }
+        }
+      }
+    }
+
+    Ok(Self {
+      response,
+      terms,
+      intercept,
+    })
+  }
+
+  /// Gets the names of the terms, e.g. `["DayOfYear", "poly(Month, 2)"]`.
+  pub fn term_names(&self) -> Vec<String> {
+    self.terms.iter().map(|term| term.to_string()).collect()
+  }
+
+  /// Builds the design matrix and the response column from a dataframe. Rows with a null in any
+  /// column used by the formula are dropped. The levels come from the same dataframe: use
+  /// `[Formula::fit]` to build other data with them.
+  pub fn design_matrices(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DesignMatrices> {
+    Ok(self.fit_transform(df)?.1)
+  }
+
+  /// Fits the formula on a training dataframe, see `[FittedFormula]`.
+  pub fn fit(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<FittedFormula> {
+    Ok(self.fit_transform(df)?.0)
+  }
+
+  /// Fits the formula on a training dataframe and builds its design matrices.
+  pub fn fit_transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<(FittedFormula, DesignMatrices)> {
+    let data = self.select_complete_rows(df)?;
+
+    let mut levels: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for factor in self.terms.iter().flat_map(|term| term.factors.iter()) {
+      if let Factor::Categorical(name) = factor {
+        let values = data.column(name)?.cast(&DataType::Utf8)?;
+        let mut column_levels: Vec<String> = values
+          .utf8()?
+          .into_no_null_iter()
+          .map(str::to_string)
+          .collect();
+        column_levels.sort_unstable();
+        column_levels.dedup();
+        levels.insert(name.clone(), column_levels);
+      }
+    }
+
+    let design = self.build(df, &levels, UnseenLevel::Error)?;
+    let fitted = FittedFormula {
+      formula: self.clone(),
+      levels,
+      column_names: design.column_names.clone(),
+      column_terms: design.column_terms.clone(),
+      unseen: UnseenLevel::Error,
+    };
+
+    Ok((fitted, design))
+  }
+
+  /// Selects the columns used by the formula, response first, and drops the rows with a null.
+  fn select_complete_rows(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let mut columns: Vec<String> = vec![self.response.clone()];
+    for factor in self.terms.iter().flat_map(|term| term.factors.iter()) {
+      if !columns.iter().any(|name| name == factor.column()) {
+        columns.push(factor.column().to_string());
+      }
+    }
+
+    Ok(df.select(&columns)?.drop_nulls::<String>(None)?)
+  }
+
+  /// Builds the design matrices of a dataframe with the given levels of the categorical
+  /// factors.
+  fn build(
+    &self,
+    df: &DataFrame,
+    levels: &BTreeMap<String, Vec<String>>,
+    unseen: UnseenLevel,
+  ) -> GenericResult<DesignMatrices> {
+    let data = self.select_complete_rows(df)?;
+    let dropped_rows = df.height() - data.height();
+
+    let mut column_names: Vec<String> = Vec::new();
+    let mut column_terms: Vec<String> = Vec::new();
+    let mut design_columns: Vec<Array1<f64>> = Vec::new();
+
+    if self.intercept {
+      column_names.push("Intercept".to_string());
+      column_terms.push("Intercept".to_string());
+      design_columns.push(Array1::ones(data.height()));
+    }
+
+    let term_set: HashSet<&Term> = self.terms.iter().collect();
+
+    for term in &self.terms {
+      // Columns of the term: the product of every combination of the factor columns
+      let mut term_columns: Vec<(String, Array1<f64>)> =
+        vec![(String::new(), Array1::ones(data.height()))];
+
+      for (index, factor) in term.factors.iter().enumerate() {
+        let marginal = Term {
+          factors: [&term.factors[..index], &term.factors[index + 1..]].concat(),
+        };
+        let reduced = if marginal.factors.is_empty() {
+          self.intercept
+        } else {
+          term_set.contains(&marginal)
+        };
+
+        let factor_columns = factor_columns(&data, factor, levels, reduced, unseen)?;
+        term_columns = term_columns
+          .iter()
+          .flat_map(|(name, values)| {
+            factor_columns
+              .iter()
+              .map(move |(factor_name, factor_values)| {
+                let name = if name.is_empty() {
+                  factor_name.clone()
+                } else {
+                  format!("{name}:{factor_name}")
+                };
+                (name, values * factor_values)
+              })
+          })
+          .collect();
+      }
+
+      for (name, values) in term_columns {
+        column_names.push(name);
+        column_terms.push(term.to_string());
+        design_columns.push(values);
+      }
+    }
+
+    let mut x = Array2::<f64>::zeros((data.height(), design_columns.len()));
+    for (index, values) in design_columns.iter().enumerate() {
+      x.column_mut(index).assign(values);
+    }
+
+    let y = data
+      .select([self.response.as_str()])?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+
+    Ok(DesignMatrices {
+      x,
+      y,
+      column_names,
+      column_terms,
+      dropped_rows,
+    })
+  }
+}
+
+impl fmt::Display for Formula {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    let mut rhs = self.term_names();
+    if !self.intercept {
+      rhs.push("0".to_string());
+    }
+    write!(f, "{} ~ {}", self.response, rhs.join(" + "))
+  }
+}
+
+/// Builds the named columns of a single factor.
+///
+/// # Arguments
+///
+/// * `data`: Dataframe without nulls in the factor column.
+/// * `factor`: Factor to build.
+/// * `levels`: Fitted levels of every categorical column.
+/// * `reduced`: Whether a categorical factor drops its first level.
+/// * `unseen`: How the levels missing from `levels` are handled.
+fn factor_columns(
+  data: &DataFrame,
+  factor: &Factor,
+  levels: &BTreeMap<String, Vec<String>>,
+  reduced: bool,
+  unseen: UnseenLevel,
+) -> GenericResult<Vec<(String, Array1<f64>)>> {
+  match factor {
+    Factor::Numeric(name) => Ok(vec![(name.clone(), numeric_column(data, name)?)]),
+    Factor::Polynomial(name, degree) => {
+      let values = numeric_column(data, name)?;
+      Ok(
+        (1..=*degree as i32)
+          .map(|power| {
+            let column_name =
+              if power == 1 { name.clone() } else { format!("{name}^{power}") };
+            (column_name, values.mapv(|value| value.powi(power)))
+          })
+          .collect(),
+      )
+    }
+    Factor::Categorical(name) => {
+      let values = data.column(name)?.cast(&DataType::Utf8)?;
+      let values: Vec<&str> = values.utf8()?.into_no_null_iter().collect();
+
+      let levels = levels.get(name).ok_or_else(|| {
+        GenericError::from(format!("The levels of \"{name}\" were not fitted"))
+      })?;
+      if unseen == UnseenLevel::Error {
+        if let Some(value) = values
+          .iter()
+          .find(|value| !levels.iter().any(|level| level == *value))
+        {
+          return Err(
+            GenericError::from(format!(
+              "The level \"{value}\" of \"{name}\" was not seen when the formula was fitted"
+            ))
+            .into(),
+          );
+        }
+      }
+
+      let first_level = usize::from(reduced && !levels.is_empty());
+      Ok(
+        levels[first_level..]
+          .iter()
+          .map(|level| {
+            let indicator = values
+              .iter()
+              .map(|value| if value == level { 1.0 } else { 0.0 })
+              .collect();
+            (format!("{name}={level}"), indicator)
+          })
+          .collect(),
+      )
+    }
+  }
+}
+
+/// Gets a column of a dataframe as 64-bit floats.
+fn numeric_column(
+  data: &DataFrame,
+  name: &str,
+) -> GenericResult<Array1<f64>> {
+  let values = data.column(name)?.cast(&DataType::Float64)?;
+  Ok(values.f64()?.into_no_null_iter().collect())
+}
+
+/// Gets the arguments of a call such as `poly(Month, 2)`, if the text is a call of `function`.
+fn strip_call<'a>(
+  text: &'a str,
+  function: &str,
+) -> Option<&'a str> {
+  text
+    .strip_prefix(function)?
+    .trim_start()
+    .strip_prefix('(')?
+    .strip_suffix(')')
+}
+
+/// Trims a column name and checks it is not empty.
+fn column_name(
+  name: &str,
+  context: &str,
+) -> GenericResult<String> {
+  let name = name.trim();
+  if name.is_empty() {
+    return Err(
+      GenericError::from(format!("Missing column name in \"{context}\"")).into(),
+    );
+  }
+  Ok(name.to_string())
+}
+
+/// Splits the right-hand side of a formula into signed terms at the `+` and `-` outside
+/// parentheses.
+fn split_top_level(rhs: &str) -> GenericResult<Vec<(char, String)>> {
+  let mut terms: Vec<(char, String)> = Vec::new();
+  let mut sign = '+';
+  let mut current = String::new();
+  let mut depth = 0;
+
+  for character in rhs.chars() {
+    match character {
+      '(' => depth += 1,
+      ')' if depth == 0 => {
+        return Err(GenericError::from(format!("Unbalanced \")\" in \"{rhs}\"")).into())
+      }
+      ')' => depth -= 1,
+      '+' | '-' if depth == 0 => {
+        if !current.trim().is_empty() {
+          terms.push((sign, current.trim().to_string()));
+        }
+        sign = character;
+        current.clear();
+        continue;
+      }
+      _ => {}
+    }
+    current.push(character);
+  }
+
+  if depth != 0 {
+    return Err(GenericError::from(format!("Unbalanced \"(\" in \"{rhs}\"")).into());
+  }
+  if !current.trim().is_empty() {
+    terms.push((sign, current.trim().to_string()));
+  }
+  if terms.is_empty() {
+    return Err(GenericError::from("The formula has no terms").into());
+  }
+
+  Ok(terms)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Gets a frame with a response, a numeric column and a categorical column.
+  fn frame() -> DataFrame {
+    df!(
+      "y" => [1.0, 2.0, 3.0, 4.0],
+      "x" => [1.0, 2.0, 3.0, 4.0],
+      "c" => ["b", "a", "c", "a"],
+    )
+    .expect("valid frame")
+  }
+
+  /// Gets the column names of the design matrix of a formula over `[frame]`.
+  fn column_names(formula: &str) -> Vec<String> {
+    Formula::parse(formula)
+      .and_then(|formula| formula.design_matrices(&frame()))
+      .expect("valid formula")
+      .column_names
+  }
+
+  #[test]
+  fn poly_adds_the_powers_of_the_column() {
+    assert_eq!(
+      column_names("y ~ poly(x, 3)"),
+      ["Intercept", "x", "x^2", "x^3"]
+    );
+  }
+
+  #[test]
+  fn categorical_drops_the_reference_level_only_with_the_intercept() {
+    assert_eq!(column_names("y ~ C(c)"), ["Intercept", "c=b", "c=c"]);
+    assert_eq!(column_names("y ~ C(c) - 1"), ["c=a", "c=b", "c=c"]);
+  }
+
+  #[test]
+  fn interaction_multiplies_the_columns_of_its_factors() {
+    assert_eq!(
+      column_names("y ~ x + C(c) + x:C(c)"),
+      ["Intercept", "x", "c=b", "c=c", "x:c=b", "x:c=c"]
+    );
+    assert_eq!(
+      column_names("y ~ x:C(c)"),
+      ["Intercept", "x:c=a", "x:c=b", "x:c=c"]
+    );
+  }
+
+  #[test]
+  fn minus_one_and_plus_zero_remove_the_intercept() {
+    assert_eq!(column_names("y ~ x - 1"), ["x"]);
+    assert_eq!(column_names("y ~ x + 0"), ["x"]);
+    assert_eq!(column_names("y ~ x - 1 + 1"), ["Intercept", "x"]);
+  }
+
+  #[test]
+  fn unbalanced_parentheses_are_errors() {
+    assert!(Formula::parse("y ~ poly(x, 2").is_err());
+    assert!(Formula::parse("y ~ x)").is_err());
+  }
+
+  #[test]
+  fn invalid_formulas_are_errors() {
+    assert!(Formula::parse("y x").is_err());
+    assert!(Formula::parse("y ~ x - c").is_err());
+    assert!(Formula::parse("y ~ poly(x, 0)").is_err());
+    assert!(Formula::parse("y ~ poly(x)").is_err());
+    assert!(Formula::parse("y ~ ").is_err());
+  }
+
+  #[test]
+  fn transform_reuses_the_fitted_levels() {
+    let fitted = Formula::parse("y ~ C(c)")
+      .and_then(|formula| formula.fit(&frame()))
+      .expect("valid formula");
+    let test = df!("y" => [5.0], "c" => ["c"]).expect("valid frame");
+
+    let design = fitted.transform(&test).expect("seen level");
+    assert_eq!(design.column_names, fitted.column_names);
+    assert_eq!(design.x.row(0).to_vec(), [1.0, 0.0, 1.0]);
+  }
+
+  #[test]
+  fn transform_handles_the_unseen_levels() {
+    let fitted = Formula::parse("y ~ C(c)")
+      .and_then(|formula| formula.fit(&frame()))
+      .expect("valid formula");
+    let test = df!("y" => [5.0], "c" => ["d"]).expect("valid frame");
+
+    assert!(fitted.transform(&test).is_err());
+
+    let design = fitted
+      .unseen(UnseenLevel::Zero)
+      .transform(&test)
+      .expect("zero-filled");
+    assert_eq!(design.x.row(0).to_vec(), [1.0, 0.0, 0.0]);
+  }
+}
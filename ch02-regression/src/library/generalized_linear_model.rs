@@ -0,0 +1,481 @@
+#![allow(non_snake_case)]
+
+use linfa_linalg::qr::QRInto;
+use ndarray::{Array1, Array2, Axis, Zip};
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::regression_functions::RegressionModel;
+
+/// Represents the distribution family of the response variable of a GLM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Family {
+  /// Normal distribution, V(μ) = 1.
+  Gaussian,
+  /// Poisson distribution, V(μ) = μ.
+  Poisson,
+  /// Gamma distribution, V(μ) = μ².
+  Gamma,
+  /// Inverse Gaussian distribution, V(μ) = μ³.
+  InverseGaussian,
+  /// Log-normal distribution: a Gaussian model of ln y with the identity link.
+  LogNormal,
+}
+
+/// Represents the link function g(μ) = η = Xβ of a GLM.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Link {
+  /// g(μ) = μ.
+  Identity,
+  /// g(μ) = ln μ.
+  Log,
+  /// g(μ) = 1 / μ.
+  Inverse,
+}
+
+impl Family {
+  /// Gets the display name of the family.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Gaussian => "Gaussian",
+      Self::Poisson => "Poisson",
+      Self::Gamma => "Gamma",
+      Self::InverseGaussian => "Inverse Gaussian",
+      Self::LogNormal => "Log-normal",
+    }
+  }
+
+  /// Variance function V(μ).
+  fn variance(
+    &self,
+    μ: f64,
+  ) -> f64 {
+    match self {
+      Self::Gaussian | Self::LogNormal => 1.0,
+      Self::Poisson => μ,
+      Self::Gamma => μ.powi(2),
+      Self::InverseGaussian => μ.powi(3),
+    }
+  }
+
+  /// Unit deviance d(y, μ), so that the deviance is D = Σ d(yᵢ, μᵢ).
+  fn unit_deviance(
+    &self,
+    y: f64,
+    μ: f64,
+  ) -> f64 {
+    match self {
+      Self::Gaussian | Self::LogNormal => (y - μ).powi(2),
+      Self::Poisson => {
+        let y_log_y_μ = if y > 0.0 { y * (y / μ).ln() } else { 0.0 };
+        2.0 * (y_log_y_μ - (y - μ))
+      }
+      Self::Gamma => 2.0 * (-(y / μ).ln() + (y - μ) / μ),
+      Self::InverseGaussian => (y - μ).powi(2) / (μ.powi(2) * y),
+    }
+  }
+
+  /// Checks that the response values are in the support of the distribution.
+  fn validate(
+    &self,
+    y: &Array2<f64>,
+  ) -> GenericResult<()> {
+    let valid = match self {
+      Self::Gaussian => y.iter().all(|value| value.is_finite()),
+      Self::Poisson => y.iter().all(|value| value.is_finite() && *value >= 0.0),
+      Self::Gamma | Self::InverseGaussian | Self::LogNormal => {
+        y.iter().all(|value| value.is_finite() && *value > 0.0)
+      }
+    };
+
+    if valid {
+      Ok(())
+    } else {
+      Err(
+        GenericError::from(format!(
+          "The response values are outside the support of the {} family",
+          self.name()
+        ))
+        .into(),
+      )
+    }
+  }
+}
+
+impl Link {
+  /// Gets the display name of the link.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Identity => "identity",
+      Self::Log => "log",
+      Self::Inverse => "inverse",
+    }
+  }
+
+  /// Link function η = g(μ).
+  fn link(
+    &self,
+    μ: f64,
+  ) -> f64 {
+    match self {
+      Self::Identity => μ,
+      Self::Log => μ.ln(),
+      Self::Inverse => 1.0 / μ,
+    }
+  }
+
+  /// Inverse link function μ = g⁻¹(η).
+  fn inverse(
+    &self,
+    η: f64,
+  ) -> f64 {
+    match self {
+      Self::Identity => η,
+      Self::Log => η.exp(),
+      Self::Inverse => 1.0 / η,
+    }
+  }
+
+  /// Derivative of the link function g'(μ).
+  fn derivative(
+    &self,
+    μ: f64,
+  ) -> f64 {
+    match self {
+      Self::Identity => 1.0,
+      Self::Log => 1.0 / μ,
+      Self::Inverse => -1.0 / μ.powi(2),
+    }
+  }
+}
+
+/// Represents a generalized linear model g(E[y]) = Xβ fitted by iteratively reweighted least
+/// squares (IRLS).
+pub struct GeneralizedLinearModel {
+  /// Distribution family of the response variable.
+  pub family: Family,
+  /// Link function between the mean and the linear predictor.
+  pub link: Link,
+  /// Maximum number of IRLS iterations.
+  pub max_iterations: usize,
+  /// Convergence tolerance on the relative change of the deviance.
+  pub tolerance: f64,
+  /// Vector of fitted parameters.
+  pub β: Array2<f64>,
+  /// Deviance of the fitted model on the training data.
+  pub deviance: f64,
+  /// Akaike information criterion of the fitted model on the training data.
+  pub aic: f64,
+  /// Estimated dispersion φ (σ² for the Gaussian family, 1 for Poisson).
+  pub dispersion: f64,
+  /// Number of IRLS iterations run.
+  pub iterations: usize,
+  /// Whether IRLS converged within `max_iterations`.
+  pub converged: bool,
+}
+
+impl GeneralizedLinearModel {
+  /// Creates a new unfitted model of the given family and link.
+  ///
+  /// # Arguments
+  ///
+  /// * `family`: Distribution family of the response variable.
+  /// * `link`: Link function. The log-normal family always uses the identity link on ln y.
+  pub fn new(
+    family: Family,
+    link: Link,
+  ) -> Self {
+    Self {
+      family,
+      link: if family == Family::LogNormal { Link::Identity } else { link },
+      max_iterations: 100,
+      tolerance: 1e-8,
+      β: Array2::<f64>::zeros((1, 1)),
+      deviance: f64::NAN,
+      aic: f64::NAN,
+      dispersion: f64::NAN,
+      iterations: 0,
+      converged: false,
+    }
+  }
+
+  /// Gets a display name such as "Gamma (log link)".
+  pub fn name(&self) -> String {
+    format!("{} ({} link)", self.family.name(), self.link.name())
+  }
+
+  /// Fits the model on a polynomial design matrix built with `[RegressionModel::polyfit_data]`,
+  /// so its parameters are comparable with those of `[RegressionModel]`.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables.
+  /// * `y`: Vector of response (output) variables.
+  /// * `degree`: Degree of the polynomial expansion of `x`.
+  pub fn fit_polynomial(
+    &mut self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+    degree: i32,
+  ) -> GenericResult<()> {
+    self.fit(&RegressionModel::polyfit_data(x, degree), y)
+  }
+
+  /// Fits the model by iteratively reweighted least squares.
+  ///
+  /// # Arguments
+  ///
+  /// * `X`: Design matrix, including the intercept column.
+  /// * `y`: Vector of response (output) variables.
+  pub fn fit(
+    &mut self,
+    X: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> GenericResult<()> {
+    self.family.validate(y)?;
+
+    // The log-normal model is a Gaussian model of ln y
+    let y: Array1<f64> = match self.family {
+      Family::LogNormal => y.column(0).mapv(f64::ln),
+      _ => y.column(0).to_owned(),
+    };
+    let family = match self.family {
+      Family::LogNormal => Family::Gaussian,
+      family => family,
+    };
+
+    // Start from the observed values, moved away from the boundary of the support
+    let y_mean = y.mean().unwrap_or(1.0);
+    let mut μ: Array1<f64> = match family {
+      Family::Gaussian => y.clone(),
+      _ => y.mapv(|value| (value + y_mean) / 2.0),
+    };
+    let mut η: Array1<f64> = μ.mapv(|value| self.link.link(value));
+    let mut deviance = Self::sum_deviance(family, &y, &μ);
+
+    self.converged = false;
+    self.iterations = 0;
+
+    while self.iterations < self.max_iterations {
+      self.iterations += 1;
+
+      // Working response z = η + (y - μ)g'(μ) and weights w = 1 / (g'(μ)² V(μ))
+      let mut z = Array1::<f64>::zeros(y.len());
+      let mut w = Array1::<f64>::zeros(y.len());
+      Zip::from(&mut z)
+        .and(&mut w)
+        .and(&η)
+        .and(&μ)
+        .and(&y)
+        .for_each(|z, w, η, μ, y| {
+          let derivative = self.link.derivative(*μ);
+          *z = η + (y - μ) * derivative;
+          *w = 1.0 / (derivative.powi(2) * family.variance(*μ));
+        });
+
+      // β = (XᵀWX)⁻¹XᵀWz
+      let XtW = (X * &w.clone().insert_axis(Axis(1))).reversed_axes();
+      let β_new = XtW
+        .dot(X)
+        .qr_into()?
+        .inverse()?
+        .dot(&XtW)
+        .dot(&z.insert_axis(Axis(1)));
+
+      // Halve the step while the mean leaves the support or the deviance grows
+      let mut β_step = β_new;
+      let mut accepted = false;
+      for _ in 0..30 {
+        let η_new = X.dot(&β_step).column(0).to_owned();
+        let μ_new = η_new.mapv(|value| self.link.inverse(value));
+        let deviance_new = Self::sum_deviance(family, &y, &μ_new);
+
+        if deviance_new.is_finite()
+          && μ_new.iter().all(|value| Self::in_support(family, *value))
+          && (self.iterations == 1 || deviance_new <= deviance * (1.0 + 1e-10))
+        {
+          let change = (deviance - deviance_new).abs() / (deviance_new.abs() + 0.1);
+          self.β = β_step;
+          η = η_new;
+          μ = μ_new;
+          self.converged = change < self.tolerance;
+          deviance = deviance_new;
+          accepted = true;
+          break;
+        }
+
+        if self.iterations == 1 {
+          break;
+        }
+        β_step = (&β_step + &self.β) / 2.0;
+      }
+
+      if !accepted {
+        return Err(
+          GenericError::from(format!(
+            "IRLS could not find a valid step for the {} model",
+            self.name()
+          ))
+          .into(),
+        );
+      }
+
+      if self.converged {
+        break;
+      }
+    }
+
+    self.deviance = deviance;
+    self.update_dispersion_and_aic(family, &y, &μ, X.ncols());
+
+    Ok(())
+  }
+
+  /// Predicts the mean response μ = g⁻¹(Xβ) for the given design matrix.
+  pub fn predict(
+    &self,
+    X: &Array2<f64>,
+  ) -> Array2<f64> {
+    let η = X.dot(&self.β);
+    match self.family {
+      // E[y] of a log-normal variable is exp(μ + σ²/2)
+      Family::LogNormal => η.mapv(|value| (value + self.dispersion / 2.0).exp()),
+      _ => η.mapv(|value| self.link.inverse(value)),
+    }
+  }
+
+  /// Predicts the mean response for a matrix of explanatory variables, expanded as a polynomial
+  /// of the given degree.
+  pub fn predict_polynomial(
+    &self,
+    x: &Array2<f64>,
+    degree: i32,
+  ) -> Array2<f64> {
+    self.predict(&RegressionModel::polyfit_data(x, degree))
+  }
+
+  /// Calculates the mean squared error of the predicted mean response.
+  pub fn mse(
+    &self,
+    X: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> f64 {
+    let e = y - &self.predict(X);
+    e.iter().map(|value| value.powi(2)).sum::<f64>() / (X.nrows() as f64)
+  }
+
+  /// Sums the unit deviances of the given responses and means.
+  fn sum_deviance(
+    family: Family,
+    y: &Array1<f64>,
+    μ: &Array1<f64>,
+  ) -> f64 {
+    y.iter()
+      .zip(μ.iter())
+      .map(|(y, μ)| family.unit_deviance(*y, *μ))
+      .sum()
+  }
+
+  /// Checks whether a mean value is valid for the given family.
+  fn in_support(
+    family: Family,
+    μ: f64,
+  ) -> bool {
+    match family {
+      Family::Gaussian | Family::LogNormal => μ.is_finite(),
+      _ => μ.is_finite() && μ > 0.0,
+    }
+  }
+
+  /// Estimates the dispersion (by maximum likelihood, as R does for the AIC) and calculates
+  /// AIC = -2 ln L + 2k, where k counts the parameters β and the dispersion when estimated.
+  fn update_dispersion_and_aic(
+    &mut self,
+    family: Family,
+    y: &Array1<f64>,
+    μ: &Array1<f64>,
+    n_parameters: usize,
+  ) {
+    let n = y.len() as f64;
+    let φ = self.deviance / n;
+
+    let (log_likelihood, k) = match family {
+      Family::Gaussian => {
+        let log_likelihood = -n / 2.0 * ((2.0 * std::f64::consts::PI * φ).ln() + 1.0);
+        (log_likelihood, n_parameters + 1)
+      }
+      Family::Poisson => {
+        let log_likelihood = y
+          .iter()
+          .zip(μ.iter())
+          .map(|(y, μ)| y * μ.ln() - μ - ln_gamma(y + 1.0))
+          .sum();
+        (log_likelihood, n_parameters)
+      }
+      Family::Gamma => {
+        let shape = 1.0 / φ;
+        let log_likelihood = y
+          .iter()
+          .zip(μ.iter())
+          .map(|(y, μ)| {
+            shape * (shape * y / μ).ln() - shape * y / μ - y.ln() - ln_gamma(shape)
+          })
+          .sum();
+        (log_likelihood, n_parameters + 1)
+      }
+      Family::InverseGaussian => {
+        let log_likelihood = y
+          .iter()
+          .zip(μ.iter())
+          .map(|(y, μ)| {
+            -0.5
+              * ((2.0 * std::f64::consts::PI * φ * y.powi(3)).ln()
+                + (y - μ).powi(2) / (φ * y * μ.powi(2)))
+          })
+          .sum();
+        (log_likelihood, n_parameters + 1)
+      }
+      Family::LogNormal => unreachable!("the log-normal family is fitted as Gaussian"),
+    };
+
+    self.dispersion = match family {
+      Family::Poisson => 1.0,
+      _ => φ,
+    };
+
+    // The log-normal likelihood of y is the Gaussian likelihood of ln y minus Σ ln y
+    self.aic = match self.family {
+      Family::LogNormal => -2.0 * (log_likelihood - y.sum()) + 2.0 * k as f64,
+      _ => -2.0 * log_likelihood + 2.0 * k as f64,
+    };
+  }
+}
+
+/// Natural logarithm of the gamma function, by the Lanczos approximation (g = 7, n = 9).
+fn ln_gamma(x: f64) -> f64 {
+  const COEFFICIENTS: [f64; 9] = [
+    0.999_999_999_999_809_9,
+    676.520_368_121_885_1,
+    -1_259.139_216_722_402_8,
+    771.323_428_777_653_1,
+    -176.615_029_162_140_6,
+    12.507_343_278_686_905,
+    -0.138_571_095_265_720_12,
+    9.984_369_578_019_572e-6,
+    1.505_632_735_149_311_6e-7,
+  ];
+
+  if x < 0.5 {
+    // Reflection formula: Γ(x)Γ(1 - x) = π / sin(πx)
+    let π = std::f64::consts::PI;
+    return (π / (π * x).sin()).ln() - ln_gamma(1.0 - x);
+  }
+
+  let x = x - 1.0;
+  let t = x + 7.5;
+  let series = COEFFICIENTS[1..]
+    .iter()
+    .enumerate()
+    .fold(COEFFICIENTS[0], |sum, (index, c)| sum + c / (x + index as f64 + 1.0));
+
+  0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
@@ -23,11 +23,7 @@ pub fn html_dataframe(
   options: Option<SampleOptions>,
 ) -> GenericResult<Markup> {
   // If no options were provided, then create default options.
-  let mut df_options = if let Some(options) = options.clone() {
-    options
-  } else {
-    SampleOptions::default()
-  };
+  let mut df_options = options.clone().unwrap_or_default();
 
   // The requested sample cannot be greater than the total number of rows.
   if df_options.sample_size > df.height() {
@@ -45,7 +41,7 @@ pub fn html_dataframe(
         df.slice(0, df_options.sample_size)
       } else {
         // The user wants a sample of rows in random order, so get an random slice from the dataset
-        df.sample_n(df_options.sample_size, false, true, None)?
+        df.sample_n(df_options.sample_size, false, true, Some(df_options.seed))?
       }
     }
   };
@@ -54,7 +50,7 @@ pub fn html_dataframe(
     div .tblcon {
     table .dataframe-table {
       // Show a caption with metadata about the dataframe
-      caption { ( format!("Dataframe info: rows: {0}, columns: {1}. Showing: {2} rows. Suffle: {3}", df.height(), df.width(), df_options.sample_size , (if df_options.shuffle { "Yes"} else { "No "}) ) ) }
+      caption { ( format!("Dataframe info: rows: {0}, columns: {1}. Showing: {2} rows. Suffle: {3}", df.height(), df.width(), df_options.sample_size , (if df_options.shuffle { format!("Yes, seed: {}", df_options.seed) } else { "No".to_string() }) ) ) }
 
       // Display the table headers containing two rows:
       // - Field name
@@ -121,7 +117,7 @@ pub fn create_tbody(
 fn format_series_value(
   serie: &Series,
   index: usize,
-) -> GenericResult<Cow<str>> {
+) -> GenericResult<Cow<'_, str>> {
   let out = match serie.0.get(index)? {
     AnyValue::Utf8(s) => {
       let value = Cow::Borrowed(s);
@@ -135,14 +131,6 @@ fn format_series_value(
     AnyValue::Float64(value) => Cow::Owned(format!("{:.3}", value)),
     AnyValue::Decimal(value, _size) => Cow::Owned(format!("{:.3}", value)),
     AnyValue::Null => Cow::Borrowed("<span class=\"null-value\">null</span>"),
-    #[cfg(feature = "dtype-categorical")]
-    AnyValue::Categorical(idx, rev, arr) => {
-      if arr.is_null() {
-        Cow::Borrowed(rev.get(idx))
-      } else {
-        unsafe { Cow::Borrowed(arr.deref_unchecked().value(idx as usize)) }
-      }
-    }
     av => Cow::Owned(format!("{av}")),
   };
   Ok(out)
@@ -0,0 +1,455 @@
+use ndarray::Array2;
+use polars::export::rayon::prelude::*;
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+use crate::application_error::GenericResult;
+use crate::cross_validation::{mean_std, select_rows, shuffled_k_fold, TrainTestSplit};
+use crate::regression_functions::RegressionModel;
+
+/// Name of the leaderboard column holding the rank of the candidate.
+pub const COL_RANK: &str = "Rank";
+/// Name of the leaderboard column holding the polynomial degree.
+pub const COL_DEGREE: &str = "Degree";
+/// Name of the leaderboard column holding the ridge regularization strength.
+pub const COL_RIDGE_LAMBDA: &str = "Ridge λ";
+/// Name of the leaderboard column holding the train/test split ratio.
+pub const COL_SPLIT_RATIO: &str = "Split Ratio";
+/// Name of the leaderboard column holding the feature subset.
+pub const COL_FEATURES: &str = "Features";
+/// Name of the leaderboard column holding the mean cross-validation MSE (the score).
+pub const COL_CV_MSE: &str = "CV MSE";
+/// Name of the leaderboard column holding the standard deviation of the cross-validation MSE.
+pub const COL_CV_MSE_STD: &str = "CV MSE Std";
+/// Name of the leaderboard column holding the MSE on the held-out test split.
+pub const COL_TEST_MSE: &str = "Test MSE";
+
+/// Represents the space of hyperparameters explored by a search.
+#[derive(Clone)]
+pub struct ParameterSpace {
+  /// Degrees of the polynomial regression.
+  pub degrees: Vec<i32>,
+  /// Ridge (L2) regularization strengths.
+  pub ridge_lambdas: Vec<f64>,
+  /// Ratios of the rows used for training; the rest is the held-out test split.
+  pub split_ratios: Vec<f32>,
+  /// Subsets of the dataframe columns used as explanatory variables.
+  pub feature_subsets: Vec<Vec<String>>,
+}
+
+impl ParameterSpace {
+  /// Lists every combination of the hyperparameters of this space.
+  pub fn candidates(&self) -> Vec<Candidate> {
+    let mut candidates: Vec<Candidate> = Vec::new();
+
+    for features in self.feature_subsets.iter() {
+      for split_ratio in self.split_ratios.iter() {
+        for degree in self.degrees.iter() {
+          for ridge_lambda in self.ridge_lambdas.iter() {
+            candidates.push(Candidate {
+              degree: *degree,
+              ridge_lambda: *ridge_lambda,
+              split_ratio: *split_ratio,
+              features: features.clone(),
+            });
+          }
+        }
+      }
+    }
+
+    candidates
+  }
+}
+
+/// Represents one combination of hyperparameters evaluated by a search.
+#[derive(Clone, Debug)]
+pub struct Candidate {
+  /// Degree of the polynomial regression.
+  pub degree: i32,
+  /// Ridge (L2) regularization strength.
+  pub ridge_lambda: f64,
+  /// Ratio of the rows used for training.
+  pub split_ratio: f32,
+  /// Columns used as explanatory variables.
+  pub features: Vec<String>,
+}
+
+/// Represents how the candidates of a parameter space are chosen.
+#[derive(Clone, Copy, Debug)]
+pub enum SearchStrategy {
+  /// Evaluates every combination of the parameter space.
+  Grid,
+  /// Evaluates the given number of combinations sampled without replacement.
+  Random(usize),
+}
+
+/// Represents options for a hyperparameter search.
+#[derive(Clone)]
+pub struct SearchOptions {
+  /// How the candidates are chosen.
+  pub strategy: SearchStrategy,
+  /// Number of cross-validation folds over the training split.
+  pub cv_folds: usize,
+  /// Seed for sampling the candidates and shuffling the folds.
+  pub seed: u64,
+}
+
+impl SearchOptions {
+  /// Creates a new instance of `[SearchOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these search options.
+  pub fn builder() -> SearchOptionsBuilder {
+    SearchOptionsBuilder::default()
+  }
+}
+
+impl Default for SearchOptions {
+  fn default() -> Self {
+    SearchOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[SearchOptions]`.
+pub struct SearchOptionsBuilder {
+  /// How the candidates are chosen.
+  pub strategy: SearchStrategy,
+  /// Number of cross-validation folds over the training split.
+  pub cv_folds: usize,
+  /// Seed for sampling the candidates and shuffling the folds.
+  pub seed: u64,
+}
+
+impl SearchOptionsBuilder {
+  /// Creates a new instance of `[SearchOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets how the candidates are chosen.
+  pub fn strategy(
+    mut self,
+    strategy: SearchStrategy,
+  ) -> Self {
+    self.strategy = strategy;
+    self
+  }
+
+  /// Sets the number of cross-validation folds.
+  pub fn cv_folds(
+    mut self,
+    cv_folds: usize,
+  ) -> Self {
+    self.cv_folds = cv_folds;
+    self
+  }
+
+  /// Sets the seed for sampling the candidates and shuffling the folds.
+  pub fn seed(
+    mut self,
+    seed: u64,
+  ) -> Self {
+    self.seed = seed;
+    self
+  }
+
+  /// Builds the instance of `[SearchOptions]`.
+  pub fn build(self) -> SearchOptions {
+    SearchOptions {
+      strategy: self.strategy,
+      cv_folds: self.cv_folds,
+      seed: self.seed,
+    }
+  }
+}
+
+impl Default for SearchOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      strategy: SearchStrategy::Grid,
+      cv_folds: 5,
+      seed: 42,
+    }
+  }
+}
+
+/// Scores of one evaluated candidate.
+struct CandidateScore {
+  cv_mse: Option<f64>,
+  cv_mse_std: Option<f64>,
+  test_mse: Option<f64>,
+}
+
+/// Searches the hyperparameters of a polynomial (ridge) regression over a dataframe.
+///
+/// Every candidate is scored by the mean validation MSE of a k-fold cross-validation over its
+/// training split, and the candidates are evaluated in parallel. The same seed always yields
+/// the same candidates, folds and leaderboard. The rows with a null in the target or in any
+/// feature of the space are dropped first, so every candidate sees the same rows.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe holding the feature columns and the target column.
+/// * `target`: Name of the response (output) column.
+/// * `space`: Space of hyperparameters to explore.
+/// * `options`: Options of the search.
+///
+/// # Returns
+///
+/// A leaderboard dataframe sorted by `[COL_CV_MSE]` (best first). Candidates that could not be
+/// fitted have null scores and are listed last.
+pub fn hyperparameter_search(
+  df: &DataFrame,
+  target: &str,
+  space: &ParameterSpace,
+  options: &SearchOptions,
+) -> GenericResult<DataFrame> {
+  let mut candidates = space.candidates();
+
+  if let SearchStrategy::Random(n_iter) = options.strategy {
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    let n_iter = n_iter.min(candidates.len());
+    let mut sampled_indexes =
+      rand::seq::index::sample(&mut rng, candidates.len(), n_iter).into_vec();
+    sampled_indexes.sort_unstable();
+    candidates = sampled_indexes
+      .into_iter()
+      .map(|index| candidates[index].clone())
+      .collect();
+  }
+
+  let df = complete_rows(df, target, space)?;
+  let y = df
+    .select([target])?
+    .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+
+  // Extract the feature matrices once per distinct subset, before the parallel evaluation
+  let mut feature_matrices: Vec<(Vec<String>, Array2<f64>)> = Vec::new();
+  for features in space.feature_subsets.iter() {
+    let x = df
+      .select(features)?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+    feature_matrices.push((features.clone(), x));
+  }
+
+  let scores: Vec<CandidateScore> = candidates
+    .par_iter()
+    .map(|candidate| {
+      let x = feature_matrices
+        .iter()
+        .find(|(features, _)| *features == candidate.features)
+        .map(|(_, x)| x)
+        .expect("every candidate comes from a known feature subset");
+      score_candidate(x, &y, candidate, options)
+    })
+    .collect();
+
+  let leaderboard = DataFrame::new(vec![
+    Series::new(
+      COL_DEGREE,
+      candidates.iter().map(|c| c.degree).collect::<Vec<i32>>(),
+    ),
+    Series::new(
+      COL_RIDGE_LAMBDA,
+      candidates.iter().map(|c| c.ridge_lambda).collect::<Vec<f64>>(),
+    ),
+    Series::new(
+      COL_SPLIT_RATIO,
+      candidates.iter().map(|c| c.split_ratio).collect::<Vec<f32>>(),
+    ),
+    Series::new(
+      COL_FEATURES,
+      candidates
+        .iter()
+        .map(|c| c.features.join(", "))
+        .collect::<Vec<String>>(),
+    ),
+    Series::new(
+      COL_CV_MSE,
+      scores.iter().map(|s| s.cv_mse).collect::<Vec<Option<f64>>>(),
+    ),
+    Series::new(
+      COL_CV_MSE_STD,
+      scores.iter().map(|s| s.cv_mse_std).collect::<Vec<Option<f64>>>(),
+    ),
+    Series::new(
+      COL_TEST_MSE,
+      scores.iter().map(|s| s.test_mse).collect::<Vec<Option<f64>>>(),
+    ),
+  ])?;
+
+  let mut leaderboard = leaderboard
+    .lazy()
+    .sort(
+      COL_CV_MSE,
+      SortOptions {
+        descending: false,
+        nulls_last: true,
+        maintain_order: true,
+        multithreaded: true,
+      },
+    )
+    .collect()?;
+
+  let ranks: Vec<u32> = (1..=leaderboard.height() as u32).collect();
+  leaderboard.insert_at_idx(0, Series::new(COL_RANK, ranks))?;
+
+  Ok(leaderboard)
+}
+
+/// Gets the rows of a dataframe as every candidate of a search sees them, so its folds can be
+/// reproduced elsewhere. For every split ratio and fold, the rows without nulls are labeled
+/// like `[TrainTestSplit::label_rows]`, with a `Split` of `"train"`, `"validation"` or
+/// `"test"` (the held-out test split, the same for every fold).
+///
+/// # Arguments
+///
+/// * `df`: Dataframe given to `[hyperparameter_search]`.
+/// * `target`: Name of the response (output) column.
+/// * `space`: Space of hyperparameters explored by the search; only the split ratios matter.
+/// * `options`: Options of the search; only the folds and the seed matter.
+///
+/// # Returns
+///
+/// A dataframe with the columns `[COL_SPLIT_RATIO]`, Fold, Row and Split first, and then the
+/// columns of `df`.
+pub fn search_folds(
+  df: &DataFrame,
+  target: &str,
+  space: &ParameterSpace,
+  options: &SearchOptions,
+) -> GenericResult<DataFrame> {
+  let df = &complete_rows(df, target, space)?;
+  let mut split_ratios: Vec<f32> = Vec::new();
+  for split_ratio in space.split_ratios.iter() {
+    if !split_ratios.contains(split_ratio) {
+      split_ratios.push(*split_ratio);
+    }
+  }
+
+  let mut result: Option<DataFrame> = None;
+
+  for split_ratio in split_ratios {
+    // The same sequential split and shuffled folds as `score_candidate`
+    let split = TrainTestSplit::sequential(df.height(), split_ratio);
+    let folds = shuffled_k_fold(split.train_indexes.len(), options.cv_folds, options.seed);
+
+    for (fold_index, fold) in folds.iter().enumerate() {
+      // The training split holds the first rows, so its indexes are the rows of `df`
+      let indexes: Vec<u32> = fold
+        .train_indexes
+        .iter()
+        .chain(&fold.validation_indexes)
+        .chain(&split.test_indexes)
+        .map(|index| *index as u32)
+        .collect();
+      let splits: Vec<&str> = [
+        vec!["train"; fold.train_indexes.len()],
+        vec!["validation"; fold.validation_indexes.len()],
+        vec!["test"; split.test_indexes.len()],
+      ]
+      .concat();
+
+      let rows = df.take(&IdxCa::from_vec("Row", indexes.clone()))?;
+      let mut columns = vec![
+        Series::new(COL_SPLIT_RATIO, vec![split_ratio; indexes.len()]),
+        Series::new("Fold", vec![fold_index as u32 + 1; indexes.len()]),
+        Series::new("Row", indexes),
+        Series::new("Split", splits),
+      ];
+      columns.extend(rows.get_columns().iter().cloned());
+      let labeled = DataFrame::new(columns)?;
+
+      match result.as_mut() {
+        Some(result) => {
+          result.vstack_mut(&labeled)?;
+        }
+        None => result = Some(labeled),
+      }
+    }
+  }
+
+  Ok(result.unwrap_or_default())
+}
+
+/// Drops the rows with a null in the target or in any feature of the space.
+fn complete_rows(
+  df: &DataFrame,
+  target: &str,
+  space: &ParameterSpace,
+) -> GenericResult<DataFrame> {
+  let mut columns: Vec<String> = vec![target.to_string()];
+  for feature in space.feature_subsets.iter().flatten() {
+    if !columns.contains(feature) {
+      columns.push(feature.clone());
+    }
+  }
+  Ok(df.drop_nulls(Some(&columns))?)
+}
+
+/// Cross-validates a candidate on its training split and scores it on its test split.
+fn score_candidate(
+  x: &Array2<f64>,
+  y: &Array2<f64>,
+  candidate: &Candidate,
+  options: &SearchOptions,
+) -> CandidateScore {
+  let (x_train, x_test) = RegressionModel::split_data(x, candidate.split_ratio);
+  let (y_train, y_test) = RegressionModel::split_data(y, candidate.split_ratio);
+
+  let mut fold_errors: Vec<f64> = Vec::new();
+
+  for fold in shuffled_k_fold(x_train.nrows(), options.cv_folds, options.seed) {
+    let mut model = RegressionModel::new(
+      select_rows(&x_train, &fold.train_indexes),
+      select_rows(&y_train, &fold.train_indexes),
+      1.0,
+    )
+    .ridge(candidate.ridge_lambda);
+
+    if model.try_solve(candidate.degree).is_err() {
+      continue;
+    }
+
+    let x_validation = RegressionModel::polyfit_data(
+      &select_rows(&x_train, &fold.validation_indexes),
+      candidate.degree,
+    );
+    let y_validation = select_rows(&y_train, &fold.validation_indexes);
+    let mse = model.mse(&x_validation, &y_validation);
+
+    if mse.is_finite() {
+      fold_errors.push(mse);
+    }
+  }
+
+  if fold_errors.is_empty() {
+    return CandidateScore {
+      cv_mse: None,
+      cv_mse_std: None,
+      test_mse: None,
+    };
+  }
+
+  let (cv_mse, cv_mse_std) = mean_std(&fold_errors);
+
+  // Refit on the whole training split to score the held-out test split
+  let mut model = RegressionModel::new(x_train, y_train, 1.0).ridge(candidate.ridge_lambda);
+  let test_mse = if x_test.nrows() > 0 && model.try_solve(candidate.degree).is_ok() {
+    Some(model.mse(
+      &RegressionModel::polyfit_data(&x_test, candidate.degree),
+      &y_test,
+    ))
+  } else {
+    None
+  };
+
+  CandidateScore {
+    cv_mse: Some(cv_mse),
+    cv_mse_std: Some(cv_mse_std),
+    test_mse,
+  }
+}
@@ -0,0 +1,436 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use ndarray::{Array1, Array2};
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::knn_regression::{KnnOptions, KnnRegressor};
+
+/// Represents a value used to fill the nulls of a column.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImputeValue {
+  /// Number, for numeric columns.
+  Number(f64),
+  /// Text, for text (categorical) columns.
+  Text(String),
+}
+
+impl fmt::Display for ImputeValue {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    match self {
+      Self::Number(value) => write!(f, "{value:.3}"),
+      Self::Text(value) => write!(f, "{value}"),
+    }
+  }
+}
+
+/// Represents how the nulls of a column are filled.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImputationStrategy {
+  /// Mean of the fitted values. Numeric columns only.
+  Mean,
+  /// Median of the fitted values. Numeric columns only.
+  Median,
+  /// Most frequent fitted value; ties go to the smallest value.
+  Mode,
+  /// A constant value.
+  Constant(ImputeValue),
+  /// Last non-null value of the same group in date order. Leading nulls of a group stay null.
+  ForwardFill {
+    /// Columns that define the groups, e.g. `["City Name"]`.
+    group_by: Vec<String>,
+    /// Column that orders the rows of a group, e.g. `"Date"`.
+    order_by: String,
+  },
+  /// Mean of the `k` fitted rows closest in the given numeric features, standardized. Rows with
+  /// a null feature stay null. Numeric columns only.
+  Knn {
+    /// Number of neighbours.
+    k: usize,
+    /// Names of the numeric feature columns.
+    features: Vec<String>,
+  },
+}
+
+impl ImputationStrategy {
+  /// Gets the display name of the strategy.
+  pub fn name(&self) -> String {
+    match self {
+      Self::Mean => "Mean".to_string(),
+      Self::Median => "Median".to_string(),
+      Self::Mode => "Mode".to_string(),
+      Self::Constant(value) => format!("Constant ({value})"),
+      Self::ForwardFill { group_by, order_by } => {
+        format!("Forward fill by {} over {order_by}", group_by.join(", "))
+      }
+      Self::Knn { k, features } => format!("KNN (k = {k}) on {}", features.join(", ")),
+    }
+  }
+}
+
+/// Represents the statistics learned by a fitted `[Imputer]`.
+#[derive(Clone)]
+enum FittedImputation {
+  /// Value that fills every null.
+  Value(ImputeValue),
+  /// Forward fill needs nothing from the fitted data.
+  ForwardFill,
+  /// Nearest-neighbours model over the features standardized with the fitted means and stds.
+  Knn {
+    model: Box<KnnRegressor>,
+    means: Array1<f64>,
+    stds: Array1<f64>,
+  },
+}
+
+/// Represents an imputer of the nulls of one column. It is fitted once, e.g. on the training
+/// split, and can then fill the nulls of any data with the same columns.
+#[derive(Clone)]
+pub struct Imputer {
+  /// Name of the imputed column.
+  pub column: String,
+  /// Strategy used to fill the nulls.
+  pub strategy: ImputationStrategy,
+  /// Whether to add a `"<column> Missing"` column, 1.0 where the value was null.
+  pub add_indicator: bool,
+  fitted: Option<FittedImputation>,
+}
+
+impl fmt::Debug for Imputer {
+  fn fmt(
+    &self,
+    f: &mut fmt::Formatter<'_>,
+  ) -> fmt::Result {
+    f.debug_struct("Imputer")
+      .field("column", &self.column)
+      .field("strategy", &self.strategy)
+      .field("add_indicator", &self.add_indicator)
+      .field("fitted", &self.fitted.is_some())
+      .finish()
+  }
+}
+
+impl Imputer {
+  /// Creates a new unfitted imputer of a column.
+  pub fn new(
+    column: &str,
+    strategy: ImputationStrategy,
+  ) -> Self {
+    Self {
+      column: column.to_string(),
+      strategy,
+      add_indicator: false,
+      fitted: None,
+    }
+  }
+
+  /// Sets whether to add a missing-indicator column.
+  pub fn add_indicator(
+    mut self,
+    add_indicator: bool,
+  ) -> Self {
+    self.add_indicator = add_indicator;
+    self
+  }
+
+  /// Gets the name of the missing-indicator column.
+  pub fn indicator_name(&self) -> String {
+    format!("{} Missing", self.column)
+  }
+
+  /// Gets a display description of the imputer, e.g. "Item Size: Mode, with indicator".
+  pub fn description(&self) -> String {
+    if self.add_indicator {
+      format!("{}: {}, with indicator", self.column, self.strategy.name())
+    } else {
+      format!("{}: {}", self.column, self.strategy.name())
+    }
+  }
+
+  /// Gets the value that fills the nulls, when the strategy uses a single value.
+  pub fn fill_value(&self) -> Option<&ImputeValue> {
+    match &self.fitted {
+      Some(FittedImputation::Value(value)) => Some(value),
+      _ => None,
+    }
+  }
+
+  /// Learns the statistics of the column.
+  pub fn fit(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<()> {
+    let series = df.column(&self.column)?;
+
+    self.fitted = Some(match &self.strategy {
+      ImputationStrategy::Mean => FittedImputation::Value(ImputeValue::Number(
+        numeric(series, &self.column)?
+          .mean()
+          .ok_or_else(|| self.all_null_error())?,
+      )),
+      ImputationStrategy::Median => FittedImputation::Value(ImputeValue::Number(
+        numeric(series, &self.column)?
+          .median()
+          .ok_or_else(|| self.all_null_error())?,
+      )),
+      ImputationStrategy::Mode => FittedImputation::Value(self.mode(series)?),
+      ImputationStrategy::Constant(value) => FittedImputation::Value(value.clone()),
+      ImputationStrategy::ForwardFill { .. } => FittedImputation::ForwardFill,
+      ImputationStrategy::Knn { k, features } => {
+        let target = numeric(series, &self.column)?;
+        let (x, rows) = feature_matrix(df, features)?;
+        let rows: Vec<usize> = rows
+          .into_iter()
+          .filter(|row| target.get(*row).is_some())
+          .collect();
+        if rows.is_empty() {
+          return Err(self.all_null_error());
+        }
+
+        let x = x.select(ndarray::Axis(0), &rows);
+        let means = x.mean_axis(ndarray::Axis(0)).unwrap_or_default();
+        let stds =
+          x.std_axis(ndarray::Axis(0), 0.0)
+            .mapv(|std| if std > 0.0 { std } else { 1.0 });
+        let y = Array2::from_shape_vec(
+          (rows.len(), 1),
+          rows
+            .iter()
+            .map(|row| target.get(*row).unwrap_or_default())
+            .collect(),
+        )?;
+
+        let mut model = KnnRegressor::new(KnnOptions::builder().k(*k).build());
+        model.fit(&((&x - &means) / &stds), &y)?;
+
+        FittedImputation::Knn {
+          model: Box::new(model),
+          means,
+          stds,
+        }
+      }
+    });
+
+    Ok(())
+  }
+
+  /// Fills the nulls of the column, and adds the missing indicator if requested.
+  pub fn transform(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    let fitted = self.fitted.as_ref().ok_or_else(|| {
+      GenericError::from(format!("The imputer of \"{}\" is not fitted", self.column))
+    })?;
+    let series = df.column(&self.column)?;
+    let missing = series.is_null();
+
+    let imputed: Series = match fitted {
+      FittedImputation::Value(ImputeValue::Number(value)) => {
+        numeric(series, &self.column)?
+          .into_iter()
+          .map(|current| current.or(Some(*value)))
+          .collect::<Float64Chunked>()
+          .into_series()
+      }
+      FittedImputation::Value(ImputeValue::Text(value)) => series
+        .cast(&DataType::Utf8)?
+        .utf8()?
+        .into_iter()
+        .map(|current| Some(current.unwrap_or(value.as_str())))
+        .collect::<Utf8Chunked>()
+        .into_series(),
+      FittedImputation::ForwardFill => self.forward_fill(df)?,
+      FittedImputation::Knn { model, means, stds } => {
+        let target = numeric(series, &self.column)?;
+        let ImputationStrategy::Knn { features, .. } = &self.strategy else {
+          unreachable!("a KNN imputation comes from a KNN strategy");
+        };
+        let (x, rows) = feature_matrix(df, features)?;
+
+        let predictions = model.predict(&((&x - means) / stds));
+
+        let mut values: Vec<Option<f64>> = target.into_iter().collect();
+        for (position, row) in rows.iter().enumerate() {
+          if values[*row].is_none() {
+            values[*row] = Some(predictions[(position, 0)]);
+          }
+        }
+        Float64Chunked::from_iter(values).into_series()
+      }
+    };
+
+    let mut imputed = imputed;
+    imputed.rename(&self.column);
+    let mut result = df.clone();
+    result.replace(&self.column, imputed)?;
+    if self.add_indicator {
+      let indicator: Float64Chunked = missing
+        .into_iter()
+        .map(|missing| Some(if missing.unwrap_or(false) { 1.0 } else { 0.0 }))
+        .collect();
+      let mut indicator = indicator.into_series();
+      indicator.rename(&self.indicator_name());
+      result.with_column(indicator)?;
+    }
+
+    Ok(result)
+  }
+
+  /// Fits the imputer and transforms the same data.
+  pub fn fit_transform(
+    &mut self,
+    df: &DataFrame,
+  ) -> GenericResult<DataFrame> {
+    self.fit(df)?;
+    self.transform(df)
+  }
+
+  /// Fills every null with the last non-null value of the same group in date order.
+  fn forward_fill(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<Series> {
+    let ImputationStrategy::ForwardFill { group_by, order_by } = &self.strategy else {
+      unreachable!("a forward fill comes from a forward-fill strategy");
+    };
+
+    let row_column = "__imputation_row__";
+    let filled = df
+      .clone()
+      .lazy()
+      .with_row_count(row_column, None)
+      .sort(
+        order_by,
+        SortOptions {
+          descending: false,
+          nulls_last: true,
+          maintain_order: true,
+          multithreaded: true,
+        },
+      )
+      .with_column(
+        col(&self.column)
+          .forward_fill(None)
+          .over(group_by.iter().map(|name| col(name)).collect::<Vec<Expr>>()),
+      )
+      .sort(row_column, SortOptions::default())
+      .collect()?;
+
+    Ok(filled.column(&self.column)?.clone())
+  }
+
+  /// Gets the most frequent non-null value of a column.
+  fn mode(
+    &self,
+    series: &Series,
+  ) -> GenericResult<ImputeValue> {
+    if series.dtype() == &DataType::Utf8 {
+      let mut counts: HashMap<&str, usize> = HashMap::new();
+      for value in series.utf8()?.into_iter().flatten() {
+        *counts.entry(value).or_insert(0) += 1;
+      }
+      let (value, _) = counts
+        .into_iter()
+        .max_by(|(a, count_a), (b, count_b)| count_a.cmp(count_b).then(b.cmp(a)))
+        .ok_or_else(|| self.all_null_error())?;
+      return Ok(ImputeValue::Text(value.to_string()));
+    }
+
+    let mut counts: HashMap<u64, usize> = HashMap::new();
+    for value in numeric(series, &self.column)?.into_iter().flatten() {
+      *counts.entry(value.to_bits()).or_insert(0) += 1;
+    }
+    let (bits, _) = counts
+      .into_iter()
+      .max_by(|(a, count_a), (b, count_b)| {
+        count_a
+          .cmp(count_b)
+          .then(f64::from_bits(*b).total_cmp(&f64::from_bits(*a)))
+      })
+      .ok_or_else(|| self.all_null_error())?;
+    Ok(ImputeValue::Number(f64::from_bits(bits)))
+  }
+
+  /// Error of a column without any value to learn from.
+  fn all_null_error(&self) -> crate::application_error::ApplicationError {
+    GenericError::from(format!(
+      "\"{}\" has no non-null value to impute from",
+      self.column
+    ))
+    .into()
+  }
+}
+
+/// Fits a list of imputers and transforms the data with all of them, in order.
+pub fn fit_transform_all(
+  imputers: &mut [Imputer],
+  df: &DataFrame,
+) -> GenericResult<DataFrame> {
+  let mut result = df.clone();
+  for imputer in imputers.iter_mut() {
+    result = imputer.fit_transform(&result)?;
+  }
+  Ok(result)
+}
+
+/// Transforms the data with a list of fitted imputers, in order.
+pub fn transform_all(
+  imputers: &[Imputer],
+  df: &DataFrame,
+) -> GenericResult<DataFrame> {
+  let mut result = df.clone();
+  for imputer in imputers {
+    result = imputer.transform(&result)?;
+  }
+  Ok(result)
+}
+
+/// Gets a numeric column as 64-bit floats.
+fn numeric(
+  series: &Series,
+  column: &str,
+) -> GenericResult<Float64Chunked> {
+  if !series.dtype().is_numeric() {
+    return Err(
+      GenericError::from(format!(
+        "\"{column}\" must be numeric but it is {}",
+        series.dtype()
+      ))
+      .into(),
+    );
+  }
+  Ok(series.cast(&DataType::Float64)?.f64()?.clone())
+}
+
+/// Builds the matrix of the feature columns over the rows where none of them is null.
+///
+/// # Returns
+///
+/// The matrix and the index of the source row of every matrix row.
+fn feature_matrix(
+  df: &DataFrame,
+  features: &[String],
+) -> GenericResult<(Array2<f64>, Vec<usize>)> {
+  let columns = features
+    .iter()
+    .map(|name| numeric(df.column(name)?, name))
+    .collect::<GenericResult<Vec<Float64Chunked>>>()?;
+
+  let rows: Vec<usize> = (0..df.height())
+    .filter(|row| columns.iter().all(|column| column.get(*row).is_some()))
+    .collect();
+
+  let mut x = Array2::<f64>::zeros((rows.len(), columns.len()));
+  for (j, column) in columns.iter().enumerate() {
+    for (i, row) in rows.iter().enumerate() {
+      x[(i, j)] = column.get(*row).unwrap_or_default();
+    }
+  }
+
+  Ok((x, rows))
+}
@@ -0,0 +1,211 @@
+use maud::Markup;
+use plotly::common::{Line, LineShape, Mode, Title};
+use plotly::layout::Axis;
+use plotly::{Layout, Scatter, Trace};
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::html_plot_figure::html_plot_figure;
+
+/// Represents the direction of an isotonic (monotone) fit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Monotonicity {
+  /// The fitted values never decrease as x grows.
+  Increasing,
+  /// The fitted values never increase as x grows.
+  Decreasing,
+}
+
+impl Monotonicity {
+  /// Gets the display name of the direction.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Increasing => "increasing",
+      Self::Decreasing => "decreasing",
+    }
+  }
+}
+
+/// Represents an isotonic regression: the monotone step function closest to the data in
+/// weighted least squares, fitted with the pool-adjacent-violators algorithm (PAVA).
+#[derive(Clone, Debug)]
+pub struct IsotonicRegression {
+  /// Direction of the fit.
+  pub monotonicity: Monotonicity,
+  /// Distinct x values of the training data, sorted.
+  pub x_thresholds: Vec<f64>,
+  /// Fitted value at every x threshold.
+  pub y_thresholds: Vec<f64>,
+}
+
+impl IsotonicRegression {
+  /// Creates a new unfitted isotonic regression.
+  pub fn new(monotonicity: Monotonicity) -> Self {
+    Self {
+      monotonicity,
+      x_thresholds: Vec::new(),
+      y_thresholds: Vec::new(),
+    }
+  }
+
+  /// Fits the monotone step function.
+  ///
+  /// Observations with the same x are pooled first (weighted mean), then adjacent blocks that
+  /// violate the direction are merged until none do.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Explanatory (input) values.
+  /// * `y`: Response (output) values.
+  /// * `weights`: Optional positive weight of every observation; all 1 when `None`.
+  pub fn fit(
+    &mut self,
+    x: &[f64],
+    y: &[f64],
+    weights: Option<&[f64]>,
+  ) -> GenericResult<()> {
+    if x.len() != y.len() || weights.is_some_and(|weights| weights.len() != x.len()) {
+      return Err(
+        GenericError::from("The inputs, responses and weights must have the same length").into(),
+      );
+    }
+    if x.is_empty() {
+      return Err(GenericError::from("Isotonic regression needs at least one observation").into());
+    }
+    let invalid_weight = |weight: &f64| weight.is_nan() || *weight <= 0.0;
+    if weights.is_some_and(|weights| weights.iter().any(invalid_weight)) {
+      return Err(GenericError::from("Sample weights must be positive").into());
+    }
+
+    let sign = match self.monotonicity {
+      Monotonicity::Increasing => 1.0,
+      Monotonicity::Decreasing => -1.0,
+    };
+
+    let mut order: Vec<usize> = (0..x.len()).collect();
+    order.sort_by(|a, b| x[*a].total_cmp(&x[*b]));
+
+    // Pool the observations with the same x: (x, weighted mean of y, total weight)
+    let mut points: Vec<(f64, f64, f64)> = Vec::new();
+    for index in order {
+      let weight = weights.map_or(1.0, |weights| weights[index]);
+      let value = sign * y[index];
+      match points.last_mut() {
+        Some((last_x, mean, total)) if *last_x == x[index] => {
+          *mean = (*mean * *total + value * weight) / (*total + weight);
+          *total += weight;
+        }
+        _ => points.push((x[index], value, weight)),
+      }
+    }
+
+    // Blocks of consecutive points: (mean, total weight, number of points)
+    let mut blocks: Vec<(f64, f64, usize)> = Vec::with_capacity(points.len());
+    for (_, value, weight) in &points {
+      blocks.push((*value, *weight, 1));
+      while blocks.len() > 1 && blocks[blocks.len() - 2].0 > blocks[blocks.len() - 1].0 {
+        let (mean, total, count) = blocks.pop().expect("at least two blocks");
+        let previous = blocks.last_mut().expect("at least one block");
+        previous.0 = (previous.0 * previous.1 + mean * total) / (previous.1 + total);
+        previous.1 += total;
+        previous.2 += count;
+      }
+    }
+
+    self.x_thresholds = points.iter().map(|(x, _, _)| *x).collect();
+    self.y_thresholds = blocks
+      .iter()
+      .flat_map(|(mean, _, count)| std::iter::repeat_n(sign * mean, *count))
+      .collect();
+
+    Ok(())
+  }
+
+  /// Predicts the response by linear interpolation between the fitted thresholds. Inputs outside
+  /// the training range get the value of the nearest end.
+  pub fn predict(
+    &self,
+    x: &[f64],
+  ) -> Vec<f64> {
+    x.iter().map(|x| self.interpolate(*x)).collect()
+  }
+
+  /// Calculates the mean squared error of the predictions.
+  pub fn mse(
+    &self,
+    x: &[f64],
+    y: &[f64],
+  ) -> f64 {
+    self
+      .predict(x)
+      .iter()
+      .zip(y.iter())
+      .map(|(prediction, y)| (y - prediction).powi(2))
+      .sum::<f64>()
+      / y.len().max(1) as f64
+  }
+
+  /// Creates a step-line trace of the fitted thresholds, to draw over a scatter plot.
+  pub fn step_trace(
+    &self,
+    name: &str,
+  ) -> Box<Scatter<f64, f64>> {
+    Scatter::new(self.x_thresholds.clone(), self.y_thresholds.clone())
+      .mode(Mode::Lines)
+      .line(Line::new().shape(LineShape::Hv))
+      .name(name)
+  }
+
+  /// Interpolates the fitted value at a single input.
+  fn interpolate(
+    &self,
+    x: f64,
+  ) -> f64 {
+    let (Some(first), Some(last)) = (self.x_thresholds.first(), self.x_thresholds.last()) else {
+      return f64::NAN;
+    };
+    if x <= *first {
+      return self.y_thresholds[0];
+    }
+    if x >= *last {
+      return self.y_thresholds[self.y_thresholds.len() - 1];
+    }
+
+    let upper = self.x_thresholds.partition_point(|threshold| *threshold <= x);
+    let (x0, x1) = (self.x_thresholds[upper - 1], self.x_thresholds[upper]);
+    let (y0, y1) = (self.y_thresholds[upper - 1], self.y_thresholds[upper]);
+    y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+  }
+}
+
+/// Generates a HTML figure with the scatter of the data and the fitted isotonic steps over it.
+///
+/// # Arguments
+///
+/// * `x`: Explanatory (input) values.
+/// * `y`: Response (output) values.
+/// * `model`: Fitted isotonic regression.
+/// * `x_title`: Title of the x axis.
+/// * `y_title`: Title of the y axis.
+/// * `caption`: Caption text of the figure.
+pub fn isotonic_figure(
+  x: &[f64],
+  y: &[f64],
+  model: &IsotonicRegression,
+  x_title: &str,
+  y_title: &str,
+  caption: &str,
+) -> GenericResult<Markup> {
+  let traces: Vec<Box<dyn Trace>> = vec![
+    Scatter::new(x.to_vec(), y.to_vec())
+      .mode(Mode::Markers)
+      .name("Observations"),
+    model.step_trace(&format!("Isotonic ({})", model.monotonicity.name())),
+  ];
+
+  let layout = Layout::new()
+    .title(Title::new(&format!("{y_title} vs {x_title}")))
+    .x_axis(Axis::new().title(Title::new(x_title)))
+    .y_axis(Axis::new().title(Title::new(y_title)));
+
+  html_plot_figure(traces, &layout, caption)
+}
@@ -0,0 +1,495 @@
+use linfa_linalg::cholesky::Cholesky;
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::application_error::GenericResult;
+
+/// Represents the smoothness ν of a Matérn kernel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MaternSmoothness {
+  /// ν = 1/2, equivalent to the exponential kernel.
+  Half,
+  /// ν = 3/2, once differentiable functions.
+  ThreeHalves,
+  /// ν = 5/2, twice differentiable functions.
+  FiveHalves,
+}
+
+/// Represents a covariance function k(x, x') between two input vectors.
+#[derive(Clone, Copy, Debug)]
+pub enum Kernel {
+  /// Radial basis function (squared exponential): σ² exp(-r² / 2ℓ²).
+  Rbf {
+    /// Length scale ℓ.
+    length_scale: f64,
+    /// Signal variance σ².
+    variance: f64,
+  },
+  /// Matérn kernel of smoothness ν.
+  Matern {
+    /// Length scale ℓ.
+    length_scale: f64,
+    /// Signal variance σ².
+    variance: f64,
+    /// Smoothness ν.
+    smoothness: MaternSmoothness,
+  },
+  /// Periodic (exp-sine-squared) kernel: σ² exp(-2 sin²(πr / p) / ℓ²).
+  Periodic {
+    /// Length scale ℓ.
+    length_scale: f64,
+    /// Period p.
+    period: f64,
+    /// Signal variance σ².
+    variance: f64,
+  },
+}
+
+impl Kernel {
+  /// Evaluates the kernel on two input vectors.
+  pub fn evaluate(
+    &self,
+    a: ArrayView1<f64>,
+    b: ArrayView1<f64>,
+  ) -> f64 {
+    let r = a
+      .iter()
+      .zip(b.iter())
+      .map(|(a, b)| (a - b).powi(2))
+      .sum::<f64>()
+      .sqrt();
+
+    match *self {
+      Self::Rbf {
+        length_scale,
+        variance,
+      } => variance * (-(r / length_scale).powi(2) / 2.0).exp(),
+      Self::Matern {
+        length_scale,
+        variance,
+        smoothness,
+      } => {
+        let r = r / length_scale;
+        match smoothness {
+          MaternSmoothness::Half => variance * (-r).exp(),
+          MaternSmoothness::ThreeHalves => {
+            let s = 3.0_f64.sqrt() * r;
+            variance * (1.0 + s) * (-s).exp()
+          }
+          MaternSmoothness::FiveHalves => {
+            let s = 5.0_f64.sqrt() * r;
+            variance * (1.0 + s + s.powi(2) / 3.0) * (-s).exp()
+          }
+        }
+      }
+      Self::Periodic {
+        length_scale,
+        period,
+        variance,
+      } => {
+        let sine = (std::f64::consts::PI * r / period).sin();
+        variance * (-2.0 * sine.powi(2) / length_scale.powi(2)).exp()
+      }
+    }
+  }
+
+  /// Calculates the kernel (Gram) matrix Kᵢⱼ = k(aᵢ, bⱼ) between the rows of two matrices.
+  pub fn matrix(
+    &self,
+    a: &Array2<f64>,
+    b: &Array2<f64>,
+  ) -> Array2<f64> {
+    Array2::from_shape_fn((a.nrows(), b.nrows()), |(i, j)| {
+      self.evaluate(a.row(i), b.row(j))
+    })
+  }
+
+  /// Gets a display name with the hyperparameters, e.g. "RBF(ℓ = 12.000, σ² = 4.000)".
+  pub fn name(&self) -> String {
+    match *self {
+      Self::Rbf {
+        length_scale,
+        variance,
+      } => format!("RBF(ℓ = {length_scale:.3}, σ² = {variance:.3})"),
+      Self::Matern {
+        length_scale,
+        variance,
+        smoothness,
+      } => {
+        let smoothness = match smoothness {
+          MaternSmoothness::Half => "1/2",
+          MaternSmoothness::ThreeHalves => "3/2",
+          MaternSmoothness::FiveHalves => "5/2",
+        };
+        format!("Matérn ν={smoothness}(ℓ = {length_scale:.3}, σ² = {variance:.3})")
+      }
+      Self::Periodic {
+        length_scale,
+        period,
+        variance,
+      } => format!("Periodic(ℓ = {length_scale:.3}, p = {period:.3}, σ² = {variance:.3})"),
+    }
+  }
+
+  /// Gets the logarithms of the hyperparameters, the space in which they are optimized.
+  fn log_parameters(&self) -> Vec<f64> {
+    match *self {
+      Self::Rbf {
+        length_scale,
+        variance,
+      }
+      | Self::Matern {
+        length_scale,
+        variance,
+        ..
+      } => vec![length_scale.ln(), variance.ln()],
+      Self::Periodic {
+        length_scale,
+        period,
+        variance,
+      } => vec![length_scale.ln(), period.ln(), variance.ln()],
+    }
+  }
+
+  /// Creates a kernel of the same kind with the given logarithms of the hyperparameters.
+  fn with_log_parameters(
+    &self,
+    log_parameters: &[f64],
+  ) -> Self {
+    match *self {
+      Self::Rbf { .. } => Self::Rbf {
+        length_scale: log_parameters[0].exp(),
+        variance: log_parameters[1].exp(),
+      },
+      Self::Matern { smoothness, .. } => Self::Matern {
+        length_scale: log_parameters[0].exp(),
+        variance: log_parameters[1].exp(),
+        smoothness,
+      },
+      Self::Periodic { .. } => Self::Periodic {
+        length_scale: log_parameters[0].exp(),
+        period: log_parameters[1].exp(),
+        variance: log_parameters[2].exp(),
+      },
+    }
+  }
+}
+
+/// Represents a kernel ridge regression: the ridge regression in the feature space of a
+/// kernel, with the prediction f(x) = Σ αᵢ k(x, xᵢ) and α = (K + λI)⁻¹(y - ȳ).
+pub struct KernelRidge {
+  /// Covariance function.
+  pub kernel: Kernel,
+  /// Ridge (L2) regularization strength λ.
+  pub λ: f64,
+  /// Training inputs.
+  pub x_train: Array2<f64>,
+  /// Dual coefficients α.
+  pub dual_coefficients: Array1<f64>,
+  /// Mean of the training responses, added back to the predictions.
+  pub y_mean: f64,
+}
+
+impl KernelRidge {
+  /// Creates a new unfitted kernel ridge regression.
+  pub fn new(
+    kernel: Kernel,
+    λ: f64,
+  ) -> Self {
+    Self {
+      kernel,
+      λ,
+      x_train: Array2::<f64>::zeros((0, 1)),
+      dual_coefficients: Array1::<f64>::zeros(0),
+      y_mean: 0.0,
+    }
+  }
+
+  /// Fits the dual coefficients on the given training data.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables.
+  /// * `y`: Vector of response (output) variables.
+  pub fn fit(
+    &mut self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> GenericResult<()> {
+    let y = y.column(0);
+    self.y_mean = y.mean().unwrap_or(0.0);
+
+    let mut k = self.kernel.matrix(x, x);
+    k.diag_mut().mapv_inplace(|value| value + self.λ);
+
+    let l = k.cholesky()?;
+    self.dual_coefficients = cholesky_solve(&l, &y.mapv(|value| value - self.y_mean));
+    self.x_train = x.clone();
+
+    Ok(())
+  }
+
+  /// Predicts the response for the given inputs.
+  pub fn predict(
+    &self,
+    x: &Array2<f64>,
+  ) -> Array2<f64> {
+    let prediction = self.kernel.matrix(x, &self.x_train).dot(&self.dual_coefficients) + self.y_mean;
+    prediction.insert_axis(ndarray::Axis(1))
+  }
+}
+
+/// Represents a Gaussian-process regression with a zero-mean prior on the centered responses
+/// and Gaussian observation noise.
+pub struct GaussianProcess {
+  /// Covariance function of the prior.
+  pub kernel: Kernel,
+  /// Variance of the observation noise.
+  pub noise_variance: f64,
+  /// Training inputs.
+  pub x_train: Array2<f64>,
+  /// Weights α = (K + σₙ²I)⁻¹(y - ȳ).
+  pub weights: Array1<f64>,
+  /// Lower Cholesky factor L of K + σₙ²I.
+  pub l: Array2<f64>,
+  /// Mean of the training responses, used as the prior mean.
+  pub y_mean: f64,
+  /// Log marginal likelihood ln p(y | X) of the training data.
+  pub log_marginal_likelihood: f64,
+}
+
+impl GaussianProcess {
+  /// Creates a new unfitted Gaussian process.
+  pub fn new(
+    kernel: Kernel,
+    noise_variance: f64,
+  ) -> Self {
+    Self {
+      kernel,
+      noise_variance,
+      x_train: Array2::<f64>::zeros((0, 1)),
+      weights: Array1::<f64>::zeros(0),
+      l: Array2::<f64>::zeros((0, 0)),
+      y_mean: 0.0,
+      log_marginal_likelihood: f64::NAN,
+    }
+  }
+
+  /// Conditions the process on the training data with the current hyperparameters.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables.
+  /// * `y`: Vector of response (output) variables.
+  pub fn fit(
+    &mut self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> GenericResult<()> {
+    let y = y.column(0);
+    self.y_mean = y.mean().unwrap_or(0.0);
+    let y_centered = y.mapv(|value| value - self.y_mean);
+
+    let mut k = self.kernel.matrix(x, x);
+    k.diag_mut()
+      .mapv_inplace(|value| value + self.noise_variance);
+
+    self.l = k.cholesky()?;
+    self.weights = cholesky_solve(&self.l, &y_centered);
+    self.x_train = x.clone();
+
+    // ln p(y | X) = -½ yᵀα - Σ ln Lᵢᵢ - n/2 ln 2π
+    let n = y.len() as f64;
+    self.log_marginal_likelihood = -0.5 * y_centered.dot(&self.weights)
+      - self.l.diag().iter().map(|value| value.ln()).sum::<f64>()
+      - n / 2.0 * (2.0 * std::f64::consts::PI).ln();
+
+    Ok(())
+  }
+
+  /// Fits the kernel hyperparameters and the noise variance by maximizing the log marginal
+  /// likelihood (Nelder-Mead in log space, starting from the current values), and then
+  /// conditions the process on the training data.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables.
+  /// * `y`: Vector of response (output) variables.
+  /// * `max_iterations`: Maximum number of Nelder-Mead iterations.
+  pub fn fit_optimized(
+    &mut self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+    max_iterations: usize,
+  ) -> GenericResult<()> {
+    let kernel = self.kernel;
+    let mut start = kernel.log_parameters();
+    start.push(self.noise_variance.ln());
+
+    let negative_log_likelihood = |log_parameters: &[f64]| {
+      let n_kernel = log_parameters.len() - 1;
+      let mut gp = GaussianProcess::new(
+        kernel.with_log_parameters(&log_parameters[..n_kernel]),
+        log_parameters[n_kernel].exp(),
+      );
+      match gp.fit(x, y) {
+        Ok(()) if gp.log_marginal_likelihood.is_finite() => -gp.log_marginal_likelihood,
+        _ => f64::INFINITY,
+      }
+    };
+
+    let best = nelder_mead(negative_log_likelihood, &start, max_iterations);
+    let n_kernel = best.len() - 1;
+    self.kernel = kernel.with_log_parameters(&best[..n_kernel]);
+    self.noise_variance = best[n_kernel].exp();
+
+    self.fit(x, y)
+  }
+
+  /// Predicts the posterior mean and variance of the latent function at the given inputs.
+  ///
+  /// # Returns
+  ///
+  /// A tuple `(mean, variance)`. Add `noise_variance` to the variance to get the predictive
+  /// variance of new observations.
+  pub fn predict(
+    &self,
+    x: &Array2<f64>,
+  ) -> (Array2<f64>, Array2<f64>) {
+    let k_star = self.kernel.matrix(x, &self.x_train);
+    let mean = k_star.dot(&self.weights) + self.y_mean;
+
+    // var = k(x, x) - vᵀv, where v = L⁻¹k*
+    let variance = Array1::from_iter(k_star.rows().into_iter().zip(x.rows()).map(
+      |(k_row, x_row)| {
+        let v = forward_substitution(&self.l, &k_row.to_owned());
+        (self.kernel.evaluate(x_row, x_row) - v.dot(&v)).max(0.0)
+      },
+    ));
+
+    (
+      mean.insert_axis(ndarray::Axis(1)),
+      variance.insert_axis(ndarray::Axis(1)),
+    )
+  }
+
+  /// Calculates the mean squared error of the posterior mean.
+  pub fn mse(
+    &self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> f64 {
+    let (mean, _) = self.predict(x);
+    (y - &mean).iter().map(|e| e.powi(2)).sum::<f64>() / (x.nrows().max(1) as f64)
+  }
+}
+
+/// Solves Ax = b given the lower Cholesky factor L of A = LLᵀ.
+fn cholesky_solve(
+  l: &Array2<f64>,
+  b: &Array1<f64>,
+) -> Array1<f64> {
+  backward_substitution(l, &forward_substitution(l, b))
+}
+
+/// Solves Lx = b for a lower-triangular matrix L.
+fn forward_substitution(
+  l: &Array2<f64>,
+  b: &Array1<f64>,
+) -> Array1<f64> {
+  let mut x = Array1::<f64>::zeros(b.len());
+  for i in 0..b.len() {
+    let sum: f64 = (0..i).map(|j| l[(i, j)] * x[j]).sum();
+    x[i] = (b[i] - sum) / l[(i, i)];
+  }
+  x
+}
+
+/// Solves Lᵀx = b for a lower-triangular matrix L.
+fn backward_substitution(
+  l: &Array2<f64>,
+  b: &Array1<f64>,
+) -> Array1<f64> {
+  let mut x = Array1::<f64>::zeros(b.len());
+  for i in (0..b.len()).rev() {
+    let sum: f64 = (i + 1..b.len()).map(|j| l[(j, i)] * x[j]).sum();
+    x[i] = (b[i] - sum) / l[(i, i)];
+  }
+  x
+}
+
+/// Minimizes a function with the Nelder-Mead simplex method.
+///
+/// # Arguments
+///
+/// * `f`: Function to minimize. Infinite values mark invalid points.
+/// * `start`: Starting point.
+/// * `max_iterations`: Maximum number of iterations.
+fn nelder_mead<F: Fn(&[f64]) -> f64>(
+  f: F,
+  start: &[f64],
+  max_iterations: usize,
+) -> Vec<f64> {
+  let n = start.len();
+
+  // Initial simplex: the start and one point moved along every axis
+  let mut simplex: Vec<(Vec<f64>, f64)> = vec![(start.to_vec(), f(start))];
+  for axis in 0..n {
+    let mut point = start.to_vec();
+    point[axis] += 0.5;
+    let value = f(&point);
+    simplex.push((point, value));
+  }
+
+  for _ in 0..max_iterations {
+    simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+    if (simplex[n].1 - simplex[0].1).abs() <= 1e-6 * (1.0 + simplex[0].1.abs()) {
+      break;
+    }
+
+    let centroid: Vec<f64> = (0..n)
+      .map(|axis| simplex[..n].iter().map(|(point, _)| point[axis]).sum::<f64>() / n as f64)
+      .collect();
+    let along = |t: f64| -> Vec<f64> {
+      (0..n)
+        .map(|axis| centroid[axis] + t * (simplex[n].0[axis] - centroid[axis]))
+        .collect()
+    };
+
+    let reflected = along(-1.0);
+    let reflected_value = f(&reflected);
+
+    if reflected_value < simplex[0].1 {
+      let expanded = along(-2.0);
+      let expanded_value = f(&expanded);
+      simplex[n] = if expanded_value < reflected_value {
+        (expanded, expanded_value)
+      } else {
+        (reflected, reflected_value)
+      };
+    } else if reflected_value < simplex[n - 1].1 {
+      simplex[n] = (reflected, reflected_value);
+    } else {
+      let contracted = along(0.5);
+      let contracted_value = f(&contracted);
+      if contracted_value < simplex[n].1 {
+        simplex[n] = (contracted, contracted_value);
+      } else {
+        // Shrink every point towards the best one
+        let best = simplex[0].0.clone();
+        for (point, value) in simplex.iter_mut().skip(1) {
+          for axis in 0..n {
+            point[axis] = best[axis] + 0.5 * (point[axis] - best[axis]);
+          }
+          *value = f(point);
+        }
+      }
+    }
+  }
+
+  simplex.sort_by(|a, b| a.1.total_cmp(&b.1));
+  if simplex[0].1.is_finite() {
+    simplex.swap_remove(0).0
+  } else {
+    start.to_vec()
+  }
+}
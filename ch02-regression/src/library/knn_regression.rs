@@ -0,0 +1,428 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use ndarray::{Array1, Array2, ArrayView1};
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Represents the distance between two points used to find the nearest neighbours.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distance {
+  /// Straight-line (L2) distance: √Σ(aᵢ - bᵢ)².
+  Euclidean,
+  /// City-block (L1) distance: Σ|aᵢ - bᵢ|.
+  Manhattan,
+}
+
+impl Distance {
+  /// Calculates the distance between two points.
+  pub fn between(
+    &self,
+    a: &ArrayView1<f64>,
+    b: &ArrayView1<f64>,
+  ) -> f64 {
+    let differences = a.iter().zip(b.iter()).map(|(a, b)| a - b);
+    match self {
+      Self::Euclidean => differences.map(|d| d * d).sum::<f64>().sqrt(),
+      Self::Manhattan => differences.map(f64::abs).sum(),
+    }
+  }
+
+  /// Gets the display name of the distance.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Euclidean => "Euclidean",
+      Self::Manhattan => "Manhattan",
+    }
+  }
+}
+
+/// Represents how the responses of the nearest neighbours are averaged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weighting {
+  /// Every neighbour counts the same.
+  Uniform,
+  /// Every neighbour counts by the inverse of its distance. Neighbours at distance 0, if any,
+  /// are the only ones averaged.
+  Distance,
+}
+
+impl Weighting {
+  /// Gets the display name of the weighting.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Uniform => "uniform",
+      Self::Distance => "distance",
+    }
+  }
+}
+
+/// Represents options for the k-nearest-neighbours regressor.
+#[derive(Clone)]
+pub struct KnnOptions {
+  /// Number of neighbours averaged per prediction.
+  pub k: usize,
+  /// Distance between points.
+  pub distance: Distance,
+  /// Weighting of the neighbours.
+  pub weighting: Weighting,
+}
+
+impl KnnOptions {
+  /// Creates a new instance of `[KnnOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these KNN options.
+  pub fn builder() -> KnnOptionsBuilder {
+    KnnOptionsBuilder::default()
+  }
+}
+
+impl Default for KnnOptions {
+  fn default() -> Self {
+    KnnOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[KnnOptions]`.
+pub struct KnnOptionsBuilder {
+  /// Number of neighbours averaged per prediction.
+  pub k: usize,
+  /// Distance between points.
+  pub distance: Distance,
+  /// Weighting of the neighbours.
+  pub weighting: Weighting,
+}
+
+impl KnnOptionsBuilder {
+  /// Creates a new instance of `[KnnOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the number of neighbours averaged per prediction.
+  pub fn k(
+    mut self,
+    k: usize,
+  ) -> Self {
+    self.k = k;
+    self
+  }
+
+  /// Sets the distance between points.
+  pub fn distance(
+    mut self,
+    distance: Distance,
+  ) -> Self {
+    self.distance = distance;
+    self
+  }
+
+  /// Sets the weighting of the neighbours.
+  pub fn weighting(
+    mut self,
+    weighting: Weighting,
+  ) -> Self {
+    self.weighting = weighting;
+    self
+  }
+
+  /// Builds the instance of `[KnnOptions]`.
+  pub fn build(self) -> KnnOptions {
+    KnnOptions {
+      k: self.k,
+      distance: self.distance,
+      weighting: self.weighting,
+    }
+  }
+}
+
+impl Default for KnnOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      k: 5,
+      distance: Distance::Euclidean,
+      weighting: Weighting::Uniform,
+    }
+  }
+}
+
+/// Represents a node of a `[KdTree]`: a point that splits its subtree along one axis.
+#[derive(Clone, Debug)]
+struct KdNode {
+  /// Row of the point in the matrix of points.
+  point: usize,
+  /// Column (axis) the node splits on.
+  axis: usize,
+  /// Node holding the points with a lower or equal value on the axis.
+  left: Option<usize>,
+  /// Node holding the points with a greater or equal value on the axis.
+  right: Option<usize>,
+}
+
+/// Represents a neighbour found by a search, ordered by distance.
+#[derive(Clone, Copy, Debug)]
+pub struct Neighbour {
+  /// Distance to the query point.
+  pub distance: f64,
+  /// Row of the neighbour in the matrix of points.
+  pub index: usize,
+}
+
+impl PartialEq for Neighbour {
+  fn eq(
+    &self,
+    other: &Self,
+  ) -> bool {
+    self.cmp(other) == Ordering::Equal
+  }
+}
+
+impl Eq for Neighbour {}
+
+impl PartialOrd for Neighbour {
+  fn partial_cmp(
+    &self,
+    other: &Self,
+  ) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl Ord for Neighbour {
+  fn cmp(
+    &self,
+    other: &Self,
+  ) -> Ordering {
+    self
+      .distance
+      .total_cmp(&other.distance)
+      .then(self.index.cmp(&other.index))
+  }
+}
+
+/// Represents a k-d tree: a binary space partition of points that finds the nearest
+/// neighbours of a query without measuring the distance to every point.
+#[derive(Clone, Debug)]
+pub struct KdTree {
+  /// Points indexed by the tree, one per row.
+  pub points: Array2<f64>,
+  nodes: Vec<KdNode>,
+  root: Option<usize>,
+}
+
+impl KdTree {
+  /// Builds a balanced k-d tree, splitting every level at the median of the next axis.
+  pub fn new(points: Array2<f64>) -> Self {
+    let mut tree = Self {
+      points,
+      nodes: Vec::new(),
+      root: None,
+    };
+    let mut indexes: Vec<usize> = (0..tree.points.nrows()).collect();
+    tree.root = tree.build(&mut indexes, 0);
+    tree
+  }
+
+  /// Builds the subtree of the given points and returns its root node.
+  fn build(
+    &mut self,
+    indexes: &mut [usize],
+    depth: usize,
+  ) -> Option<usize> {
+    if indexes.is_empty() {
+      return None;
+    }
+
+    let axis = depth % self.points.ncols().max(1);
+    let points = &self.points;
+    indexes.sort_unstable_by(|a, b| points[(*a, axis)].total_cmp(&points[(*b, axis)]));
+
+    let median = indexes.len() / 2;
+    let node = self.nodes.len();
+    self.nodes.push(KdNode {
+      point: indexes[median],
+      axis,
+      left: None,
+      right: None,
+    });
+
+    let (lower, upper) = indexes.split_at_mut(median);
+    self.nodes[node].left = self.build(lower, depth + 1);
+    self.nodes[node].right = self.build(&mut upper[1..], depth + 1);
+
+    Some(node)
+  }
+
+  /// Finds the `k` points closest to the query, nearest first.
+  ///
+  /// # Arguments
+  ///
+  /// * `query`: Point to search around.
+  /// * `k`: Number of neighbours.
+  /// * `distance`: Distance between points.
+  pub fn nearest(
+    &self,
+    query: &ArrayView1<f64>,
+    k: usize,
+    distance: Distance,
+  ) -> Vec<Neighbour> {
+    // Max-heap of the best neighbours so far: the top is the farthest one
+    let mut best: BinaryHeap<Neighbour> = BinaryHeap::with_capacity(k + 1);
+    if k > 0 {
+      self.search(self.root, query, k, distance, &mut best);
+    }
+    best.into_sorted_vec()
+  }
+
+  /// Searches a subtree, descending first into the side of the query.
+  fn search(
+    &self,
+    node: Option<usize>,
+    query: &ArrayView1<f64>,
+    k: usize,
+    distance: Distance,
+    best: &mut BinaryHeap<Neighbour>,
+  ) {
+    let Some(node) = node.map(|node| &self.nodes[node]) else {
+      return;
+    };
+
+    let point = self.points.row(node.point);
+    best.push(Neighbour {
+      distance: distance.between(query, &point),
+      index: node.point,
+    });
+    if best.len() > k {
+      best.pop();
+    }
+
+    let offset = query[node.axis] - point[node.axis];
+    let (near, far) = if offset <= 0.0 {
+      (node.left, node.right)
+    } else {
+      (node.right, node.left)
+    };
+
+    self.search(near, query, k, distance, best);
+
+    // The gap along the axis is a lower bound of both distances to any point on the far side
+    let farthest = best.peek().map_or(f64::INFINITY, |neighbour| neighbour.distance);
+    if best.len() < k || offset.abs() <= farthest {
+      self.search(far, query, k, distance, best);
+    }
+  }
+}
+
+/// Represents a k-nearest-neighbours regressor: the prediction is the (weighted) mean response
+/// of the `k` closest training points.
+#[derive(Clone)]
+pub struct KnnRegressor {
+  /// Options of the regressor.
+  pub options: KnnOptions,
+  /// k-d tree over the training inputs.
+  pub tree: KdTree,
+  /// Training responses.
+  pub y_train: Array1<f64>,
+}
+
+impl KnnRegressor {
+  /// Creates a new unfitted KNN regressor.
+  pub fn new(options: KnnOptions) -> Self {
+    Self {
+      options,
+      tree: KdTree::new(Array2::<f64>::zeros((0, 1))),
+      y_train: Array1::<f64>::zeros(0),
+    }
+  }
+
+  /// Indexes the training data in a k-d tree.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables. Features should share a scale.
+  /// * `y`: Vector of response (output) variables.
+  pub fn fit(
+    &mut self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> GenericResult<()> {
+    if self.options.k == 0 {
+      return Err(GenericError::from("The number of neighbours k must be at least 1").into());
+    }
+    if x.nrows() != y.nrows() {
+      return Err(
+        GenericError::from(format!(
+          "The inputs have {} rows but the responses have {} rows",
+          x.nrows(),
+          y.nrows()
+        ))
+        .into(),
+      );
+    }
+
+    self.tree = KdTree::new(x.clone());
+    self.y_train = y.column(0).to_owned();
+
+    Ok(())
+  }
+
+  /// Predicts the response for the given inputs.
+  pub fn predict(
+    &self,
+    x: &Array2<f64>,
+  ) -> Array2<f64> {
+    let prediction = Array1::from_iter(x.rows().into_iter().map(|row| {
+      let neighbours = self.tree.nearest(&row, self.options.k, self.options.distance);
+      self.average(&neighbours)
+    }));
+    prediction.insert_axis(ndarray::Axis(1))
+  }
+
+  /// Calculates the mean squared error of the predictions.
+  pub fn mse(
+    &self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+  ) -> f64 {
+    (y - &self.predict(x)).mapv(|e| e.powi(2)).mean().unwrap_or(0.0)
+  }
+
+  /// Gets a display string of the options, e.g. "k = 5, Euclidean, uniform".
+  pub fn parameters_to_string(&self) -> String {
+    format!(
+      "k = {}, {}, {}",
+      self.options.k,
+      self.options.distance.name(),
+      self.options.weighting.name()
+    )
+  }
+
+  /// Averages the responses of the neighbours according to the weighting.
+  fn average(
+    &self,
+    neighbours: &[Neighbour],
+  ) -> f64 {
+    let exact: Vec<&Neighbour> = neighbours.iter().filter(|n| n.distance == 0.0).collect();
+
+    let weighted: Vec<(f64, f64)> = match self.options.weighting {
+      Weighting::Distance if !exact.is_empty() => {
+        exact.iter().map(|n| (1.0, self.y_train[n.index])).collect()
+      }
+      Weighting::Distance => neighbours
+        .iter()
+        .map(|n| (1.0 / n.distance, self.y_train[n.index]))
+        .collect(),
+      Weighting::Uniform => neighbours.iter().map(|n| (1.0, self.y_train[n.index])).collect(),
+    };
+
+    let total_weight: f64 = weighted.iter().map(|(weight, _)| weight).sum();
+    if total_weight > 0.0 {
+      weighted.iter().map(|(weight, y)| weight * y).sum::<f64>() / total_weight
+    } else {
+      f64::NAN
+    }
+  }
+}
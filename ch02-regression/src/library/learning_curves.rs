@@ -0,0 +1,367 @@
+use maud::Markup;
+use ndarray::Array2;
+use plotly::common::{ErrorData, ErrorType, Mode, Title};
+use plotly::layout::Axis;
+use plotly::{Layout, Scatter, Trace};
+use polars::prelude::{DataFrame, NamedFrom};
+use polars::series::Series;
+
+use crate::application_error::GenericResult;
+use crate::cross_validation::{k_fold, mean_std, select_rows};
+use crate::html_plot_figure::html_plot_figure;
+use crate::regression_functions::RegressionModel;
+
+/// Name of the column holding the number of rows used for training.
+pub const COL_TRAIN_SIZE: &str = "Train Size";
+/// Name of the column holding the fraction of the training split used for training.
+pub const COL_TRAIN_FRACTION: &str = "Train Fraction";
+/// Name of the column holding the mean squared error on the training rows.
+pub const COL_TRAIN_MSE: &str = "Train MSE";
+/// Name of the column holding the standard deviation of the training error across folds.
+pub const COL_TRAIN_MSE_STD: &str = "Train MSE Std";
+/// Name of the column holding the mean squared error on the validation rows.
+pub const COL_VALIDATION_MSE: &str = "Validation MSE";
+/// Name of the column holding the standard deviation of the validation error across folds.
+pub const COL_VALIDATION_MSE_STD: &str = "Validation MSE Std";
+
+/// Training inputs, training targets, validation inputs and validation targets.
+type SplitArrays = (Array2<f64>, Array2<f64>, Array2<f64>, Array2<f64>);
+
+/// Represents options for computing a learning curve.
+#[derive(Clone)]
+pub struct LearningCurveOptions {
+  /// Degree of the polynomial regression model.
+  pub degree: i32,
+  /// Ratio of the rows used as training split. The remaining rows are the validation split
+  /// when no cross-validation is requested.
+  pub split_ratio: f32,
+  /// Fractions of the training split on which the model is trained, e.g. `[0.1, 0.5, 1.0]`.
+  pub train_fractions: Vec<f32>,
+  /// Number of folds for cross-validating each training size over the training split.
+  pub cv_folds: Option<usize>,
+}
+
+impl LearningCurveOptions {
+  /// Creates a new instance of `[LearningCurveOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these learning curve options.
+  pub fn builder() -> LearningCurveOptionsBuilder {
+    LearningCurveOptionsBuilder::default()
+  }
+}
+
+impl Default for LearningCurveOptions {
+  fn default() -> Self {
+    LearningCurveOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[LearningCurveOptions]`.
+pub struct LearningCurveOptionsBuilder {
+  /// Degree of the polynomial regression model.
+  pub degree: i32,
+  /// Ratio of the rows used as training split.
+  pub split_ratio: f32,
+  /// Fractions of the training split on which the model is trained.
+  pub train_fractions: Vec<f32>,
+  /// Number of folds for cross-validating each training size.
+  pub cv_folds: Option<usize>,
+}
+
+impl LearningCurveOptionsBuilder {
+  /// Creates a new instance of `[LearningCurveOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the degree of the polynomial regression model.
+  pub fn degree(
+    mut self,
+    degree: i32,
+  ) -> Self {
+    self.degree = degree;
+    self
+  }
+
+  /// Sets the ratio of the rows used as training split.
+  pub fn split_ratio(
+    mut self,
+    split_ratio: f32,
+  ) -> Self {
+    self.split_ratio = split_ratio;
+    self
+  }
+
+  /// Sets the fractions of the training split on which the model is trained.
+  pub fn train_fractions(
+    mut self,
+    train_fractions: Vec<f32>,
+  ) -> Self {
+    self.train_fractions = train_fractions;
+    self
+  }
+
+  /// Sets the number of cross-validation folds, or `None` for a single validation split.
+  pub fn cv_folds(
+    mut self,
+    cv_folds: Option<usize>,
+  ) -> Self {
+    self.cv_folds = cv_folds;
+    self
+  }
+
+  /// Builds the instance of `[LearningCurveOptions]`.
+  pub fn build(self) -> LearningCurveOptions {
+    LearningCurveOptions {
+      degree: self.degree,
+      split_ratio: self.split_ratio,
+      train_fractions: self.train_fractions,
+      cv_folds: self.cv_folds,
+    }
+  }
+}
+
+impl Default for LearningCurveOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      degree: 1,
+      split_ratio: 0.8,
+      train_fractions: vec![0.1, 0.2, 0.3, 0.4, 0.5, 0.6, 0.7, 0.8, 0.9, 1.0],
+      cv_folds: None,
+    }
+  }
+}
+
+/// Computes the learning curve of a polynomial regression model: the training and validation
+/// errors of the model trained on increasing fractions of the training split.
+///
+/// Without cross-validation, the first `split_ratio` rows are the training split and the
+/// remaining rows are the validation split. With `k` folds, every fold of the training split is
+/// used once as validation set and the errors are averaged.
+///
+/// # Arguments
+///
+/// * `x`: Matrix of explanatory (input) variables.
+/// * `y`: Vector of response (output) variables.
+/// * `options`: Options of the learning curve.
+///
+/// # Returns
+///
+/// A dataframe with one row per training size and the columns `[COL_TRAIN_SIZE]`,
+/// `[COL_TRAIN_FRACTION]`, `[COL_TRAIN_MSE]`, `[COL_TRAIN_MSE_STD]`, `[COL_VALIDATION_MSE]`
+/// and `[COL_VALIDATION_MSE_STD]`. Sizes on which the model could not be fitted have null errors.
+pub fn learning_curve(
+  x: &Array2<f64>,
+  y: &Array2<f64>,
+  options: &LearningCurveOptions,
+) -> GenericResult<DataFrame> {
+  let (x_train, x_validation) = RegressionModel::split_data(x, options.split_ratio);
+  let (y_train, y_validation) = RegressionModel::split_data(y, options.split_ratio);
+
+  // Build the pairs of (training rows, validation rows) on which every training size is scored
+  let splits: Vec<SplitArrays> =
+    match options.cv_folds {
+      None => vec![(x_train, y_train, x_validation, y_validation)],
+      Some(k) => k_fold(x_train.nrows(), k)
+        .into_iter()
+        .map(|fold| {
+          (
+            select_rows(&x_train, &fold.train_indexes),
+            select_rows(&y_train, &fold.train_indexes),
+            select_rows(&x_train, &fold.validation_indexes),
+            select_rows(&y_train, &fold.validation_indexes),
+          )
+        })
+        .collect(),
+    };
+
+  let mut train_sizes: Vec<u32> = Vec::new();
+  let mut train_fractions: Vec<f64> = Vec::new();
+  let mut train_mse: Vec<Option<f64>> = Vec::new();
+  let mut train_mse_std: Vec<Option<f64>> = Vec::new();
+  let mut validation_mse: Vec<Option<f64>> = Vec::new();
+  let mut validation_mse_std: Vec<Option<f64>> = Vec::new();
+
+  for fraction in options.train_fractions.iter() {
+    let mut fold_train_errors: Vec<f64> = Vec::new();
+    let mut fold_validation_errors: Vec<f64> = Vec::new();
+    let mut fold_sizes: Vec<usize> = Vec::new();
+
+    for (x_fit, y_fit, x_val, y_val) in splits.iter() {
+      // Take the first rows of the training split, never more than available
+      let size = ((x_fit.nrows() as f32 * fraction).ceil() as usize).min(x_fit.nrows());
+      fold_sizes.push(size);
+
+      // A polynomial of degree d needs at least d + 1 rows to be determined
+      if size <= options.degree as usize || x_val.nrows() == 0 {
+        continue;
+      }
+
+      let indexes: Vec<usize> = (0..size).collect();
+      let mut model = RegressionModel::new(
+        select_rows(x_fit, &indexes),
+        select_rows(y_fit, &indexes),
+        1.0,
+      );
+
+      if model.try_solve(options.degree).is_err() {
+        continue;
+      }
+
+      let x_val_model = RegressionModel::polyfit_data(x_val, options.degree);
+      fold_train_errors.push(model.mse(&model.x_train, &model.y_train));
+      fold_validation_errors.push(model.mse(&x_val_model, y_val));
+    }
+
+    let size = fold_sizes.iter().sum::<usize>() / fold_sizes.len().max(1);
+    train_sizes.push(size as u32);
+    train_fractions.push(*fraction as f64);
+
+    if fold_train_errors.is_empty() {
+      train_mse.push(None);
+      train_mse_std.push(None);
+      validation_mse.push(None);
+      validation_mse_std.push(None);
+    } else {
+      let (train_mean, train_std) = mean_std(&fold_train_errors);
+      let (validation_mean, validation_std) = mean_std(&fold_validation_errors);
+      train_mse.push(Some(train_mean));
+      train_mse_std.push(Some(train_std));
+      validation_mse.push(Some(validation_mean));
+      validation_mse_std.push(Some(validation_std));
+    }
+  }
+
+  Ok(DataFrame::new(vec![
+    Series::new(COL_TRAIN_SIZE, train_sizes),
+    Series::new(COL_TRAIN_FRACTION, train_fractions),
+    Series::new(COL_TRAIN_MSE, train_mse),
+    Series::new(COL_TRAIN_MSE_STD, train_mse_std),
+    Series::new(COL_VALIDATION_MSE, validation_mse),
+    Series::new(COL_VALIDATION_MSE_STD, validation_mse_std),
+  ])?)
+}
+
+/// Generates a HTML figure plotting the training and validation errors of a learning curve
+/// against the training size, with the cross-validation standard deviations as error bars.
+///
+/// # Arguments
+///
+/// * `learning_curve_df`: Dataframe returned by `[learning_curve]`.
+/// * `caption`: Caption text of the figure.
+pub fn learning_curve_figure(
+  learning_curve_df: &DataFrame,
+  caption: &str,
+) -> GenericResult<Markup> {
+  let train_sizes: Vec<Option<u32>> =
+    learning_curve_df[COL_TRAIN_SIZE].u32()?.into_iter().collect();
+
+  let mut traces: Vec<Box<dyn Trace>> = Vec::new();
+
+  for (name, mse_column, std_column) in [
+    ("Training error", COL_TRAIN_MSE, COL_TRAIN_MSE_STD),
+    ("Validation error", COL_VALIDATION_MSE, COL_VALIDATION_MSE_STD),
+  ] {
+    let mse: Vec<Option<f64>> = learning_curve_df[mse_column].f64()?.into_iter().collect();
+    let std: Vec<f64> = learning_curve_df[std_column]
+      .f64()?
+      .into_iter()
+      .map(|value| value.unwrap_or(0.0))
+      .collect();
+
+    traces.push(
+      Scatter::new(train_sizes.clone(), mse)
+        .mode(Mode::LinesMarkers)
+        .name(name)
+        .error_y(ErrorData::new(ErrorType::Data).array(std)),
+    );
+  }
+
+  let layout = Layout::new()
+    .title(Title::new("Learning Curve"))
+    .x_axis(Axis::new().title(Title::new("Training Size (rows)")))
+    .y_axis(Axis::new().title(Title::new("Mean Squared Error (MSE)")));
+
+  html_plot_figure(traces, &layout, caption)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Gets the rows of `y = 2x + 1 + noise(i)` for `x` in 0 to `n_rows - 1`.
+  fn line(
+    n_rows: usize,
+    noise: impl Fn(usize) -> f64,
+  ) -> (Array2<f64>, Array2<f64>) {
+    let x = Array2::from_shape_fn((n_rows, 1), |(row, _)| row as f64);
+    let y = Array2::from_shape_fn((n_rows, 1), |(row, _)| 2.0 * row as f64 + 1.0 + noise(row));
+    (x, y)
+  }
+
+  /// Gets the values of a column of errors, without the sizes that could not be fitted.
+  fn errors(
+    curve: &DataFrame,
+    column: &str,
+  ) -> Vec<f64> {
+    curve
+      .column(column)
+      .and_then(|errors| errors.f64().cloned())
+      .expect("column of errors")
+      .into_iter()
+      .flatten()
+      .collect()
+  }
+
+  #[test]
+  fn exact_line_has_no_error_at_any_size() {
+    let (x, y) = line(50, |_| 0.0);
+    let curve =
+      learning_curve(&x, &y, &LearningCurveOptions::new()).expect("learning curve");
+
+    let sizes: Vec<u32> = curve
+      .column(COL_TRAIN_SIZE)
+      .and_then(|sizes| sizes.u32().cloned())
+      .expect("column of sizes")
+      .into_no_null_iter()
+      .collect();
+    assert_eq!(sizes, [4, 8, 12, 16, 20, 24, 28, 32, 36, 40]);
+    for mse in errors(&curve, COL_TRAIN_MSE)
+      .into_iter()
+      .chain(errors(&curve, COL_VALIDATION_MSE))
+    {
+      assert!(mse < 1e-10, "MSE {mse} of an exact line");
+    }
+  }
+
+  #[test]
+  fn noisy_line_training_error_does_not_decrease_with_more_rows() {
+    let (x, y) = line(50, |row| if row % 2 == 0 { 1.0 } else { -1.0 });
+    let curve =
+      learning_curve(&x, &y, &LearningCurveOptions::new()).expect("learning curve");
+
+    let train_mse = errors(&curve, COL_TRAIN_MSE);
+    assert_eq!(train_mse.len(), 10);
+    for pair in train_mse.windows(2) {
+      assert!(pair[1] >= pair[0] - 1e-9, "training MSE {train_mse:?}");
+    }
+    // The noise has a variance of 1, which the training error approaches from below
+    assert!(train_mse.iter().all(|mse| *mse <= 1.0 + 1e-9));
+  }
+
+  #[test]
+  fn sizes_too_small_for_the_degree_have_null_errors() {
+    let (x, y) = line(20, |_| 0.0);
+    let options = LearningCurveOptions::builder()
+      .degree(2)
+      .train_fractions(vec![0.1, 1.0])
+      .build();
+    let curve = learning_curve(&x, &y, &options).expect("learning curve");
+
+    assert_eq!(curve.column(COL_TRAIN_MSE).expect("column").null_count(), 1);
+    assert_eq!(errors(&curve, COL_TRAIN_MSE).len(), 1);
+  }
+}
@@ -0,0 +1,257 @@
+use ndarray::Array1;
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::sparse_matrix::DesignMatrix;
+
+/// Represents options for the iterative least-squares solvers.
+#[derive(Clone)]
+pub struct SolverOptions {
+  /// Ridge (L2) regularization strength λ, applied to every parameter.
+  pub λ: f64,
+  /// Maximum number of iterations.
+  pub max_iterations: usize,
+  /// Convergence tolerance on the relative norm of the gradient of the normal equations.
+  pub tolerance: f64,
+  /// Step size of the gradient descent solver.
+  pub learning_rate: f64,
+}
+
+impl SolverOptions {
+  /// Creates a new instance of `[SolverOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these solver options.
+  pub fn builder() -> SolverOptionsBuilder {
+    SolverOptionsBuilder::default()
+  }
+}
+
+impl Default for SolverOptions {
+  fn default() -> Self {
+    SolverOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[SolverOptions]`.
+pub struct SolverOptionsBuilder {
+  /// Ridge (L2) regularization strength λ.
+  pub λ: f64,
+  /// Maximum number of iterations.
+  pub max_iterations: usize,
+  /// Convergence tolerance.
+  pub tolerance: f64,
+  /// Step size of the gradient descent solver.
+  pub learning_rate: f64,
+}
+
+impl SolverOptionsBuilder {
+  /// Creates a new instance of `[SolverOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the ridge (L2) regularization strength λ.
+  pub fn λ(
+    mut self,
+    λ: f64,
+  ) -> Self {
+    self.λ = λ;
+    self
+  }
+
+  /// Sets the maximum number of iterations.
+  pub fn max_iterations(
+    mut self,
+    max_iterations: usize,
+  ) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  /// Sets the convergence tolerance.
+  pub fn tolerance(
+    mut self,
+    tolerance: f64,
+  ) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  /// Sets the step size of the gradient descent solver.
+  pub fn learning_rate(
+    mut self,
+    learning_rate: f64,
+  ) -> Self {
+    self.learning_rate = learning_rate;
+    self
+  }
+
+  /// Builds the instance of `[SolverOptions]`.
+  pub fn build(self) -> SolverOptions {
+    SolverOptions {
+      λ: self.λ,
+      max_iterations: self.max_iterations,
+      tolerance: self.tolerance,
+      learning_rate: self.learning_rate,
+    }
+  }
+}
+
+impl Default for SolverOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      λ: 0.0,
+      max_iterations: 1000,
+      tolerance: 1e-10,
+      learning_rate: 0.01,
+    }
+  }
+}
+
+/// Represents the solution of a least-squares problem.
+#[derive(Clone, Debug)]
+pub struct LeastSquaresSolution {
+  /// Vector of estimated parameters.
+  pub β: Array1<f64>,
+  /// Number of iterations run.
+  pub iterations: usize,
+  /// Whether the solver reached the tolerance within the maximum number of iterations.
+  pub converged: bool,
+  /// Relative norm of the gradient of the normal equations at the solution.
+  pub relative_residual: f64,
+}
+
+/// Solves the (ridge) least-squares problem min ‖y - Xβ‖² + λ‖β‖² by the conjugate gradient
+/// method on the normal equations (XᵀX + λI)β = Xᵀy, with a Jacobi preconditioner.
+///
+/// XᵀX is built in sparse form, so the memory used grows with its non-zero values instead of
+/// the size of X.
+///
+/// # Arguments
+///
+/// * `X`: Dense or sparse design matrix.
+/// * `y`: Vector of response (output) variables.
+/// * `options`: Options of the solver.
+#[allow(non_snake_case)]
+pub fn solve_least_squares<M: DesignMatrix>(
+  X: &M,
+  y: &Array1<f64>,
+  options: &SolverOptions,
+) -> GenericResult<LeastSquaresSolution> {
+  check_dimensions(X, y)?;
+
+  let gram = X.gram();
+  let apply = |v: &Array1<f64>| gram.dot_vector(v) + &(v * options.λ);
+
+  // Jacobi preconditioner: the inverse of the diagonal of XᵀX + λI
+  let preconditioner = gram
+    .diagonal()
+    .mapv(|value| if value + options.λ > 0.0 { 1.0 / (value + options.λ) } else { 1.0 });
+
+  let b = X.t_dot_vector(y);
+  let b_norm = b.dot(&b).sqrt().max(f64::MIN_POSITIVE);
+
+  let mut β = Array1::<f64>::zeros(X.n_cols());
+  let mut r = b.clone();
+  let mut z = &r * &preconditioner;
+  let mut p = z.clone();
+  let mut rz = r.dot(&z);
+  let mut relative_residual = r.dot(&r).sqrt() / b_norm;
+  let mut iterations = 0;
+
+  while iterations < options.max_iterations && relative_residual > options.tolerance {
+    iterations += 1;
+
+    let ap = apply(&p);
+    let p_ap = p.dot(&ap);
+    if p_ap <= 0.0 {
+      // The remaining directions are in the null space of XᵀX
+      break;
+    }
+
+    let step_size = rz / p_ap;
+    β.scaled_add(step_size, &p);
+    r.scaled_add(-step_size, &ap);
+    relative_residual = r.dot(&r).sqrt() / b_norm;
+
+    z = &r * &preconditioner;
+    let rz_next = r.dot(&z);
+    p = &z + &(&p * (rz_next / rz));
+    rz = rz_next;
+  }
+
+  Ok(LeastSquaresSolution {
+    β,
+    iterations,
+    converged: relative_residual <= options.tolerance,
+    relative_residual,
+  })
+}
+
+/// Solves the (ridge) least-squares problem by batch gradient descent on the mean squared
+/// error: β ← β - η (2/n Xᵀ(Xβ - y) + 2λβ).
+///
+/// # Arguments
+///
+/// * `X`: Dense or sparse design matrix. Features should be scaled for a stable step size.
+/// * `y`: Vector of response (output) variables.
+/// * `options`: Options of the solver; `learning_rate` is the step size η.
+#[allow(non_snake_case)]
+pub fn solve_gradient_descent<M: DesignMatrix>(
+  X: &M,
+  y: &Array1<f64>,
+  options: &SolverOptions,
+) -> GenericResult<LeastSquaresSolution> {
+  check_dimensions(X, y)?;
+
+  let n = X.n_rows() as f64;
+  let b_norm = X.t_dot_vector(y).mapv(f64::abs).sum().max(f64::MIN_POSITIVE);
+
+  let mut β = Array1::<f64>::zeros(X.n_cols());
+  let mut relative_residual = f64::INFINITY;
+  let mut iterations = 0;
+
+  while iterations < options.max_iterations && relative_residual > options.tolerance {
+    iterations += 1;
+
+    let residuals = X.dot_vector(&β) - y;
+    let gradient = X.t_dot_vector(&residuals) * (2.0 / n) + &(&β * (2.0 * options.λ));
+    relative_residual = gradient.mapv(f64::abs).sum() * n / (2.0 * b_norm);
+
+    if !relative_residual.is_finite() {
+      return Err(
+        GenericError::from("Gradient descent diverged; reduce the learning rate").into(),
+      );
+    }
+
+    β.scaled_add(-options.learning_rate, &gradient);
+  }
+
+  Ok(LeastSquaresSolution {
+    β,
+    iterations,
+    converged: relative_residual <= options.tolerance,
+    relative_residual,
+  })
+}
+
+/// Checks that the design matrix and the response vector have the same number of rows.
+#[allow(non_snake_case)]
+fn check_dimensions<M: DesignMatrix>(
+  X: &M,
+  y: &Array1<f64>,
+) -> GenericResult<()> {
+  if X.n_rows() != y.len() {
+    return Err(
+      GenericError::from(format!(
+        "The design matrix has {} rows but the response vector has {} values",
+        X.n_rows(),
+        y.len()
+      ))
+      .into(),
+    );
+  }
+  Ok(())
+}
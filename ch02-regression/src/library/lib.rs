@@ -1,7 +1,32 @@
 pub mod application_error;
 pub mod sample_options;
+pub mod csv_schema;
+pub mod dataset_loader;
+pub mod dataset_export;
+pub mod dataset_registry;
+pub mod dataframe_cache;
 pub mod display_options;
+pub mod date_features;
+pub mod pumpkins;
 pub mod regression_functions;
+pub mod cross_validation;
+pub mod learning_curves;
+pub mod hyperparameter_search;
+pub mod temporal_split;
+pub mod generalized_linear_model;
+pub mod sparse_matrix;
+pub mod least_squares;
+pub mod kernel_regression;
+pub mod tree_regressors;
+pub mod knn_regression;
+pub mod isotonic_regression;
+pub mod nonlinear_least_squares;
+pub mod formula;
+pub mod categorical_encoders;
+pub mod imputation;
+pub mod outliers;
+pub mod data_profile;
+pub mod provenance;
 pub mod html_dataframe;
 pub mod html_plot_figure;
 pub mod partials;
\ No newline at end of file
@@ -0,0 +1,445 @@
+use linfa_linalg::qr::QRInto;
+use ndarray::{Array1, Array2, Axis};
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Function of a nonlinear model: maps the inputs (one observation per row) and the
+/// parameters β to one prediction per row.
+pub type ModelFunction<'a> = Box<dyn Fn(&Array2<f64>, &Array1<f64>) -> Array1<f64> + 'a>;
+
+/// Jacobian of a nonlinear model: maps the inputs and the parameters β to the matrix of
+/// partial derivatives ∂f(xᵢ; β)/∂βⱼ, one row per observation and one column per parameter.
+pub type JacobianFunction<'a> =
+  Box<dyn Fn(&Array2<f64>, &Array1<f64>) -> Array2<f64> + 'a>;
+
+/// Represents options for the Levenberg–Marquardt solver.
+#[derive(Clone)]
+pub struct LevenbergMarquardtOptions {
+  /// Maximum number of iterations.
+  pub max_iterations: usize,
+  /// Convergence tolerance on the relative size of the step and of the decrease of the RSS.
+  pub tolerance: f64,
+  /// Convergence tolerance on the largest absolute value of the gradient Jᵀr.
+  pub gradient_tolerance: f64,
+  /// Initial damping factor μ.
+  pub initial_damping: f64,
+  /// Relative step of the central finite differences used when there is no analytic Jacobian.
+  pub finite_difference_step: f64,
+}
+
+impl LevenbergMarquardtOptions {
+  /// Creates a new instance of `[LevenbergMarquardtOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these solver options.
+  pub fn builder() -> LevenbergMarquardtOptionsBuilder {
+    LevenbergMarquardtOptionsBuilder::default()
+  }
+}
+
+impl Default for LevenbergMarquardtOptions {
+  fn default() -> Self {
+    LevenbergMarquardtOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[LevenbergMarquardtOptions]`.
+pub struct LevenbergMarquardtOptionsBuilder {
+  /// Maximum number of iterations.
+  pub max_iterations: usize,
+  /// Convergence tolerance on the step and the decrease of the RSS.
+  pub tolerance: f64,
+  /// Convergence tolerance on the gradient.
+  pub gradient_tolerance: f64,
+  /// Initial damping factor μ.
+  pub initial_damping: f64,
+  /// Relative step of the finite differences.
+  pub finite_difference_step: f64,
+}
+
+impl LevenbergMarquardtOptionsBuilder {
+  /// Creates a new instance of `[LevenbergMarquardtOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the maximum number of iterations.
+  pub fn max_iterations(
+    mut self,
+    max_iterations: usize,
+  ) -> Self {
+    self.max_iterations = max_iterations;
+    self
+  }
+
+  /// Sets the convergence tolerance on the step and the decrease of the RSS.
+  pub fn tolerance(
+    mut self,
+    tolerance: f64,
+  ) -> Self {
+    self.tolerance = tolerance;
+    self
+  }
+
+  /// Sets the convergence tolerance on the gradient.
+  pub fn gradient_tolerance(
+    mut self,
+    gradient_tolerance: f64,
+  ) -> Self {
+    self.gradient_tolerance = gradient_tolerance;
+    self
+  }
+
+  /// Sets the initial damping factor μ.
+  pub fn initial_damping(
+    mut self,
+    initial_damping: f64,
+  ) -> Self {
+    self.initial_damping = initial_damping;
+    self
+  }
+
+  /// Sets the relative step of the finite differences.
+  pub fn finite_difference_step(
+    mut self,
+    finite_difference_step: f64,
+  ) -> Self {
+    self.finite_difference_step = finite_difference_step;
+    self
+  }
+
+  /// Builds the instance of `[LevenbergMarquardtOptions]`.
+  pub fn build(self) -> LevenbergMarquardtOptions {
+    LevenbergMarquardtOptions {
+      max_iterations: self.max_iterations,
+      tolerance: self.tolerance,
+      gradient_tolerance: self.gradient_tolerance,
+      initial_damping: self.initial_damping,
+      finite_difference_step: self.finite_difference_step,
+    }
+  }
+}
+
+impl Default for LevenbergMarquardtOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      max_iterations: 200,
+      tolerance: 1e-10,
+      gradient_tolerance: 1e-10,
+      initial_damping: 1e-3,
+      finite_difference_step: 1e-6,
+    }
+  }
+}
+
+/// Represents why the Levenberg–Marquardt solver stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConvergenceStatus {
+  /// The step became smaller than the tolerance.
+  SmallStep,
+  /// The relative decrease of the RSS became smaller than the tolerance.
+  SmallDecrease,
+  /// The gradient Jᵀr became smaller than the gradient tolerance.
+  SmallGradient,
+  /// The maximum number of iterations was reached.
+  MaxIterations,
+  /// No step decreased the RSS, even with the largest damping.
+  Stalled,
+}
+
+impl ConvergenceStatus {
+  /// Whether the solver reached one of its tolerances.
+  pub fn is_converged(&self) -> bool {
+    matches!(
+      self,
+      Self::SmallStep | Self::SmallDecrease | Self::SmallGradient
+    )
+  }
+
+  /// Gets the display name of the status.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::SmallStep => "Converged (small step)",
+      Self::SmallDecrease => "Converged (small RSS decrease)",
+      Self::SmallGradient => "Converged (small gradient)",
+      Self::MaxIterations => "Maximum iterations reached",
+      Self::Stalled => "Stalled",
+    }
+  }
+}
+
+/// Represents the result of a nonlinear least-squares fit.
+#[derive(Clone, Debug)]
+pub struct NonlinearFit {
+  /// Vector of estimated parameters.
+  pub β: Array1<f64>,
+  /// Residuals y - f(x; β) at the estimated parameters.
+  pub residuals: Array1<f64>,
+  /// Residual sum of squares at the estimated parameters.
+  pub rss: f64,
+  /// Asymptotic covariance of the parameters: σ²(JᵀJ)⁻¹ with σ² = RSS / (n - p).
+  pub covariance: Array2<f64>,
+  /// Number of iterations run.
+  pub iterations: usize,
+  /// Why the solver stopped.
+  pub status: ConvergenceStatus,
+}
+
+impl NonlinearFit {
+  /// Gets the standard error of every parameter, the square root of the covariance diagonal.
+  pub fn standard_errors(&self) -> Array1<f64> {
+    self.covariance.diag().mapv(f64::sqrt)
+  }
+
+  /// Calculates the mean squared error of the fit on the training data.
+  pub fn mse(&self) -> f64 {
+    self.rss / self.residuals.len().max(1) as f64
+  }
+
+  /// Calculates the coefficient of determination r² of the fit on the training responses.
+  pub fn r2(
+    &self,
+    y: &Array2<f64>,
+  ) -> f64 {
+    let y_mean = y.mean().unwrap_or(0.0);
+    1.0 - self.rss / y.mapv(|value| (value - y_mean).powi(2)).sum()
+  }
+
+  /// Gets a display string of the parameters with their standard errors, e.g. "peak = 280.1 ± 3.2".
+  pub fn parameters_to_string(
+    &self,
+    parameter_names: &[String],
+  ) -> String {
+    parameter_names
+      .iter()
+      .zip(self.β.iter().zip(self.standard_errors().iter()))
+      .map(|(name, (value, error))| format!("{name} = {value:.5} ± {error:.5}"))
+      .collect::<Vec<String>>()
+      .join(",\n")
+  }
+}
+
+/// Represents a model that is not linear in its parameters, e.g. a Gaussian bump
+/// f(x; β) = β₀ + β₁ exp(-(x - β₂)² / (2β₃²)), fitted with the Levenberg–Marquardt algorithm.
+pub struct NonlinearModel<'a> {
+  /// Names of the parameters, in the order of β.
+  pub parameter_names: Vec<String>,
+  function: ModelFunction<'a>,
+  jacobian: Option<JacobianFunction<'a>>,
+}
+
+impl<'a> NonlinearModel<'a> {
+  /// Creates a model from its function. Without an analytic Jacobian the solver uses central
+  /// finite differences.
+  ///
+  /// # Arguments
+  ///
+  /// * `parameter_names`: Names of the parameters, in the order of β.
+  /// * `function`: Model function f(x; β), evaluated on all the rows of x at once.
+  pub fn new(
+    parameter_names: &[&str],
+    function: impl Fn(&Array2<f64>, &Array1<f64>) -> Array1<f64> + 'a,
+  ) -> Self {
+    Self {
+      parameter_names: parameter_names
+        .iter()
+        .map(|name| name.to_string())
+        .collect(),
+      function: Box::new(function),
+      jacobian: None,
+    }
+  }
+
+  /// Sets the analytic Jacobian of the model function.
+  pub fn jacobian(
+    mut self,
+    jacobian: impl Fn(&Array2<f64>, &Array1<f64>) -> Array2<f64> + 'a,
+  ) -> Self {
+    self.jacobian = Some(Box::new(jacobian));
+    self
+  }
+
+  /// Whether the model has an analytic Jacobian.
+  pub fn has_jacobian(&self) -> bool {
+    self.jacobian.is_some()
+  }
+
+  /// Predicts the response for the given inputs as a column vector.
+  pub fn predict(
+    &self,
+    x: &Array2<f64>,
+    β: &Array1<f64>,
+  ) -> Array2<f64> {
+    (self.function)(x, β).insert_axis(Axis(1))
+  }
+
+  /// Fits the parameters by minimizing the residual sum of squares with the
+  /// Levenberg–Marquardt algorithm, scaling the damping by the diagonal of JᵀJ.
+  ///
+  /// # Arguments
+  ///
+  /// * `x`: Matrix of explanatory (input) variables.
+  /// * `y`: Vector of response (output) variables, as a column.
+  /// * `initial_β`: Starting parameters; nonlinear fits need a sensible guess.
+  /// * `options`: Options of the solver.
+  pub fn fit(
+    &self,
+    x: &Array2<f64>,
+    y: &Array2<f64>,
+    initial_β: &Array1<f64>,
+    options: &LevenbergMarquardtOptions,
+  ) -> GenericResult<NonlinearFit> {
+    if x.nrows() != y.nrows() {
+      return Err(
+        GenericError::from(format!(
+          "The inputs have {} rows but the responses have {} rows",
+          x.nrows(),
+          y.nrows()
+        ))
+        .into(),
+      );
+    }
+    if initial_β.len() != self.parameter_names.len() {
+      return Err(
+        GenericError::from(format!(
+          "The model has {} parameters but {} initial values were given",
+          self.parameter_names.len(),
+          initial_β.len()
+        ))
+        .into(),
+      );
+    }
+
+    let y = y.column(0).to_owned();
+    let mut β = initial_β.clone();
+    let mut residuals = &y - &(self.function)(x, &β);
+    let mut rss = residuals.dot(&residuals);
+    let mut μ = options.initial_damping;
+    let mut iterations = 0;
+    let mut status = ConvergenceStatus::MaxIterations;
+
+    if !rss.is_finite() {
+      return Err(
+        GenericError::from("The model is not finite at the initial parameters").into(),
+      );
+    }
+
+    while iterations < options.max_iterations {
+      iterations += 1;
+
+      let jacobian = self.evaluate_jacobian(x, &β, options.finite_difference_step);
+      let jtj = jacobian.t().dot(&jacobian);
+      let gradient = jacobian.t().dot(&residuals);
+
+      if gradient
+        .iter()
+        .all(|value| value.abs() <= options.gradient_tolerance)
+      {
+        status = ConvergenceStatus::SmallGradient;
+        break;
+      }
+
+      // Increase the damping until a step decreases the RSS
+      let mut accepted = None;
+      while μ <= 1e16 {
+        let mut damped = jtj.clone();
+        for j in 0..damped.nrows() {
+          damped[(j, j)] += μ * jtj[(j, j)].max(1e-12);
+        }
+
+        let step = damped
+          .qr_into()
+          .and_then(|qr| qr.inverse())
+          .map(|inverse| inverse.dot(&gradient));
+
+        if let Ok(step) = step {
+          let candidate_β = &β + &step;
+          let candidate_residuals = &y - &(self.function)(x, &candidate_β);
+          let candidate_rss = candidate_residuals.dot(&candidate_residuals);
+
+          if candidate_rss.is_finite() && candidate_rss < rss {
+            μ = (μ / 10.0).max(1e-12);
+            accepted = Some((step, candidate_β, candidate_residuals, candidate_rss));
+            break;
+          }
+        }
+        μ *= 10.0;
+      }
+
+      let Some((step, candidate_β, candidate_residuals, candidate_rss)) = accepted
+      else {
+        status = ConvergenceStatus::Stalled;
+        break;
+      };
+
+      let step_norm = step.dot(&step).sqrt();
+      let β_norm = β.dot(&β).sqrt();
+      let decrease = (rss - candidate_rss) / rss.max(f64::MIN_POSITIVE);
+
+      β = candidate_β;
+      residuals = candidate_residuals;
+      rss = candidate_rss;
+
+      if step_norm <= options.tolerance * (β_norm + options.tolerance) {
+        status = ConvergenceStatus::SmallStep;
+        break;
+      }
+      if decrease <= options.tolerance {
+        status = ConvergenceStatus::SmallDecrease;
+        break;
+      }
+    }
+
+    let jacobian = self.evaluate_jacobian(x, &β, options.finite_difference_step);
+    let degrees_of_freedom = x.nrows() as f64 - β.len() as f64;
+    let σ2 = if degrees_of_freedom > 0.0 {
+      rss / degrees_of_freedom
+    } else {
+      f64::NAN
+    };
+    let covariance = jacobian
+      .t()
+      .dot(&jacobian)
+      .qr_into()
+      .and_then(|qr| qr.inverse())
+      .map(|inverse| inverse * σ2)
+      .unwrap_or_else(|_| Array2::from_elem((β.len(), β.len()), f64::NAN));
+
+    Ok(NonlinearFit {
+      β,
+      residuals,
+      rss,
+      covariance,
+      iterations,
+      status,
+    })
+  }
+
+  /// Evaluates the Jacobian, analytically when available or by central finite differences.
+  fn evaluate_jacobian(
+    &self,
+    x: &Array2<f64>,
+    β: &Array1<f64>,
+    relative_step: f64,
+  ) -> Array2<f64> {
+    if let Some(jacobian) = &self.jacobian {
+      return jacobian(x, β);
+    }
+
+    let mut jacobian = Array2::<f64>::zeros((x.nrows(), β.len()));
+    for j in 0..β.len() {
+      let h = relative_step * β[j].abs().max(1.0);
+      let mut β_forward = β.clone();
+      let mut β_backward = β.clone();
+      β_forward[j] += h;
+      β_backward[j] -= h;
+
+      let derivative =
+        ((self.function)(x, &β_forward) - (self.function)(x, &β_backward)) / (2.0 * h);
+      jacobian.column_mut(j).assign(&derivative);
+    }
+    jacobian
+  }
+}
@@ -0,0 +1,275 @@
+use polars::prelude::*;
+
+use crate::application_error::GenericResult;
+
+/// Name of the temporary column with the lower fence of a rule.
+const LOWER_FENCE: &str = "__lower_fence";
+/// Name of the temporary column with the upper fence of a rule.
+const UPPER_FENCE: &str = "__upper_fence";
+/// Name of the temporary column with the median used by the modified z-score.
+const MEDIAN: &str = "__median";
+
+/// Scale of the modified z-score: 0.6745 is the 75th percentile of the standard normal, so the
+/// MAD of normal data times 1 / 0.6745 estimates its standard deviation.
+const MAD_SCALE: f64 = 0.6745;
+
+/// Represents how the fences of the outliers of a column are calculated.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutlierMethod {
+  /// Tukey fences: values below Q1 - factor * IQR or above Q3 + factor * IQR, usually 1.5.
+  Iqr { factor: f64 },
+  /// Values farther than `threshold` standard deviations from the mean, usually 3.
+  ZScore { threshold: f64 },
+  /// Values whose modified z-score 0.6745 * |x - median| / MAD is greater than `threshold`,
+  /// usually 3.5. It is robust to the outliers themselves, unlike the z-score.
+  ModifiedZScore { threshold: f64 },
+}
+
+impl OutlierMethod {
+  /// Gets the display name of the method.
+  pub fn name(&self) -> String {
+    match self {
+      Self::Iqr { factor } => format!("IQR (factor {factor})"),
+      Self::ZScore { threshold } => format!("Z-score (threshold {threshold})"),
+      Self::ModifiedZScore { threshold } => {
+        format!("Modified z-score (threshold {threshold})")
+      }
+    }
+  }
+}
+
+/// Represents what happens to the rows with an outlier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutlierAction {
+  /// Removes the rows.
+  Drop,
+  /// Replaces the outliers with the nearest fence (winsorizing).
+  Cap,
+  /// Adds a boolean `"<column> Outlier"` column, true for the outliers.
+  Flag,
+}
+
+impl OutlierAction {
+  /// Gets the display name of the action.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Drop => "Drop",
+      Self::Cap => "Cap",
+      Self::Flag => "Flag",
+    }
+  }
+}
+
+/// Represents a rule that detects the outliers of a numeric column, optionally per group, and
+/// handles them with an action. Nulls are never outliers, and a group whose spread is zero
+/// has no outliers.
+#[derive(Clone, Debug)]
+pub struct OutlierRule {
+  /// Name of the numeric column.
+  pub column: String,
+  /// Method that calculates the fences.
+  pub method: OutlierMethod,
+  /// Columns that define the groups with their own fences, e.g. `["Package"]`. Empty for one
+  /// group with every row.
+  pub group_by: Vec<String>,
+  /// What happens to the outliers.
+  pub action: OutlierAction,
+}
+
+impl OutlierRule {
+  /// Creates a new rule over every row that drops the outliers.
+  pub fn new(
+    column: &str,
+    method: OutlierMethod,
+  ) -> Self {
+    Self {
+      column: column.to_string(),
+      method,
+      group_by: Vec::new(),
+      action: OutlierAction::Drop,
+    }
+  }
+
+  /// Sets the columns that define the groups.
+  pub fn group_by(
+    mut self,
+    group_by: &[&str],
+  ) -> Self {
+    self.group_by = group_by.iter().map(|name| name.to_string()).collect();
+    self
+  }
+
+  /// Sets what happens to the outliers.
+  pub fn action(
+    mut self,
+    action: OutlierAction,
+  ) -> Self {
+    self.action = action;
+    self
+  }
+
+  /// Gets the name of the flag column.
+  pub fn flag_name(&self) -> String {
+    format!("{} Outlier", self.column)
+  }
+
+  /// Gets a display description of the rule, e.g. "Price: IQR (factor 1.5) per Package".
+  pub fn description(&self) -> String {
+    if self.group_by.is_empty() {
+      format!("{}: {}", self.column, self.method.name())
+    } else {
+      format!(
+        "{}: {} per {}",
+        self.column,
+        self.method.name(),
+        self.group_by.join(", ")
+      )
+    }
+  }
+
+  /// Applies the rule to a lazy frame.
+  pub fn apply(
+    &self,
+    lf: LazyFrame,
+  ) -> LazyFrame {
+    let value = col(&self.column).cast(DataType::Float64);
+    let is_outlier = value
+      .clone()
+      .lt(col(LOWER_FENCE))
+      .or(value.clone().gt(col(UPPER_FENCE)))
+      .fill_null(lit(false));
+
+    let lf = self.with_fences(lf);
+    let lf = match self.action {
+      OutlierAction::Drop => lf.filter(is_outlier.not()),
+      OutlierAction::Cap => lf.with_column(
+        when(value.clone().lt(col(LOWER_FENCE)))
+          .then(col(LOWER_FENCE))
+          .when(value.clone().gt(col(UPPER_FENCE)))
+          .then(col(UPPER_FENCE))
+          .otherwise(value)
+          .alias(&self.column),
+      ),
+      OutlierAction::Flag => lf.with_column(is_outlier.alias(&self.flag_name())),
+    };
+
+    lf.drop_columns([LOWER_FENCE, UPPER_FENCE, MEDIAN])
+  }
+
+  /// Counts the outliers of a dataframe.
+  pub fn count(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<usize> {
+    let flags = self
+      .clone()
+      .action(OutlierAction::Flag)
+      .apply(df.clone().lazy())
+      .select([col(&self.flag_name())])
+      .collect()?;
+
+    Ok(flags.column(&self.flag_name())?.bool()?.sum().unwrap_or(0) as usize)
+  }
+
+  /// Adds the lower and upper fence columns, and the median column that only the modified
+  /// z-score uses.
+  fn with_fences(
+    &self,
+    lf: LazyFrame,
+  ) -> LazyFrame {
+    let value = col(&self.column).cast(DataType::Float64);
+    let lf = lf.with_column(self.over(value.clone().median()).alias(MEDIAN));
+
+    let (lower, upper) = match self.method {
+      OutlierMethod::Iqr { factor } => {
+        let q1 = self.over(
+          value
+            .clone()
+            .quantile(lit(0.25), QuantileInterpolOptions::Linear),
+        );
+        let q3 = self.over(value.quantile(lit(0.75), QuantileInterpolOptions::Linear));
+        let iqr = q3.clone() - q1.clone();
+        (q1 - lit(factor) * iqr.clone(), q3 + lit(factor) * iqr)
+      }
+      OutlierMethod::ZScore { threshold } => {
+        let mean = self.over(value.clone().mean());
+        let std = self.over(value.std(1));
+        (
+          mean.clone() - lit(threshold) * std.clone(),
+          mean + lit(threshold) * std,
+        )
+      }
+      OutlierMethod::ModifiedZScore { threshold } => {
+        let mad = self.over((value - col(MEDIAN)).abs().median());
+        let width = lit(threshold / MAD_SCALE) * mad;
+        (col(MEDIAN) - width.clone(), col(MEDIAN) + width)
+      }
+    };
+
+    // A zero spread would flag every value other than the centre: keep the whole group instead
+    let degenerate = lower.clone().eq(upper.clone());
+    lf.with_columns([
+      when(degenerate.clone())
+        .then(lit(f64::NEG_INFINITY))
+        .otherwise(lower)
+        .alias(LOWER_FENCE),
+      when(degenerate)
+        .then(lit(f64::INFINITY))
+        .otherwise(upper)
+        .alias(UPPER_FENCE),
+    ])
+  }
+
+  /// Evaluates an aggregation per group, or over every row without groups.
+  fn over(
+    &self,
+    expr: Expr,
+  ) -> Expr {
+    if self.group_by.is_empty() {
+      expr
+    } else {
+      let groups: Vec<Expr> = self.group_by.iter().map(|name| col(name)).collect();
+      expr.over(groups)
+    }
+  }
+}
+
+/// Applies the rules in order, and reports the rows of every step.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe with the columns of the rules.
+/// * `rules`: Rules applied in order; every rule sees the result of the previous ones.
+///
+/// Returns the resulting dataframe and a report with the columns Rule, Action, Outliers,
+/// Rows Before and Rows After.
+pub fn apply_outlier_rules(
+  df: &DataFrame,
+  rules: &[OutlierRule],
+) -> GenericResult<(DataFrame, DataFrame)> {
+  let mut result = df.clone();
+  let mut descriptions: Vec<String> = Vec::new();
+  let mut actions: Vec<&str> = Vec::new();
+  let mut outliers: Vec<u32> = Vec::new();
+  let mut rows_before: Vec<u32> = Vec::new();
+  let mut rows_after: Vec<u32> = Vec::new();
+
+  for rule in rules {
+    descriptions.push(rule.description());
+    actions.push(rule.action.name());
+    outliers.push(rule.count(&result)? as u32);
+    rows_before.push(result.height() as u32);
+    result = rule.apply(result.lazy()).collect()?;
+    rows_after.push(result.height() as u32);
+  }
+
+  let report = df!(
+    "Rule" => descriptions,
+    "Action" => actions,
+    "Outliers" => outliers,
+    "Rows Before" => rows_before,
+    "Rows After" => rows_after,
+  )?;
+
+  Ok((result, report))
+}
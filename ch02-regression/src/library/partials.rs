@@ -1,4 +1,6 @@
-use crate::application_error::GenericResult;
+use crate::application_error::{GenericError, GenericResult};
+use crate::dataset_loader::DatasetFormat;
+use crate::provenance::Provenance;
 use maud::{html, Markup, PreEscaped, DOCTYPE};
 
 /// Create an HTML page using the Maud library.
@@ -7,6 +9,7 @@ use maud::{html, Markup, PreEscaped, DOCTYPE};
 ///
 /// * `page_title`: Title of the page
 /// * `article_elements`: Collection of HTML elements to build an article element for the notebook.
+/// * `provenance`: Where the numbers of the notebook come from, shown in its footer.
 ///
 /// # Returns
 ///
@@ -14,6 +17,7 @@ use maud::{html, Markup, PreEscaped, DOCTYPE};
 pub fn create_html_notebook(
   page_title: &str,
   article_elements: Vec<PreEscaped<String>>,
+  provenance: &Provenance,
 ) -> GenericResult<Markup> {
   // Build the article with the given elements
   let article = html!({
@@ -35,6 +39,7 @@ pub fn create_html_notebook(
     }
     body {
       (article)
+      (html_provenance(provenance)?)
     }
   });
 
@@ -72,3 +77,88 @@ pub fn create_html_page(
 
   Ok(page)
 }
+
+/// Create links to download a table in several formats.
+///
+/// # Arguments
+///
+/// * `url`: URL of the table without extension; the extension of the format is appended.
+/// * `formats`: Formats of the links, in order.
+///
+/// # Returns
+///
+/// A paragraph with one link per format.
+pub fn html_download_links(
+  url: &str,
+  formats: &[DatasetFormat],
+) -> Markup {
+  html!({
+    p .download-links {
+      "Download: "
+      @for (index, format) in formats.iter().enumerate() {
+        @if index > 0 { " · " }
+        a href={ (url) "." (format.extension()) } download { (format.name()) }
+      }
+    }
+  })
+}
+
+/// Create the footer of a notebook with its provenance: a collapsible summary, and the same data
+/// as JSON in a `script` element with the id `provenance`, for tools.
+///
+/// # Arguments
+///
+/// * `provenance`: Where the numbers of the notebook come from.
+///
+/// # Returns
+///
+/// A result containing the footer.
+pub fn html_provenance(provenance: &Provenance) -> GenericResult<Markup> {
+  // "</" would end the script element, and "<\/" is the same JSON string
+  let json = serde_json::to_string(&provenance.to_json())
+    .map_err(|error| GenericError::from(error.to_string()))?
+    .replace("</", "<\\/");
+
+  Ok(html!({
+    footer .provenance {
+      details {
+        summary { "Provenance: rendered " (provenance.rendered_at_rfc3339()) " by version " (provenance.crate_version) }
+        @if !provenance.datasets.is_empty() {
+          table .dataframe-table {
+            thead {
+              tr { th { "Dataset" } th { "Path" } th { "Format" } th { "Content Hash (FNV-1a 64)" } th { "Rows" } th { "Columns" } }
+            }
+            tbody {
+              @for dataset in &provenance.datasets {
+                tr {
+                  td { (dataset.name) }
+                  td { code { (dataset.path) } }
+                  td { (dataset.format) }
+                  td { code { (dataset.content_hash_hex()) } }
+                  td .numeric-value { (dataset.rows) }
+                  td .numeric-value { (dataset.columns) }
+                }
+              }
+            }
+          }
+        }
+        @if !provenance.steps.is_empty() {
+          p { "Steps:" }
+          ol {
+            @for step in &provenance.steps {
+              li { code { (step) } }
+            }
+          }
+        }
+        p {
+          "Seeds: "
+          @for (index, (usage, seed)) in provenance.seeds.iter().enumerate() {
+            @if index > 0 { ", " }
+            (usage) " " code { (seed) }
+          }
+        }
+      }
+      script type="application/json" id="provenance" { (PreEscaped(json)) }
+    }
+  }))
+}
@@ -0,0 +1,218 @@
+use std::time::SystemTime;
+
+use polars::export::chrono::{DateTime, Utc};
+use polars::prelude::*;
+use serde_json::{json, Value};
+
+use crate::dataframe_cache::FileFingerprint;
+use crate::dataset_registry::DatasetEntry;
+use crate::display_options::DisplayOptions;
+
+/// Version of the crate that renders the notebooks.
+pub const CRATE_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Represents a dataset as a notebook loaded it.
+#[derive(Clone, Debug)]
+pub struct DatasetProvenance {
+  /// Name of the dataset in the registry.
+  pub name: String,
+  /// Absolute path of the file.
+  pub path: String,
+  /// Format of the file, e.g. `"CSV"`.
+  pub format: String,
+  /// Hash of the content of the file, see `[crate::dataframe_cache::content_hash]`.
+  pub content_hash: u64,
+  /// Number of rows at load.
+  pub rows: usize,
+  /// Number of columns at load.
+  pub columns: usize,
+}
+
+impl DatasetProvenance {
+  /// Records a loaded dataset.
+  ///
+  /// # Arguments
+  ///
+  /// * `dataset`: Registered dataset.
+  /// * `fingerprint`: Fingerprint of the file the dataframe was loaded from.
+  /// * `df`: Dataframe as loaded, before any preparation.
+  pub fn new(
+    dataset: &DatasetEntry,
+    fingerprint: &FileFingerprint,
+    df: &DataFrame,
+  ) -> Self {
+    Self {
+      name: dataset.name.clone(),
+      path: dataset.path.clone(),
+      format: dataset.format.to_string(),
+      content_hash: fingerprint.hash,
+      rows: df.height(),
+      columns: df.width(),
+    }
+  }
+
+  /// Gets the content hash as 16 hexadecimal digits.
+  pub fn content_hash_hex(&self) -> String {
+    format!("{:016x}", self.content_hash)
+  }
+}
+
+/// Represents the preparation steps and the seeds recorded while a notebook computes its
+/// results, as the functions are called with their options, see `[Provenance::record]`.
+#[derive(Clone, Debug, Default)]
+pub struct ProvenanceRecord {
+  /// Preparation steps, in order.
+  pub steps: Vec<String>,
+  /// Seeds used, by usage.
+  pub seeds: Vec<(String, u64)>,
+}
+
+impl ProvenanceRecord {
+  /// Creates an empty record.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Records preparation steps, in order, e.g. the ones of
+  /// `[crate::pumpkins::PreparedPumpkins::steps]`.
+  pub fn steps<S: AsRef<str>>(
+    &mut self,
+    steps: &[S],
+  ) {
+    self.steps.extend(steps.iter().map(|step| step.as_ref().to_string()));
+  }
+
+  /// Records a preparation step.
+  pub fn step(
+    &mut self,
+    step: &str,
+  ) {
+    self.steps.push(step.to_string());
+  }
+
+  /// Records the seed of a random choice, as passed to the function that uses it.
+  ///
+  /// # Arguments
+  ///
+  /// * `usage`: What the seed is used for, e.g. `"SmartCore split"`.
+  /// * `seed`: Value of the seed.
+  pub fn seed(
+    &mut self,
+    usage: &str,
+    seed: u64,
+  ) {
+    self.seeds.push((usage.to_string(), seed));
+  }
+}
+
+/// Represents where the numbers of a rendered notebook come from: the datasets, the steps that
+/// prepared them, the seeds of the random choices, the version of the crate and the time of the
+/// rendering.
+#[derive(Clone, Debug)]
+pub struct Provenance {
+  /// Datasets loaded by the notebook.
+  pub datasets: Vec<DatasetProvenance>,
+  /// Preparation steps, in order, e.g. `"filter_bushels"`.
+  pub steps: Vec<String>,
+  /// Seeds used by the notebook, by usage.
+  pub seeds: Vec<(String, u64)>,
+  /// Version of the crate.
+  pub crate_version: String,
+  /// Time of the rendering.
+  pub rendered_at: SystemTime,
+}
+
+impl Provenance {
+  /// Creates the provenance of a notebook rendered now, with the seed of its shuffled samples,
+  /// see `[DisplayOptions::current]`.
+  pub fn new() -> Self {
+    Self {
+      datasets: Vec::new(),
+      steps: Vec::new(),
+      seeds: vec![(
+        "Shuffled samples".to_string(),
+        DisplayOptions::current().sample_seed,
+      )],
+      crate_version: CRATE_VERSION.to_string(),
+      rendered_at: SystemTime::now(),
+    }
+  }
+
+  /// Adds a loaded dataset.
+  pub fn dataset(
+    mut self,
+    dataset: DatasetProvenance,
+  ) -> Self {
+    self.datasets.push(dataset);
+    self
+  }
+
+  /// Adds preparation steps, in order.
+  pub fn steps<S: AsRef<str>>(
+    mut self,
+    steps: &[S],
+  ) -> Self {
+    self.steps.extend(steps.iter().map(|step| step.as_ref().to_string()));
+    self
+  }
+
+  /// Adds the steps and the seeds recorded while the notebook computed its results.
+  pub fn record(
+    mut self,
+    record: &ProvenanceRecord,
+  ) -> Self {
+    self.steps.extend(record.steps.iter().cloned());
+    self.seeds.extend(record.seeds.iter().cloned());
+    self
+  }
+
+  /// Adds the seed of a random choice.
+  ///
+  /// # Arguments
+  ///
+  /// * `usage`: What the seed is used for, e.g. `"SmartCore split"`.
+  /// * `seed`: Value of the seed.
+  pub fn seed(
+    mut self,
+    usage: &str,
+    seed: u64,
+  ) -> Self {
+    self.seeds.push((usage.to_string(), seed));
+    self
+  }
+
+  /// Gets the time of the rendering in RFC 3339 format, in UTC.
+  pub fn rendered_at_rfc3339(&self) -> String {
+    DateTime::<Utc>::from(self.rendered_at)
+      .format("%Y-%m-%dT%H:%M:%SZ")
+      .to_string()
+  }
+
+  /// Gets the provenance as JSON, to embed it in the notebook.
+  pub fn to_json(&self) -> Value {
+    json!({
+      "datasets": self.datasets.iter().map(|dataset| json!({
+        "name": dataset.name,
+        "path": dataset.path,
+        "format": dataset.format,
+        "content_hash": dataset.content_hash_hex(),
+        "content_hash_algorithm": "FNV-1a 64",
+        "rows": dataset.rows,
+        "columns": dataset.columns,
+      })).collect::<Vec<Value>>(),
+      "steps": self.steps,
+      "seeds": self.seeds.iter().map(|(usage, seed)| json!({
+        "usage": usage,
+        "seed": seed,
+      })).collect::<Vec<Value>>(),
+      "crate_version": self.crate_version,
+      "rendered_at": self.rendered_at_rfc3339(),
+    })
+  }
+}
+
+impl Default for Provenance {
+  fn default() -> Self {
+    Self::new()
+  }
+}
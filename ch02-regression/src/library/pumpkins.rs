@@ -0,0 +1,753 @@
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::csv_schema::{ColumnSchema, CsvSchema, ValidatedCsv};
+use crate::dataset_loader::{load_dataset_as, DatasetFormat};
+use crate::date_features::{day_of_year, month};
+use crate::imputation::{fit_transform_all, ImputationStrategy, ImputeValue, Imputer};
+use crate::outliers::{apply_outlier_rules, OutlierMethod, OutlierRule};
+
+/// Path of the US pumpkins dataset, relative to the crate root.
+pub const PUMPKINS_CSV_PATH: &str = "data/US-pumpkins.csv";
+
+/// Name of the US pumpkins dataset in the `[crate::dataset_registry::DatasetRegistry]`.
+pub const PUMPKINS_DATASET: &str = "us-pumpkins";
+
+/// Gets the declared schema of the US pumpkins dataset. The header ends with two unnamed
+/// columns: the first is always empty and the second holds the market tone, e.g. "STEADY.".
+pub fn pumpkins_schema() -> CsvSchema {
+  let text = |name: &str| ColumnSchema::new(name, DataType::Utf8);
+  let price = |name: &str| ColumnSchema::new(name, DataType::Float64).range(0.0, 1000.0);
+
+  CsvSchema::new(vec![
+    text("City Name").required().allowed_values(&[
+      "ATLANTA",
+      "BALTIMORE",
+      "BOSTON",
+      "CHICAGO",
+      "COLUMBIA",
+      "DALLAS",
+      "DETROIT",
+      "LOS ANGELES",
+      "MIAMI",
+      "NEW YORK",
+      "PHILADELPHIA",
+      "SAN FRANCISCO",
+      "ST. LOUIS",
+    ]),
+    text("Type").allowed_values(&["Organic"]),
+    text("Package").required(),
+    text("Variety"),
+    text("Sub Variety"),
+    text("Grade"),
+    text("Date").required().date_format("%m/%d/%y"),
+    price("Low Price").required(),
+    price("High Price").required(),
+    price("Mostly Low"),
+    price("Mostly High"),
+    text("Origin"),
+    text("Origin District"),
+    text("Item Size")
+      .allowed_values(&["sml", "med", "med-lge", "lge", "xlge", "jbo", "exjbo"]),
+    text("Color").allowed_values(&["ORANGE", "STRIPED", "WHITE"]),
+    text("Environment"),
+    text("Unit of Sale").allowed_values(&["EACH", "PER BIN", "PER LB", "SHELLACKED"]),
+    text("Quality"),
+    text("Condition"),
+    text("Appearance"),
+    text("Storage"),
+    text("Crop"),
+    text("Repack").allowed_values(&["E", "N"]),
+    text("Trans Mode"),
+    text("Unnamed: 24"),
+    text("Unnamed: 25"),
+  ])
+}
+
+/// Loads the US pumpkins dataset from a CSV file with its declared schema, and reports the
+/// values that break it.
+///
+/// # Arguments
+///
+/// * `path`: Path of the CSV file, usually `[PUMPKINS_CSV_PATH]`.
+pub fn validate_pumpkins(path: &str) -> GenericResult<ValidatedCsv> {
+  pumpkins_schema().load(path)
+}
+
+/// Loads the US pumpkins dataset. A CSV file is loaded with the declared schema, so values that
+/// break it are null (`[validate_pumpkins]` reports them); Parquet, Arrow IPC and JSON files
+/// carry their own types and are conformed to the declared columns.
+///
+/// # Arguments
+///
+/// * `path`: Path of the file, usually `[PUMPKINS_CSV_PATH]`.
+pub fn load_pumpkins(path: &str) -> GenericResult<DataFrame> {
+  match DatasetFormat::detect(path)? {
+    DatasetFormat::Csv => Ok(validate_pumpkins(path)?.df),
+    format => pumpkins_schema().conform(&load_dataset_as(path, format)?),
+  }
+}
+
+/// Gets the options that parse the `Date` column, e.g. "9/24/16".
+pub fn date_options() -> StrptimeOptions {
+  StrptimeOptions {
+    format: Some("%m/%d/%y".to_string()),
+    strict: true,
+    exact: true,
+    cache: true,
+    use_earliest: Some(true),
+  }
+}
+
+/// Keeps only the pumpkins sold by the bushel (bushel cartons, baskets and crates).
+pub fn filter_bushels(pumpkins: LazyFrame) -> LazyFrame {
+  pumpkins.filter(col("Package").str().contains(lit("bushel"), true))
+}
+
+/// Converts the `Date` column from text into a proper date.
+pub fn parse_dates(pumpkins: LazyFrame) -> LazyFrame {
+  pumpkins.with_column(col("Date").str().strptime(DataType::Date, date_options()))
+}
+
+/// Adds the `Price` column: the average of the low and the high price.
+pub fn average_price(pumpkins: LazyFrame) -> LazyFrame {
+  pumpkins.with_column(((col("Low Price") + col("High Price")) / lit(2.0)).alias("Price"))
+}
+
+/// Adds the `Month` column (1.0 to 12.0) from the parsed `Date`.
+pub fn extract_month(pumpkins: LazyFrame) -> LazyFrame {
+  pumpkins.with_column(month(col("Date")).alias("Month"))
+}
+
+/// Adds the `DayOfYear` column from the parsed `Date`: the number of days since January 1st,
+/// so January 1st is day 0.0.
+pub fn extract_day_of_year(pumpkins: LazyFrame) -> LazyFrame {
+  pumpkins.with_column(day_of_year(col("Date")).alias("DayOfYear"))
+}
+
+/// Volume of a US (Winchester) bushel in cubic inches, as defined by NIST Handbook 44,
+/// Appendix C.
+pub const CUBIC_INCHES_PER_BUSHEL: f64 = 2150.42;
+
+/// Represents the conversion factors from every unit of sale into bushels.
+///
+/// Only the bushel volume is a standard: the defaults of the other factors are assumptions of
+/// this crate, not figures of the USDA reports the dataset comes from, so set them when better
+/// figures are known. The preparation keeps only the packages sold by the bushel before
+/// normalizing the price (see `[prepare]`), so these factors only change the prices of
+/// `[normalize_bushel_price]` when it is called on the other packages.
+#[derive(Clone, Debug)]
+pub struct PackageConversions {
+  /// Weight of a bushel of pumpkins in pounds, to convert "35 lb cartons" and the like.
+  /// Assumed 40 lb by default.
+  pub pounds_per_bushel: f64,
+  /// Base area of a bin in square inches; bins are converted by volume. Assumed 48 x 40 inches
+  /// by default: the footprint of the GMA pallet (ISO 6780, 1219 x 1016 mm).
+  pub bin_base_square_inches: f64,
+  /// Height in inches of the bins sold without a size ("bins"). Assumed 36 inches by default,
+  /// the tallest size of the dataset.
+  pub default_bin_height_inches: f64,
+  /// Weight of a single pumpkin in pounds, to convert the ones sold "each". Assumed 10 lb by
+  /// default.
+  pub pounds_per_each: f64,
+}
+
+impl PackageConversions {
+  /// Creates a new instance of `[PackageConversions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these package conversions.
+  pub fn builder() -> PackageConversionsBuilder {
+    PackageConversionsBuilder::default()
+  }
+}
+
+impl Default for PackageConversions {
+  fn default() -> Self {
+    PackageConversionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[PackageConversions]`.
+pub struct PackageConversionsBuilder {
+  /// Weight of a bushel of pumpkins in pounds.
+  pub pounds_per_bushel: f64,
+  /// Base area of a bin in square inches.
+  pub bin_base_square_inches: f64,
+  /// Height in inches of the bins sold without a size.
+  pub default_bin_height_inches: f64,
+  /// Weight of a single pumpkin in pounds.
+  pub pounds_per_each: f64,
+}
+
+impl PackageConversionsBuilder {
+  /// Creates a new instance of `[PackageConversionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the weight of a bushel of pumpkins in pounds.
+  pub fn pounds_per_bushel(
+    mut self,
+    pounds_per_bushel: f64,
+  ) -> Self {
+    self.pounds_per_bushel = pounds_per_bushel;
+    self
+  }
+
+  /// Sets the base area of a bin in square inches.
+  pub fn bin_base_square_inches(
+    mut self,
+    bin_base_square_inches: f64,
+  ) -> Self {
+    self.bin_base_square_inches = bin_base_square_inches;
+    self
+  }
+
+  /// Sets the height in inches of the bins sold without a size.
+  pub fn default_bin_height_inches(
+    mut self,
+    default_bin_height_inches: f64,
+  ) -> Self {
+    self.default_bin_height_inches = default_bin_height_inches;
+    self
+  }
+
+  /// Sets the weight of a single pumpkin in pounds.
+  pub fn pounds_per_each(
+    mut self,
+    pounds_per_each: f64,
+  ) -> Self {
+    self.pounds_per_each = pounds_per_each;
+    self
+  }
+
+  /// Builds the instance of `[PackageConversions]`.
+  pub fn build(self) -> PackageConversions {
+    PackageConversions {
+      pounds_per_bushel: self.pounds_per_bushel,
+      bin_base_square_inches: self.bin_base_square_inches,
+      default_bin_height_inches: self.default_bin_height_inches,
+      pounds_per_each: self.pounds_per_each,
+    }
+  }
+}
+
+impl Default for PackageConversionsBuilder {
+  fn default() -> Self {
+    Self {
+      pounds_per_bushel: 40.0,
+      // Standard 48 x 40 inch pallet bin
+      bin_base_square_inches: 48.0 * 40.0,
+      default_bin_height_inches: 36.0,
+      pounds_per_each: 10.0,
+    }
+  }
+}
+
+/// Represents the unit of sale of a `Package` value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PackageUnit {
+  /// A container holding a number of bushels, e.g. "1 1/9 bushel cartons" or "bushel baskets".
+  Bushels(f64),
+  /// A bin of the given height in inches, e.g. "24 inch bins", or of the default height.
+  Bin(Option<f64>),
+  /// A container holding a weight in pounds, e.g. "50 lb sacks".
+  Pounds(f64),
+  /// A single pumpkin ("each").
+  Each,
+}
+
+impl PackageUnit {
+  /// Parses a `Package` value, or returns `None` when it is not understood.
+  pub fn parse(package: &str) -> Option<Self> {
+    let package = package.trim().to_lowercase();
+    let tokens: Vec<&str> = package.split_whitespace().collect();
+
+    if tokens == ["each"] {
+      return Some(Self::Each);
+    }
+
+    let (container, quantity) = tokens.split_last()?;
+    let container = container.strip_suffix('s').unwrap_or(container);
+    if !["carton", "crate", "basket", "sack", "bin"].contains(&container) {
+      return None;
+    }
+
+    match quantity {
+      [] if container == "bin" => Some(Self::Bin(None)),
+      [height, "inch"] if container == "bin" => {
+        Some(Self::Bin(Some(height.parse().ok()?)))
+      }
+      [weight, "lb" | "lbs"] => Some(Self::Pounds(weight.parse().ok()?)),
+      [amount @ .., "bushel" | "bushels"] => {
+        Some(Self::Bushels(parse_mixed_number(amount)?))
+      }
+      _ => None,
+    }
+  }
+
+  /// Calculates how many bushels the unit holds.
+  pub fn bushels(
+    &self,
+    conversions: &PackageConversions,
+  ) -> f64 {
+    match self {
+      Self::Bushels(bushels) => *bushels,
+      Self::Bin(height) => {
+        height.unwrap_or(conversions.default_bin_height_inches)
+          * conversions.bin_base_square_inches
+          / CUBIC_INCHES_PER_BUSHEL
+      }
+      Self::Pounds(pounds) => pounds / conversions.pounds_per_bushel,
+      Self::Each => conversions.pounds_per_each / conversions.pounds_per_bushel,
+    }
+  }
+
+  /// Gets the display name of the unit, e.g. "bushels" or "pounds".
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::Bushels(_) => "bushels",
+      Self::Bin(_) => "bin",
+      Self::Pounds(_) => "pounds",
+      Self::Each => "each",
+    }
+  }
+}
+
+/// Parses an amount such as "1", "1/2" or "1 1/9". An empty amount is 1.
+fn parse_mixed_number(tokens: &[&str]) -> Option<f64> {
+  if tokens.is_empty() {
+    return Some(1.0);
+  }
+
+  tokens.iter().try_fold(0.0, |total, token| {
+    let value = match token.split_once('/') {
+      Some((numerator, denominator)) => {
+        numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?
+      }
+      None => token.parse::<f64>().ok()?,
+    };
+    Some(total + value)
+  })
+}
+
+/// Describes how every distinct `Package` of a dataframe converts into bushels.
+///
+/// # Returns
+///
+/// A dataframe with the columns Package, Rows, Unit and Bushels, sorted by the number of rows.
+/// Packages that cannot be parsed have a null Unit and Bushels.
+pub fn package_conversion_table(
+  pumpkins: &DataFrame,
+  conversions: &PackageConversions,
+) -> GenericResult<DataFrame> {
+  let counts = pumpkins
+    .clone()
+    .lazy()
+    .groupby([col("Package")])
+    .agg([count().alias("Rows")])
+    .sort(
+      "Rows",
+      SortOptions {
+        descending: true,
+        nulls_last: true,
+        maintain_order: true,
+        multithreaded: true,
+      },
+    )
+    .collect()?;
+
+  let units: Vec<Option<PackageUnit>> = counts
+    .column("Package")?
+    .utf8()?
+    .into_iter()
+    .map(|package| package.and_then(PackageUnit::parse))
+    .collect();
+
+  let mut table = counts;
+  table.with_column(Series::new(
+    "Unit",
+    units
+      .iter()
+      .map(|unit| unit.map(|unit| unit.name()))
+      .collect::<Vec<Option<&str>>>(),
+  ))?;
+  table.with_column(Series::new(
+    "Bushels",
+    units
+      .iter()
+      .map(|unit| unit.map(|unit| unit.bushels(conversions)))
+      .collect::<Vec<Option<f64>>>(),
+  ))?;
+
+  Ok(table)
+}
+
+/// Gets the distinct `Package` values that cannot be converted into bushels.
+pub fn unparsed_packages(pumpkins: &DataFrame) -> GenericResult<Vec<String>> {
+  let mut packages: Vec<String> = pumpkins
+    .column("Package")?
+    .utf8()?
+    .into_iter()
+    .map(|package| package.unwrap_or_default())
+    .filter(|package| PackageUnit::parse(package).is_none())
+    .map(|package| package.to_string())
+    .collect();
+  packages.sort_unstable();
+  packages.dedup();
+  Ok(packages)
+}
+
+/// Converts the `Price` of every row into a price per bushel with the given conversions. Rows
+/// with a package that cannot be parsed get a null price instead of keeping their price; see
+/// `[unparsed_packages]` to report them.
+pub fn normalize_bushel_price(
+  pumpkins: LazyFrame,
+  conversions: &PackageConversions,
+) -> LazyFrame {
+  let conversions = conversions.clone();
+  let bushels = col("Package").map(
+    move |packages| {
+      let bushels: Float64Chunked = packages
+        .utf8()?
+        .into_iter()
+        .map(|package| {
+          package
+            .and_then(PackageUnit::parse)
+            .map(|unit| unit.bushels(&conversions))
+        })
+        .collect();
+      Ok(Some(bushels.into_series()))
+    },
+    GetOutput::from_type(DataType::Float64),
+  );
+
+  pumpkins.with_column((col("Price") / bushels).alias("Price"))
+}
+
+/// Gets the imputers of the nulls of the US pumpkins, one per column, fitted on every row
+/// with a proper date: KNN on the prices for "Mostly Low", the median for "Mostly High", the
+/// mode for "Item Size" and "Origin", "UNKNOWN" for "Color", and the last "Variety" of the
+/// same city. They are not part of the default `[PreparationOptions]`, which keep every null:
+/// they are fitted on every row, so a lesson that splits the rows into train and test should
+/// fit its own imputers on the training rows only.
+pub fn pumpkins_imputers() -> Vec<Imputer> {
+  vec![
+    Imputer::new(
+      "Mostly Low",
+      ImputationStrategy::Knn {
+        k: 5,
+        features: vec!["Low Price".to_string(), "High Price".to_string()],
+      },
+    )
+    .add_indicator(true),
+    Imputer::new("Mostly High", ImputationStrategy::Median),
+    Imputer::new("Item Size", ImputationStrategy::Mode).add_indicator(true),
+    Imputer::new(
+      "Color",
+      ImputationStrategy::Constant(ImputeValue::Text("UNKNOWN".to_string())),
+    ),
+    Imputer::new(
+      "Variety",
+      ImputationStrategy::ForwardFill {
+        group_by: vec!["City Name".to_string()],
+        order_by: "Date".to_string(),
+      },
+    ),
+    Imputer::new("Origin", ImputationStrategy::Mode),
+  ]
+}
+
+/// Gets the rules that drop the outliers of the average `Price` of the US pumpkins, in order:
+/// Tukey fences over every row, then the modified z-score and the z-score per `Package`. Bins
+/// are priced per bin and cartons per carton, so the fences over every package are too wide to
+/// catch much: the fences per package catch the rest. They are not part of the default
+/// `[PreparationOptions]`, which keep every row: a lesson opts in to drop them.
+pub fn pumpkins_outlier_rules() -> Vec<OutlierRule> {
+  vec![
+    OutlierRule::new("Price", OutlierMethod::Iqr { factor: 1.5 }),
+    OutlierRule::new("Price", OutlierMethod::ModifiedZScore { threshold: 3.5 })
+      .group_by(&["Package"]),
+    OutlierRule::new("Price", OutlierMethod::ZScore { threshold: 3.0 }).group_by(&["Package"]),
+  ]
+}
+
+/// Represents the options of the preparation of the US pumpkins, see `[prepare]`.
+#[derive(Clone, Debug)]
+pub struct PreparationOptions {
+  /// Imputers of the nulls, fitted and applied in order after the dates are parsed. None by
+  /// default, so every null is kept.
+  pub imputers: Vec<Imputer>,
+  /// Rules of the outliers of the average price, applied in order before the bushels are kept.
+  /// None by default, so every row is kept.
+  pub outlier_rules: Vec<OutlierRule>,
+  /// Conversions of the packages into bushels, see `[normalize_bushel_price]`.
+  pub conversions: PackageConversions,
+}
+
+impl PreparationOptions {
+  /// Creates a new instance of `[PreparationOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these preparation options.
+  pub fn builder() -> PreparationOptionsBuilder {
+    PreparationOptionsBuilder::default()
+  }
+}
+
+impl Default for PreparationOptions {
+  fn default() -> Self {
+    PreparationOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[PreparationOptions]`.
+#[derive(Default)]
+pub struct PreparationOptionsBuilder {
+  /// Imputers of the nulls.
+  pub imputers: Vec<Imputer>,
+  /// Rules of the outliers of the average price.
+  pub outlier_rules: Vec<OutlierRule>,
+  /// Conversions of the packages into bushels.
+  pub conversions: PackageConversions,
+}
+
+impl PreparationOptionsBuilder {
+  /// Creates a new instance of `[PreparationOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the imputers of the nulls, e.g. none to keep every null.
+  pub fn imputers(
+    mut self,
+    imputers: Vec<Imputer>,
+  ) -> Self {
+    self.imputers = imputers;
+    self
+  }
+
+  /// Sets the rules of the outliers of the average price, e.g. none to keep every row.
+  pub fn outlier_rules(
+    mut self,
+    outlier_rules: Vec<OutlierRule>,
+  ) -> Self {
+    self.outlier_rules = outlier_rules;
+    self
+  }
+
+  /// Sets the conversions of the packages into bushels.
+  pub fn conversions(
+    mut self,
+    conversions: PackageConversions,
+  ) -> Self {
+    self.conversions = conversions;
+    self
+  }
+
+  /// Builds the instance of `[PreparationOptions]`.
+  pub fn build(self) -> PreparationOptions {
+    PreparationOptions {
+      imputers: self.imputers,
+      outlier_rules: self.outlier_rules,
+      conversions: self.conversions,
+    }
+  }
+}
+
+/// Represents the dataframe after one step of `[prepare]`.
+#[derive(Clone)]
+pub struct PreparationStage {
+  /// Name of the step function, e.g. `"filter_bushels"`.
+  pub step: &'static str,
+  /// Options of the step, empty when it has none.
+  pub options: String,
+  /// Dataframe after the step.
+  pub df: DataFrame,
+}
+
+impl PreparationStage {
+  /// Gets the step with its options, e.g. `"normalize_bushel_price(...)"`.
+  pub fn description(&self) -> String {
+    if self.options.is_empty() {
+      self.step.to_string()
+    } else {
+      format!("{}({})", self.step, self.options)
+    }
+  }
+}
+
+/// Represents the US pumpkins prepared by `[prepare]`, with the dataframe after every step.
+#[derive(Clone)]
+pub struct PreparedPumpkins {
+  /// Stages of the preparation, in the order of the steps.
+  pub stages: Vec<PreparationStage>,
+  /// Imputers fitted by the preparation, e.g. to fill the nulls of new data the same way;
+  /// empty without imputers.
+  pub imputers: Vec<Imputer>,
+  /// Report of the outlier rules, see `[apply_outlier_rules]`; empty without outlier rules.
+  pub outlier_report: DataFrame,
+}
+
+impl PreparedPumpkins {
+  /// Gets the prepared pumpkins: the dataframe after the last step.
+  pub fn df(&self) -> &DataFrame {
+    &self
+      .stages
+      .last()
+      .expect("the preparation has at least one step")
+      .df
+  }
+
+  /// Gets the dataframe after a step, e.g. `"filter_bushels"`.
+  pub fn stage(
+    &self,
+    step: &str,
+  ) -> GenericResult<&DataFrame> {
+    self
+      .stages
+      .iter()
+      .find(|stage| stage.step == step)
+      .map(|stage| &stage.df)
+      .ok_or_else(|| GenericError::from(format!("The preparation has no step \"{step}\"")).into())
+  }
+
+  /// Gets the steps with their options, in order.
+  pub fn steps(&self) -> Vec<String> {
+    self.stages.iter().map(PreparationStage::description).collect()
+  }
+
+  /// Records the dataframe after a step.
+  fn record(
+    &mut self,
+    step: &'static str,
+    options: String,
+    df: &DataFrame,
+  ) {
+    self.stages.push(PreparationStage {
+      step,
+      options,
+      df: df.clone(),
+    });
+  }
+}
+
+/// Applies every preparation step to the loaded pumpkins, in order: parse the dates, fill the
+/// nulls with the imputers, average the prices, extract the month and the day of year, drop
+/// the outliers of the price, keep the bushels, and normalize the price per bushel. The
+/// pipeline stays bushel-only, like the lessons: only the bushel fractions of the packages
+/// reach the prepared prices, and the bin, weight and "each" conversions do not. Every
+/// lesson starts from it, so they all work on the same data. The nulls are only filled, and
+/// the outliers only dropped, when the options have imputers and outlier rules: otherwise
+/// those steps are not recorded.
+///
+/// # Arguments
+///
+/// * `pumpkins`: US pumpkins as loaded, e.g. with `[load_pumpkins]`.
+/// * `options`: Options of the steps.
+pub fn prepare(
+  pumpkins: &DataFrame,
+  options: &PreparationOptions,
+) -> GenericResult<PreparedPumpkins> {
+  let mut prepared = PreparedPumpkins {
+    stages: Vec::new(),
+    imputers: options.imputers.clone(),
+    outlier_report: DataFrame::default(),
+  };
+
+  let pumpkins = parse_dates(pumpkins.clone().lazy()).collect()?;
+  prepared.record("parse_dates", String::new(), &pumpkins);
+  let pumpkins = if options.imputers.is_empty() {
+    pumpkins
+  } else {
+    let pumpkins = fit_transform_all(&mut prepared.imputers, &pumpkins)?;
+    let imputers: Vec<String> = prepared.imputers.iter().map(Imputer::description).collect();
+    prepared.record("impute", imputers.join("; "), &pumpkins);
+    pumpkins
+  };
+  let pumpkins = average_price(pumpkins.lazy()).collect()?;
+  prepared.record("average_price", String::new(), &pumpkins);
+  let pumpkins = extract_month(pumpkins.lazy()).collect()?;
+  prepared.record("extract_month", String::new(), &pumpkins);
+  let pumpkins = extract_day_of_year(pumpkins.lazy()).collect()?;
+  prepared.record("extract_day_of_year", String::new(), &pumpkins);
+  let pumpkins = if options.outlier_rules.is_empty() {
+    pumpkins
+  } else {
+    let (pumpkins, outlier_report) = apply_outlier_rules(&pumpkins, &options.outlier_rules)?;
+    let rules: Vec<String> = options.outlier_rules.iter().map(OutlierRule::description).collect();
+    prepared.record("apply_outlier_rules", rules.join("; "), &pumpkins);
+    prepared.outlier_report = outlier_report;
+    pumpkins
+  };
+  let pumpkins = filter_bushels(pumpkins.lazy()).collect()?;
+  prepared.record("filter_bushels", String::new(), &pumpkins);
+  let pumpkins = normalize_bushel_price(pumpkins.lazy(), &options.conversions).collect()?;
+  prepared.record(
+    "normalize_bushel_price",
+    format!("{:?}", options.conversions),
+    &pumpkins,
+  );
+
+  Ok(prepared)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// Every distinct `Package` of the US pumpkins, with its unit and its bushels with the
+  /// default conversions.
+  const DATASET_PACKAGES: [(&str, PackageUnit, f64); 15] = [
+    ("1 1/9 bushel cartons", PackageUnit::Bushels(10.0 / 9.0), 10.0 / 9.0),
+    ("1 1/9 bushel crates", PackageUnit::Bushels(10.0 / 9.0), 10.0 / 9.0),
+    ("1/2 bushel cartons", PackageUnit::Bushels(0.5), 0.5),
+    ("bushel baskets", PackageUnit::Bushels(1.0), 1.0),
+    ("bushel cartons", PackageUnit::Bushels(1.0), 1.0),
+    ("20 lb cartons", PackageUnit::Pounds(20.0), 0.5),
+    ("22 lb cartons", PackageUnit::Pounds(22.0), 0.55),
+    ("35 lb cartons", PackageUnit::Pounds(35.0), 0.875),
+    ("40 lb cartons", PackageUnit::Pounds(40.0), 1.0),
+    ("50 lb cartons", PackageUnit::Pounds(50.0), 1.25),
+    ("50 lb sacks", PackageUnit::Pounds(50.0), 1.25),
+    ("24 inch bins", PackageUnit::Bin(Some(24.0)), 21.428372),
+    ("36 inch bins", PackageUnit::Bin(Some(36.0)), 32.142558),
+    ("bins", PackageUnit::Bin(None), 32.142558),
+    ("each", PackageUnit::Each, 0.25),
+  ];
+
+  #[test]
+  fn every_dataset_package_converts_into_its_bushels() {
+    let conversions = PackageConversions::default();
+
+    for (package, unit, bushels) in DATASET_PACKAGES {
+      let parsed = PackageUnit::parse(package);
+      assert_eq!(parsed, Some(unit), "unit of \"{package}\"");
+      let parsed_bushels = unit.bushels(&conversions);
+      assert!(
+        (parsed_bushels - bushels).abs() < 1e-6,
+        "\"{package}\" holds {parsed_bushels} bushels, expected {bushels}"
+      );
+    }
+  }
+
+  #[test]
+  fn unknown_packages_are_not_parsed() {
+    for package in ["", "bushel", "1 1/0x bushel cartons", "20 kg cartons", "large bags"] {
+      assert_eq!(PackageUnit::parse(package), None, "\"{package}\"");
+    }
+  }
+
+  #[test]
+  fn mixed_numbers_are_parsed() {
+    assert_eq!(parse_mixed_number(&[]), Some(1.0));
+    assert_eq!(parse_mixed_number(&["1/2"]), Some(0.5));
+    assert_eq!(parse_mixed_number(&["2", "1/4"]), Some(2.25));
+    assert_eq!(parse_mixed_number(&["x"]), None);
+  }
+}
@@ -7,6 +7,8 @@ use ndarray::Array;
 use ndarray::Array2;
 use ndarray::Ix2;
 
+use crate::application_error::GenericResult;
+
 /// Represents a model for a regression.
 pub struct RegressionModel {
   /// Matrix of explanatory (input) variables.
@@ -28,6 +30,9 @@ pub struct RegressionModel {
   pub split_ratio: f32,
 
   pub δ2: f64,
+
+  /// Ridge (L2) regularization strength λ. The intercept is not penalized.
+  pub λ: f64,
 }
 
 impl RegressionModel {
@@ -57,35 +62,59 @@ impl RegressionModel {
       y_test: Array2::<f64>::zeros((n_rows, 1)),
       split_ratio,
       δ2: 0.0_f64,
+      λ: 0.0_f64,
     }
   }
 
+  /// Sets the ridge (L2) regularization strength λ used by `solve`.
+  /// A value of 0.0 gives ordinary least squares.
+  pub fn ridge(
+    mut self,
+    λ: f64,
+  ) -> Self {
+    self.λ = λ;
+    self
+  }
+
   /// Solves the linear model equation Y = Xβ + ε.
   /// It calculates β: the coeficients or parameters.
   pub fn solve(
     &mut self,
     degree: i32,
   ) {
+    self.try_solve(degree).expect("QRError");
+  }
+
+  /// Solves the linear model equation Y = Xβ + ε, returning an error instead of panicking
+  /// when XᵀX cannot be inverted (e.g. too few distinct rows for the polynomial degree).
+  ///
+  /// # Arguments
+  ///
+  /// * `degree`: Degree of the polynomial vector of explanatory `x` (input) variables.
+  pub fn try_solve(
+    &mut self,
+    degree: i32,
+  ) -> GenericResult<()> {
     (self.x_train, self.x_test) =
       Self::split_polyfit_data(&self.x, degree, self.split_ratio);
 
     (self.y_train, self.y_test) = Self::split_data(&self.y, self.split_ratio);
 
-    self.β = self
-      .x_train
-      .clone()
-      .reversed_axes()
-      .dot(&self.x_train)
-      .qr_into()
-      .expect("QRError")
-      .inverse()
-      .expect("inverse error")
+    // Ridge penalty λI on every parameter except the intercept β0
+    let mut penalty = Array2::<f64>::eye(self.x_train.ncols()) * self.λ;
+    penalty[(0, 0)] = 0.0;
+
+    self.β = (self.x_train.clone().reversed_axes().dot(&self.x_train) + penalty)
+      .qr_into()?
+      .inverse()?
       .dot(&self.x_train.clone().reversed_axes())
       .dot(&self.y_train.clone());
     self.δ2 = (&self.y_train - &self.x_train.dot(&self.β))
       .norm_max()
       .powi(2)
       / (self.x_train.nrows() as f64);
+
+    Ok(())
   }
 
   /// Gets the vector of residuals: e = y - Xβ.
@@ -94,7 +123,7 @@ impl RegressionModel {
   ///
   /// * `x`: Matrix of explanatory (input) variables.
   /// * `y`: Real, measured or observed response (output) variables. On the other hand, Xβ is
-  /// the estimated or predicted `y` values by the regression.
+  ///   the estimated or predicted `y` values by the regression.
   pub fn e(
     &self,
     x: &Array<f64, Ix2>,
@@ -194,18 +223,30 @@ impl RegressionModel {
     (train_data, test_data)
   }
 
+  /// Builds the polynomial design matrix X = [1, x₁, x₁², …, x₁ᵈ, x₂, …, x₂ᵈ, …] from the
+  /// columns of the given data. With a single column this is the usual [1, x, …, xᵈ].
+  ///
+  /// # Arguments
+  ///
+  /// * `source_data`: Matrix of explanatory (input) variables, one variable per column.
+  /// * `degree`: Degree of the polynomial expansion of each column.
   pub fn polyfit_data(
     source_data: &Array<f64, Ix2>,
     degree: i32,
   ) -> Array<f64, Ix2> {
-    // Create a matrix to hold the values of x as a polynomial
-    let mut x_model = Array2::<f64>::zeros((source_data.nrows(), (degree + 1) as usize));
-
-    // Create the columns for the powers of x
-    for column_index in 0..=degree {
-      ndarray::Zip::from(&mut x_model.column_mut(column_index as usize))
-        .and(source_data.clone().column(0))
-        .for_each(|x, a| *x = a.powi(column_index));
+    // Create a matrix to hold the intercept and the powers of every column
+    let n_columns = 1 + source_data.ncols() * degree.max(0) as usize;
+    let mut x_model = Array2::<f64>::zeros((source_data.nrows(), n_columns));
+    x_model.column_mut(0).fill(1.0);
+
+    // Create the columns for the powers of each x
+    for (source_index, source_column) in source_data.columns().into_iter().enumerate() {
+      for power in 1..=degree {
+        let column_index = 1 + source_index * degree as usize + (power - 1) as usize;
+        ndarray::Zip::from(&mut x_model.column_mut(column_index))
+          .and(source_column)
+          .for_each(|x, a| *x = a.powi(power));
+      }
     }
 
     x_model
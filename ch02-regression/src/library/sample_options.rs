@@ -7,6 +7,8 @@ pub struct SampleOptions {
   pub sample_size: usize,
   /// Whether or not the retrieved samples are randomly selected.
   pub shuffle: bool,
+  /// Seed of the random selection, so the same samples are selected every time.
+  pub seed: u64,
   /// Display options for the elements of the sample set.
   pub display_options: DisplayOptions,
 }
@@ -25,11 +27,7 @@ impl SampleOptions {
 
 impl Default for SampleOptions {
   fn default() -> Self {
-    Self {
-      sample_size: usize::MAX,
-      shuffle: false,
-      display_options: DisplayOptions::new(),
-    }
+    SampleOptionsBuilder::default().build()
   }
 }
 
@@ -39,6 +37,8 @@ pub struct SampleOptionsBuilder {
   pub sample_size: usize,
   /// Whether or not the retrieved samples are randomly selected.
   pub shuffle: bool,
+  /// Seed of the random selection.
+  pub seed: u64,
   /// Display options for the elements of the sample set.
   pub display_options: DisplayOptions,
 }
@@ -67,11 +67,21 @@ impl SampleOptionsBuilder {
     self
   }
 
+  /// Sets the seed of the random selection, instead of the one of the `[DisplayOptions]`.
+  pub fn seed(
+    mut self,
+    seed: u64,
+  ) -> Self {
+    self.seed = seed;
+    self
+  }
+
   /// Builds the instance of `[SampleOptions]`.
   pub fn build(self) -> SampleOptions {
     SampleOptions {
       sample_size: self.sample_size,
       shuffle: self.shuffle,
+      seed: self.seed,
       display_options: self.display_options,
     }
   }
@@ -79,10 +89,12 @@ impl SampleOptionsBuilder {
 
 impl Default for SampleOptionsBuilder {
   fn default() -> Self {
+    let display_options = DisplayOptions::current();
     Self {
       sample_size: usize::MAX,
       shuffle: false,
-      display_options: DisplayOptions::default(),
+      seed: display_options.sample_seed,
+      display_options,
+    }
   }
 }
-}
\ No newline at end of file
@@ -0,0 +1,314 @@
+use std::collections::BTreeMap;
+
+use ndarray::{Array1, Array2};
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+
+/// Represents a design matrix X that the least-squares and gradient solvers can work with
+/// through matrix-vector products, without knowing how it is stored.
+pub trait DesignMatrix {
+  /// Number of rows (observations).
+  fn n_rows(&self) -> usize;
+
+  /// Number of columns (parameters).
+  fn n_cols(&self) -> usize;
+
+  /// Calculates the product Xv.
+  fn dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64>;
+
+  /// Calculates the product Xᵀv.
+  fn t_dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64>;
+
+  /// Calculates the Gram matrix XᵀX in sparse form.
+  fn gram(&self) -> CsrMatrix;
+}
+
+/// Represents a sparse matrix in compressed sparse row (CSR) format.
+///
+/// The values of row `i` are `data[indptr[i]..indptr[i + 1]]`, in the columns
+/// `indices[indptr[i]..indptr[i + 1]]`, sorted by column.
+#[derive(Clone, Debug)]
+pub struct CsrMatrix {
+  /// Number of rows.
+  pub nrows: usize,
+  /// Number of columns.
+  pub ncols: usize,
+  /// Offsets of the first stored value of every row, plus the total number of stored values.
+  pub indptr: Vec<usize>,
+  /// Column index of every stored value.
+  pub indices: Vec<usize>,
+  /// Stored (non-zero) values.
+  pub data: Vec<f64>,
+}
+
+impl CsrMatrix {
+  /// Creates an empty matrix (all zeros) of the given shape.
+  pub fn zeros(
+    nrows: usize,
+    ncols: usize,
+  ) -> Self {
+    Self {
+      nrows,
+      ncols,
+      indptr: vec![0; nrows + 1],
+      indices: Vec::new(),
+      data: Vec::new(),
+    }
+  }
+
+  /// Creates a sparse matrix from rows of `(column, value)` pairs. Pairs in the same row and
+  /// column are summed and zeros are not stored.
+  ///
+  /// # Arguments
+  ///
+  /// * `ncols`: Number of columns.
+  /// * `rows`: Non-zero entries of every row.
+  pub fn from_rows(
+    ncols: usize,
+    rows: Vec<Vec<(usize, f64)>>,
+  ) -> GenericResult<Self> {
+    let mut matrix = Self::zeros(0, ncols);
+    matrix.indptr = Vec::with_capacity(rows.len() + 1);
+    matrix.indptr.push(0);
+
+    for row in rows {
+      let mut entries: BTreeMap<usize, f64> = BTreeMap::new();
+      for (column, value) in row {
+        if column >= ncols {
+          return Err(
+            GenericError::from(format!(
+              "Column index {column} is out of bounds for a matrix of {ncols} columns"
+            ))
+            .into(),
+          );
+        }
+        *entries.entry(column).or_insert(0.0) += value;
+      }
+
+      for (column, value) in entries.into_iter().filter(|(_, value)| *value != 0.0) {
+        matrix.indices.push(column);
+        matrix.data.push(value);
+      }
+      matrix.indptr.push(matrix.data.len());
+      matrix.nrows += 1;
+    }
+
+    Ok(matrix)
+  }
+
+  /// Creates a sparse matrix from the non-zero values of a dense matrix.
+  pub fn from_dense(dense: &Array2<f64>) -> Self {
+    let rows = dense
+      .rows()
+      .into_iter()
+      .map(|row| {
+        row
+          .iter()
+          .enumerate()
+          .filter(|(_, value)| **value != 0.0)
+          .map(|(column, value)| (column, *value))
+          .collect()
+      })
+      .collect();
+
+    Self::from_rows(dense.ncols(), rows).expect("dense column indexes are in bounds")
+  }
+
+  /// Converts this matrix into a dense matrix.
+  pub fn to_dense(&self) -> Array2<f64> {
+    let mut dense = Array2::<f64>::zeros((self.nrows, self.ncols));
+    for row in 0..self.nrows {
+      for (column, value) in self.row(row) {
+        dense[(row, column)] = value;
+      }
+    }
+    dense
+  }
+
+  /// Number of stored (non-zero) values.
+  pub fn nnz(&self) -> usize {
+    self.data.len()
+  }
+
+  /// Fraction of the entries that are stored, from 0 (empty) to 1 (dense).
+  pub fn density(&self) -> f64 {
+    self.nnz() as f64 / (self.nrows * self.ncols).max(1) as f64
+  }
+
+  /// Iterates over the `(column, value)` pairs stored in the given row.
+  pub fn row(
+    &self,
+    row: usize,
+  ) -> impl Iterator<Item = (usize, f64)> + '_ {
+    let range = self.indptr[row]..self.indptr[row + 1];
+    self.indices[range.clone()]
+      .iter()
+      .copied()
+      .zip(self.data[range].iter().copied())
+  }
+
+  /// Gets the diagonal of the matrix.
+  pub fn diagonal(&self) -> Array1<f64> {
+    let mut diagonal = Array1::<f64>::zeros(self.nrows.min(self.ncols));
+    for row in 0..diagonal.len() {
+      if let Some((_, value)) = self.row(row).find(|(column, _)| *column == row) {
+        diagonal[row] = value;
+      }
+    }
+    diagonal
+  }
+
+  /// Builds a sparse design matrix from a dataframe: an optional intercept column, the numeric
+  /// columns as they are, and one indicator column per level of every categorical column.
+  ///
+  /// With an intercept the first level (in sorted order) of every categorical column is the
+  /// reference level and gets no column, so the matrix keeps full column rank. Null
+  /// categories get no indicator; null numeric values are an error.
+  ///
+  /// # Arguments
+  ///
+  /// * `df`: Source dataframe.
+  /// * `numeric_columns`: Names of the numeric columns.
+  /// * `categorical_columns`: Names of the categorical (string) columns to one-hot encode.
+  /// * `intercept`: Whether to add an intercept column of ones first.
+  ///
+  /// # Returns
+  ///
+  /// The design matrix and the names of its columns, e.g. `"City Name=BOSTON"`.
+  pub fn from_dataframe(
+    df: &DataFrame,
+    numeric_columns: &[&str],
+    categorical_columns: &[&str],
+    intercept: bool,
+  ) -> GenericResult<(Self, Vec<String>)> {
+    let mut column_names: Vec<String> = Vec::new();
+    let mut rows: Vec<Vec<(usize, f64)>> = vec![Vec::new(); df.height()];
+
+    if intercept {
+      column_names.push("Intercept".to_string());
+      rows.iter_mut().for_each(|row| row.push((0, 1.0)));
+    }
+
+    for name in numeric_columns {
+      let column_index = column_names.len();
+      column_names.push(name.to_string());
+
+      let values = df.column(name)?.cast(&DataType::Float64)?;
+      for (row, value) in values.f64()?.into_iter().enumerate() {
+        let value = value.ok_or_else(|| {
+          GenericError::from(format!("Column \"{name}\" has a null value at row {row}"))
+        })?;
+        rows[row].push((column_index, value));
+      }
+    }
+
+    for name in categorical_columns {
+      let values = df.column(name)?.cast(&DataType::Utf8)?;
+      let values = values.utf8()?;
+
+      let mut levels: Vec<&str> = values.into_iter().flatten().collect();
+      levels.sort_unstable();
+      levels.dedup();
+      if intercept && !levels.is_empty() {
+        levels.remove(0);
+      }
+
+      let first_column_index = column_names.len();
+      column_names.extend(levels.iter().map(|level| format!("{name}={level}")));
+
+      for (row, value) in values.into_iter().enumerate() {
+        if let Some(level_index) = value.and_then(|value| levels.binary_search(&value).ok()) {
+          rows[row].push((first_column_index + level_index, 1.0));
+        }
+      }
+    }
+
+    Ok((Self::from_rows(column_names.len(), rows)?, column_names))
+  }
+}
+
+impl DesignMatrix for CsrMatrix {
+  fn n_rows(&self) -> usize {
+    self.nrows
+  }
+
+  fn n_cols(&self) -> usize {
+    self.ncols
+  }
+
+  fn dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64> {
+    Array1::from_iter(
+      (0..self.nrows).map(|row| self.row(row).map(|(column, value)| value * v[column]).sum()),
+    )
+  }
+
+  fn t_dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64> {
+    let mut result = Array1::<f64>::zeros(self.ncols);
+    for row in 0..self.nrows {
+      for (column, value) in self.row(row) {
+        result[column] += value * v[row];
+      }
+    }
+    result
+  }
+
+  fn gram(&self) -> CsrMatrix {
+    // XᵀX = Σᵢ xᵢxᵢᵀ, adding the outer product of the stored values of every row
+    let mut entries: Vec<BTreeMap<usize, f64>> = vec![BTreeMap::new(); self.ncols];
+    for row in 0..self.nrows {
+      for (column_a, value_a) in self.row(row) {
+        for (column_b, value_b) in self.row(row) {
+          *entries[column_a].entry(column_b).or_insert(0.0) += value_a * value_b;
+        }
+      }
+    }
+
+    let rows = entries
+      .into_iter()
+      .map(|row| row.into_iter().collect())
+      .collect();
+
+    CsrMatrix::from_rows(self.ncols, rows).expect("gram column indexes are in bounds")
+  }
+}
+
+impl DesignMatrix for Array2<f64> {
+  fn n_rows(&self) -> usize {
+    self.nrows()
+  }
+
+  fn n_cols(&self) -> usize {
+    self.ncols()
+  }
+
+  fn dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64> {
+    self.dot(v)
+  }
+
+  fn t_dot_vector(
+    &self,
+    v: &Array1<f64>,
+  ) -> Array1<f64> {
+    self.t().dot(v)
+  }
+
+  fn gram(&self) -> CsrMatrix {
+    CsrMatrix::from_dense(&self.t().dot(self))
+  }
+}
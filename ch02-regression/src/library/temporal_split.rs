@@ -0,0 +1,373 @@
+use polars::export::chrono::{Duration, NaiveDate};
+use polars::prelude::*;
+
+use crate::application_error::{GenericError, GenericResult};
+use crate::regression_functions::RegressionModel;
+
+/// Name of the column holding the number of distinct dates between origin and forecast.
+pub const COL_HORIZON_STEP: &str = "Horizon Step";
+/// Name of the column holding the mean number of days between origin and forecast.
+pub const COL_HORIZON_DAYS: &str = "Mean Horizon (days)";
+/// Name of the column holding the number of forecast observations.
+pub const COL_OBSERVATIONS: &str = "Observations";
+/// Name of the column holding the mean squared error of the forecasts.
+pub const COL_MSE: &str = "MSE";
+/// Name of the column holding the root mean squared error of the forecasts.
+pub const COL_RMSE: &str = "RMSE";
+
+/// Represents how the training window moves forward in a rolling-origin evaluation.
+#[derive(Clone, Copy, Debug)]
+pub enum TrainingWindow {
+  /// The training set keeps every date before the origin.
+  Expanding,
+  /// The training set keeps only the given number of distinct dates before the origin.
+  Rolling(usize),
+}
+
+/// Represents options for a rolling-origin (time series) cross-validation.
+#[derive(Clone)]
+pub struct RollingOriginOptions {
+  /// Number of distinct dates in the first training set.
+  pub initial_dates: usize,
+  /// Number of distinct dates forecast after each origin.
+  pub horizon: usize,
+  /// Number of distinct dates the origin moves forward between folds.
+  pub step: usize,
+  /// How the training window moves forward.
+  pub window: TrainingWindow,
+}
+
+impl RollingOriginOptions {
+  /// Creates a new instance of `[RollingOriginOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these rolling-origin options.
+  pub fn builder() -> RollingOriginOptionsBuilder {
+    RollingOriginOptionsBuilder::default()
+  }
+}
+
+impl Default for RollingOriginOptions {
+  fn default() -> Self {
+    RollingOriginOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[RollingOriginOptions]`.
+pub struct RollingOriginOptionsBuilder {
+  /// Number of distinct dates in the first training set.
+  pub initial_dates: usize,
+  /// Number of distinct dates forecast after each origin.
+  pub horizon: usize,
+  /// Number of distinct dates the origin moves forward between folds.
+  pub step: usize,
+  /// How the training window moves forward.
+  pub window: TrainingWindow,
+}
+
+impl RollingOriginOptionsBuilder {
+  /// Creates a new instance of `[RollingOriginOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the number of distinct dates in the first training set.
+  pub fn initial_dates(
+    mut self,
+    initial_dates: usize,
+  ) -> Self {
+    self.initial_dates = initial_dates;
+    self
+  }
+
+  /// Sets the number of distinct dates forecast after each origin.
+  pub fn horizon(
+    mut self,
+    horizon: usize,
+  ) -> Self {
+    self.horizon = horizon;
+    self
+  }
+
+  /// Sets the number of distinct dates the origin moves forward between folds.
+  pub fn step(
+    mut self,
+    step: usize,
+  ) -> Self {
+    self.step = step;
+    self
+  }
+
+  /// Sets how the training window moves forward.
+  pub fn window(
+    mut self,
+    window: TrainingWindow,
+  ) -> Self {
+    self.window = window;
+    self
+  }
+
+  /// Builds the instance of `[RollingOriginOptions]`.
+  pub fn build(self) -> RollingOriginOptions {
+    RollingOriginOptions {
+      initial_dates: self.initial_dates,
+      horizon: self.horizon,
+      step: self.step,
+      window: self.window,
+    }
+  }
+}
+
+impl Default for RollingOriginOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      initial_dates: 6,
+      horizon: 4,
+      step: 1,
+      window: TrainingWindow::Expanding,
+    }
+  }
+}
+
+/// Represents one fold of a rolling-origin evaluation.
+#[derive(Clone)]
+pub struct TemporalFold {
+  /// Last date of the training set; forecasts are made from this date.
+  pub origin: NaiveDate,
+  /// Rows dated up to and including the origin.
+  pub train: DataFrame,
+  /// Rows dated in the `horizon` distinct dates after the origin.
+  pub test: DataFrame,
+}
+
+/// Splits a dataframe into the rows dated before a cutoff date (training set) and the rows
+/// dated on or after it (testing set), so no future observation leaks into training.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe to split.
+/// * `date_column`: Name of the column of `Date` datatype.
+/// * `cutoff`: First date of the testing set.
+pub fn temporal_split(
+  df: &DataFrame,
+  date_column: &str,
+  cutoff: NaiveDate,
+) -> GenericResult<(DataFrame, DataFrame)> {
+  let cutoff_days = date_to_days(cutoff);
+  let days = col(date_column).cast(DataType::Int32);
+
+  let train = df
+    .clone()
+    .lazy()
+    .filter(days.clone().lt(lit(cutoff_days)))
+    .collect()?;
+  let test = df
+    .clone()
+    .lazy()
+    .filter(days.gt_eq(lit(cutoff_days)))
+    .collect()?;
+
+  Ok((train, test))
+}
+
+/// Creates the folds of a rolling-origin (also known as forward-chaining) cross-validation.
+///
+/// The distinct dates of `date_column` are sorted and every fold trains on the dates up to an
+/// origin and tests on the next `horizon` dates. The origin then moves `step` dates forward.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe to split.
+/// * `date_column`: Name of the column of `Date` datatype.
+/// * `options`: Options of the rolling-origin evaluation.
+pub fn rolling_origin_folds(
+  df: &DataFrame,
+  date_column: &str,
+  options: &RollingOriginOptions,
+) -> GenericResult<Vec<TemporalFold>> {
+  if options.initial_dates == 0 || options.horizon == 0 || options.step == 0 {
+    return Err(
+      GenericError::from("The initial dates, horizon and step must be greater than 0").into(),
+    );
+  }
+
+  let dates = distinct_dates(df, date_column)?;
+  let days = col(date_column).cast(DataType::Int32);
+  let mut folds: Vec<TemporalFold> = Vec::new();
+
+  let mut origin_index = options.initial_dates - 1;
+  while origin_index + 1 < dates.len() {
+    let first_train_index = match options.window {
+      TrainingWindow::Expanding => 0,
+      TrainingWindow::Rolling(size) => (origin_index + 1).saturating_sub(size.max(1)),
+    };
+    let last_test_index = (origin_index + options.horizon).min(dates.len() - 1);
+
+    let train = df
+      .clone()
+      .lazy()
+      .filter(
+        days
+          .clone()
+          .gt_eq(lit(dates[first_train_index]))
+          .and(days.clone().lt_eq(lit(dates[origin_index]))),
+      )
+      .collect()?;
+    let test = df
+      .clone()
+      .lazy()
+      .filter(
+        days
+          .clone()
+          .gt(lit(dates[origin_index]))
+          .and(days.clone().lt_eq(lit(dates[last_test_index]))),
+      )
+      .collect()?;
+
+    folds.push(TemporalFold {
+      origin: days_to_date(dates[origin_index]),
+      train,
+      test,
+    });
+
+    origin_index += options.step;
+  }
+
+  Ok(folds)
+}
+
+/// Evaluates how the accuracy of a polynomial regression degrades with the forecast horizon.
+///
+/// A model is fitted on the training set of every rolling-origin fold and its forecasts are
+/// grouped by the number of distinct dates between the origin and the forecast date.
+///
+/// # Arguments
+///
+/// * `df`: Dataframe holding the date, feature and target columns.
+/// * `date_column`: Name of the column of `Date` datatype.
+/// * `features`: Names of the explanatory (input) columns.
+/// * `target`: Name of the response (output) column.
+/// * `degree`: Degree of the polynomial regression.
+/// * `options`: Options of the rolling-origin evaluation.
+///
+/// # Returns
+///
+/// A dataframe with one row per horizon step and the columns `[COL_HORIZON_STEP]`,
+/// `[COL_HORIZON_DAYS]`, `[COL_OBSERVATIONS]`, `[COL_MSE]` and `[COL_RMSE]`.
+pub fn forecast_horizon_errors(
+  df: &DataFrame,
+  date_column: &str,
+  features: &[&str],
+  target: &str,
+  degree: i32,
+  options: &RollingOriginOptions,
+) -> GenericResult<DataFrame> {
+  let dates = distinct_dates(df, date_column)?;
+
+  // Accumulated (squared errors, horizon days, observations) per horizon step
+  let mut sum_squared_errors = vec![0.0_f64; options.horizon];
+  let mut sum_days = vec![0_i64; options.horizon];
+  let mut observations = vec![0_u32; options.horizon];
+
+  for fold in rolling_origin_folds(df, date_column, options)? {
+    if fold.test.height() == 0 || fold.train.height() <= degree as usize {
+      continue;
+    }
+
+    let mut model = RegressionModel::new(
+      fold
+        .train
+        .select(features)?
+        .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+      fold
+        .train
+        .select([target])?
+        .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+      1.0,
+    );
+
+    if model.try_solve(degree).is_err() {
+      continue;
+    }
+
+    let x_test = RegressionModel::polyfit_data(
+      &fold
+        .test
+        .select(features)?
+        .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+      degree,
+    );
+    let y_test = fold
+      .test
+      .select([target])?
+      .to_ndarray::<Float64Type>(IndexOrder::Fortran)?;
+    let errors = model.e(&x_test, &y_test);
+
+    let origin_days = date_to_days(fold.origin);
+    let origin_index = dates.binary_search(&origin_days).unwrap_or(0);
+    let test_days = fold.test.column(date_column)?.cast(&DataType::Int32)?;
+
+    for (row_index, day) in test_days.i32()?.into_iter().enumerate() {
+      let Some(day) = day else { continue };
+      let date_index = dates.binary_search(&day).unwrap_or(origin_index);
+      let step = date_index.saturating_sub(origin_index).max(1) - 1;
+      let error = errors[(row_index, 0)];
+
+      if step < options.horizon && error.is_finite() {
+        sum_squared_errors[step] += error.powi(2);
+        sum_days[step] += (day - origin_days) as i64;
+        observations[step] += 1;
+      }
+    }
+  }
+
+  let steps: Vec<u32> = (1..=options.horizon as u32).collect();
+  let mean_days: Vec<Option<f64>> = sum_days
+    .iter()
+    .zip(observations.iter())
+    .map(|(days, n)| (*n > 0).then(|| *days as f64 / *n as f64))
+    .collect();
+  let mse: Vec<Option<f64>> = sum_squared_errors
+    .iter()
+    .zip(observations.iter())
+    .map(|(sse, n)| (*n > 0).then(|| sse / *n as f64))
+    .collect();
+  let rmse: Vec<Option<f64>> = mse.iter().map(|mse| mse.map(f64::sqrt)).collect();
+
+  Ok(DataFrame::new(vec![
+    Series::new(COL_HORIZON_STEP, steps),
+    Series::new(COL_HORIZON_DAYS, mean_days),
+    Series::new(COL_OBSERVATIONS, observations),
+    Series::new(COL_MSE, mse),
+    Series::new(COL_RMSE, rmse),
+  ])?)
+}
+
+/// Gets the sorted distinct dates of a `Date` column as days since the Unix epoch.
+fn distinct_dates(
+  df: &DataFrame,
+  date_column: &str,
+) -> GenericResult<Vec<i32>> {
+  let days = df.column(date_column)?.cast(&DataType::Int32)?;
+  let mut dates: Vec<i32> = days.i32()?.into_iter().flatten().collect();
+  dates.sort_unstable();
+  dates.dedup();
+  Ok(dates)
+}
+
+/// Converts a date into the number of days since the Unix epoch, as stored by polars.
+fn date_to_days(date: NaiveDate) -> i32 {
+  (date - unix_epoch()).num_days() as i32
+}
+
+/// Converts a number of days since the Unix epoch into a date.
+fn days_to_date(days: i32) -> NaiveDate {
+  unix_epoch() + Duration::days(days as i64)
+}
+
+/// Gets the Unix epoch date (1970-01-01).
+fn unix_epoch() -> NaiveDate {
+  NaiveDate::from_ymd_opt(1970, 1, 1).expect("valid epoch date")
+}
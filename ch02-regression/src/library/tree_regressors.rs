@@ -0,0 +1,366 @@
+use ndarray::{Array2, Axis};
+use polars::prelude::*;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use smartcore::ensemble::random_forest_regressor::{
+  RandomForestRegressor, RandomForestRegressorParameters,
+};
+use smartcore::tree::decision_tree_regressor::{
+  DecisionTreeRegressor, DecisionTreeRegressorParameters,
+};
+
+use crate::application_error::GenericResult;
+
+/// Name of the importances column holding the feature name.
+pub const COL_FEATURE: &str = "Feature";
+/// Name of the importances column holding the mean increase of the MSE.
+pub const COL_MSE_INCREASE: &str = "MSE Increase";
+/// Name of the importances column holding the importance as a fraction of the total.
+pub const COL_IMPORTANCE: &str = "Importance";
+
+/// Represents the kind of tree-based regressor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TreeModelKind {
+  /// A single CART decision tree.
+  DecisionTree,
+  /// A random forest: bootstrap-aggregated decision trees.
+  RandomForest,
+}
+
+impl TreeModelKind {
+  /// Gets the display name of the model kind.
+  pub fn name(&self) -> &'static str {
+    match self {
+      Self::DecisionTree => "Decision Tree",
+      Self::RandomForest => "Random Forest",
+    }
+  }
+}
+
+/// Represents options for the tree-based regressors.
+#[derive(Clone)]
+pub struct TreeRegressorOptions {
+  /// Maximum depth of every tree, or `None` to grow until the leaves are pure.
+  pub max_depth: Option<u16>,
+  /// Number of trees of a random forest. Ignored by a decision tree.
+  pub n_estimators: usize,
+  /// Minimum number of rows in a leaf.
+  pub min_samples_leaf: usize,
+  /// Number of features drawn at every split of a random forest, or `None` for ⌊√p⌋.
+  pub max_features: Option<usize>,
+  /// Seed for the bootstrap samples and the permutation importances.
+  pub seed: u64,
+}
+
+impl TreeRegressorOptions {
+  /// Creates a new instance of `[TreeRegressorOptions]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Gets the builder for these tree regressor options.
+  pub fn builder() -> TreeRegressorOptionsBuilder {
+    TreeRegressorOptionsBuilder::default()
+  }
+}
+
+impl Default for TreeRegressorOptions {
+  fn default() -> Self {
+    TreeRegressorOptionsBuilder::default().build()
+  }
+}
+
+/// Represents a builder for `[TreeRegressorOptions]`.
+pub struct TreeRegressorOptionsBuilder {
+  /// Maximum depth of every tree.
+  pub max_depth: Option<u16>,
+  /// Number of trees of a random forest.
+  pub n_estimators: usize,
+  /// Minimum number of rows in a leaf.
+  pub min_samples_leaf: usize,
+  /// Number of features drawn at every split of a random forest.
+  pub max_features: Option<usize>,
+  /// Seed for the bootstrap samples and the permutation importances.
+  pub seed: u64,
+}
+
+impl TreeRegressorOptionsBuilder {
+  /// Creates a new instance of `[TreeRegressorOptionsBuilder]`.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Sets the maximum depth of every tree.
+  pub fn max_depth(
+    mut self,
+    max_depth: Option<u16>,
+  ) -> Self {
+    self.max_depth = max_depth;
+    self
+  }
+
+  /// Sets the number of trees of a random forest.
+  pub fn n_estimators(
+    mut self,
+    n_estimators: usize,
+  ) -> Self {
+    self.n_estimators = n_estimators;
+    self
+  }
+
+  /// Sets the minimum number of rows in a leaf.
+  pub fn min_samples_leaf(
+    mut self,
+    min_samples_leaf: usize,
+  ) -> Self {
+    self.min_samples_leaf = min_samples_leaf;
+    self
+  }
+
+  /// Sets the number of features drawn at every split of a random forest.
+  pub fn max_features(
+    mut self,
+    max_features: Option<usize>,
+  ) -> Self {
+    self.max_features = max_features;
+    self
+  }
+
+  /// Sets the seed for the bootstrap samples and the permutation importances.
+  pub fn seed(
+    mut self,
+    seed: u64,
+  ) -> Self {
+    self.seed = seed;
+    self
+  }
+
+  /// Builds the instance of `[TreeRegressorOptions]`.
+  pub fn build(self) -> TreeRegressorOptions {
+    TreeRegressorOptions {
+      max_depth: self.max_depth,
+      n_estimators: self.n_estimators,
+      min_samples_leaf: self.min_samples_leaf,
+      max_features: self.max_features,
+      seed: self.seed,
+    }
+  }
+}
+
+impl Default for TreeRegressorOptionsBuilder {
+  fn default() -> Self {
+    Self {
+      max_depth: Some(4),
+      n_estimators: 100,
+      min_samples_leaf: 1,
+      max_features: None,
+      seed: 1,
+    }
+  }
+}
+
+/// Fitted SmartCore model behind a `[TreeRegressor]`.
+enum FittedTree {
+  DecisionTree(DecisionTreeRegressor<f64, f64, Array2<f64>, Vec<f64>>),
+  RandomForest(RandomForestRegressor<f64, f64, Array2<f64>, Vec<f64>>),
+}
+
+/// Represents a decision-tree or random-forest regressor fitted on dataframe columns.
+pub struct TreeRegressor {
+  /// Kind of the model.
+  pub kind: TreeModelKind,
+  /// Options used to fit the model.
+  pub options: TreeRegressorOptions,
+  /// Names of the explanatory (input) columns.
+  pub features: Vec<String>,
+  /// Name of the response (output) column.
+  pub target: String,
+  model: FittedTree,
+}
+
+impl TreeRegressor {
+  /// Fits a tree-based regressor on the given columns of a dataframe.
+  ///
+  /// # Arguments
+  ///
+  /// * `df`: Training dataframe.
+  /// * `features`: Names of the numeric explanatory (input) columns.
+  /// * `target`: Name of the response (output) column.
+  /// * `kind`: Kind of the model.
+  /// * `options`: Options of the model.
+  pub fn fit(
+    df: &DataFrame,
+    features: &[&str],
+    target: &str,
+    kind: TreeModelKind,
+    options: TreeRegressorOptions,
+  ) -> GenericResult<Self> {
+    let x = Self::features_array(df, features)?;
+    let y = Self::target_vec(df, target)?;
+
+    let model = match kind {
+      TreeModelKind::DecisionTree => FittedTree::DecisionTree(DecisionTreeRegressor::fit(
+        &x,
+        &y,
+        DecisionTreeRegressorParameters {
+          max_depth: options.max_depth,
+          min_samples_leaf: options.min_samples_leaf,
+          min_samples_split: 2,
+          seed: Some(options.seed),
+        },
+      )?),
+      TreeModelKind::RandomForest => FittedTree::RandomForest(RandomForestRegressor::fit(
+        &x,
+        &y,
+        RandomForestRegressorParameters {
+          max_depth: options.max_depth,
+          min_samples_leaf: options.min_samples_leaf,
+          min_samples_split: 2,
+          n_trees: options.n_estimators,
+          m: options.max_features,
+          keep_samples: false,
+          seed: options.seed,
+        },
+      )?),
+    };
+
+    Ok(Self {
+      kind,
+      options,
+      features: features.iter().map(|name| name.to_string()).collect(),
+      target: target.to_string(),
+      model,
+    })
+  }
+
+  /// Gets a display string of the hyperparameters, e.g. "max_depth = 4, n_estimators = 100".
+  pub fn parameters_to_string(&self) -> String {
+    let max_depth = self
+      .options
+      .max_depth
+      .map_or("none".to_string(), |depth| depth.to_string());
+
+    match self.kind {
+      TreeModelKind::DecisionTree => format!("max_depth = {max_depth}"),
+      TreeModelKind::RandomForest => format!(
+        "max_depth = {max_depth},\nn_estimators = {}",
+        self.options.n_estimators
+      ),
+    }
+  }
+
+  /// Predicts the response for the rows of a dataframe holding the feature columns.
+  pub fn predict(
+    &self,
+    df: &DataFrame,
+  ) -> GenericResult<Vec<f64>> {
+    let features: Vec<&str> = self.features.iter().map(|name| name.as_str()).collect();
+    self.predict_array(&Self::features_array(df, &features)?)
+  }
+
+  /// Predicts the response for a matrix with one column per feature, in the fitted order.
+  pub fn predict_array(
+    &self,
+    x: &Array2<f64>,
+  ) -> GenericResult<Vec<f64>> {
+    Ok(match &self.model {
+      FittedTree::DecisionTree(model) => model.predict(x)?,
+      FittedTree::RandomForest(model) => model.predict(x)?,
+    })
+  }
+
+  /// Calculates the permutation importance of every feature: how much the mean squared error
+  /// on the given data grows when the values of the feature are shuffled.
+  ///
+  /// # Arguments
+  ///
+  /// * `df`: Dataframe on which the importances are measured, ideally held-out data.
+  /// * `n_repeats`: Number of shuffles averaged per feature.
+  ///
+  /// # Returns
+  ///
+  /// A dataframe with the columns `[COL_FEATURE]`, `[COL_MSE_INCREASE]` and `[COL_IMPORTANCE]`,
+  /// sorted by importance (most important first).
+  pub fn feature_importances(
+    &self,
+    df: &DataFrame,
+    n_repeats: usize,
+  ) -> GenericResult<DataFrame> {
+    let features: Vec<&str> = self.features.iter().map(|name| name.as_str()).collect();
+    let x = Self::features_array(df, &features)?;
+    let y = Self::target_vec(df, &self.target)?;
+    let baseline_mse = mean_squared_error(&y, &self.predict_array(&x)?);
+
+    let mut rng = StdRng::seed_from_u64(self.options.seed);
+    let mut increases: Vec<f64> = Vec::with_capacity(features.len());
+
+    for feature_index in 0..features.len() {
+      let mut total_increase = 0.0;
+      for _ in 0..n_repeats.max(1) {
+        let mut shuffled_column = x.column(feature_index).to_vec();
+        shuffled_column.shuffle(&mut rng);
+
+        let mut x_permuted = x.clone();
+        x_permuted
+          .index_axis_mut(Axis(1), feature_index)
+          .assign(&ndarray::Array1::from_vec(shuffled_column));
+
+        total_increase +=
+          mean_squared_error(&y, &self.predict_array(&x_permuted)?) - baseline_mse;
+      }
+      increases.push(total_increase / n_repeats.max(1) as f64);
+    }
+
+    let total: f64 = increases.iter().map(|value| value.max(0.0)).sum();
+    let importances: Vec<f64> = increases
+      .iter()
+      .map(|value| if total > 0.0 { value.max(0.0) / total } else { 0.0 })
+      .collect();
+
+    Ok(
+      DataFrame::new(vec![
+        Series::new(COL_FEATURE, &features),
+        Series::new(COL_MSE_INCREASE, increases),
+        Series::new(COL_IMPORTANCE, importances),
+      ])?
+      .sort([COL_IMPORTANCE], true, true)?,
+    )
+  }
+
+  /// Extracts the feature columns of a dataframe as a matrix of 64-bit floats.
+  fn features_array(
+    df: &DataFrame,
+    features: &[&str],
+  ) -> GenericResult<Array2<f64>> {
+    Ok(
+      df.select(features)?
+        .to_ndarray::<Float64Type>(IndexOrder::Fortran)?,
+    )
+  }
+
+  /// Extracts the target column of a dataframe as a vector of 64-bit floats.
+  fn target_vec(
+    df: &DataFrame,
+    target: &str,
+  ) -> GenericResult<Vec<f64>> {
+    Ok(
+      df.select([target])?
+        .to_ndarray::<Float64Type>(IndexOrder::Fortran)?
+        .column(0)
+        .to_vec(),
+    )
+  }
+}
+
+/// Calculates the mean squared error between observed and predicted values.
+fn mean_squared_error(
+  y: &[f64],
+  predictions: &[f64],
+) -> f64 {
+  y.iter()
+    .zip(predictions.iter())
+    .map(|(y, prediction)| (y - prediction).powi(2))
+    .sum::<f64>()
+    / y.len().max(1) as f64
+}
@@ -1,6 +1,7 @@
 #![allow(non_snake_case)]
 
 use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 
 use mimalloc::MiMalloc;
 
@@ -11,6 +12,94 @@ static GLOBAL: MiMalloc = MiMalloc;
 mod lessons;
 
 use lessons::routes::lesson_routes;
+use lessons::state::AppState;
+use linear_regression::application_error::{GenericError, GenericResult};
+use linear_regression::dataset_registry::{DatasetRegistry, DatasetSource};
+use linear_regression::display_options::{parse_sample_seed, DisplayOptions};
+
+/// Usage of the command line.
+const USAGE: &str = "Usage: ch02-regression [--config <datasets.json>] [--dataset <name>=<path>]... [--upload-dir <dir>] [--sample-seed <seed>]";
+
+/// Represents the command line arguments:
+///
+/// * `--config <file>`: JSON file of datasets, see `[DatasetRegistry::from_config_file]`, and
+///   of the seed of the shuffled samples, see `[DisplayOptions::with_config_file]`.
+/// * `--dataset <name>=<path>`: Registers a dataset file; it can be repeated.
+/// * `--upload-dir <dir>`: Directory of the uploaded datasets.
+/// * `--sample-seed <seed>`: Seed of the shuffled samples.
+#[derive(Default)]
+struct Arguments<'a> {
+  config: Option<&'a str>,
+  upload_dir: Option<&'a str>,
+  datasets: Vec<(&'a str, &'a str)>,
+  sample_seed: Option<u64>,
+}
+
+impl<'a> Arguments<'a> {
+  fn parse(args: &'a [String]) -> GenericResult<Self> {
+    let value = |index: usize| {
+      args
+        .get(index + 1)
+        .map(String::as_str)
+        .ok_or_else(|| GenericError::from(format!("Missing value of {}", args[index])))
+    };
+
+    let mut arguments = Self::default();
+    let mut index = 0;
+    while index < args.len() {
+      match args[index].as_str() {
+        "--config" => arguments.config = Some(value(index)?),
+        "--dataset" => arguments.datasets.push(value(index)?.split_once('=').ok_or_else(|| {
+          GenericError::from(format!("Expected --dataset <name>=<path>, found \"{}\"", args[index + 1]))
+        })?),
+        "--upload-dir" => arguments.upload_dir = Some(value(index)?),
+        "--sample-seed" => arguments.sample_seed = Some(parse_sample_seed(value(index)?, "--sample-seed")?),
+        argument => return Err(GenericError::from(format!("Unknown argument \"{argument}\"")).into()),
+      }
+      index += 2;
+    }
+
+    Ok(arguments)
+  }
+}
+
+/// Builds the dataset registry from the command line arguments.
+fn dataset_registry(arguments: &Arguments) -> GenericResult<DatasetRegistry> {
+  let mut registry = match arguments.config {
+    Some(config) => DatasetRegistry::from_config_file(Path::new(config))?,
+    None => DatasetRegistry::with_defaults()?,
+  };
+  if let Some(upload_dir) = arguments.upload_dir {
+    registry.upload_dir = PathBuf::from(upload_dir);
+  }
+  for (name, path) in &arguments.datasets {
+    registry.register(name, Path::new(path), None, DatasetSource::CommandLine)?;
+  }
+  registry.register_uploads()?;
+
+  Ok(registry)
+}
+
+/// Builds the display options: the seed of the shuffled samples comes from `--sample-seed`,
+/// else from the `SAMPLE_SEED` environment variable, else from the `--config` file.
+fn display_options(arguments: &Arguments) -> GenericResult<DisplayOptions> {
+  let mut display_options = DisplayOptions::new();
+  if let Some(config) = arguments.config {
+    display_options = display_options.with_config_file(Path::new(config))?;
+  }
+
+  let environment = DisplayOptions::create_from_environment_variables()?;
+  display_options.column_width = environment.column_width;
+  if std::env::var_os("SAMPLE_SEED").is_some() {
+    display_options.sample_seed = environment.sample_seed;
+  }
+
+  if let Some(sample_seed) = arguments.sample_seed {
+    display_options.sample_seed = sample_seed;
+  }
+
+  Ok(display_options)
+}
 
 #[tokio::main]
 async fn main() -> Result<(), hyper::Error> {
@@ -28,7 +117,34 @@ async fn main() -> Result<(), hyper::Error> {
   // let server_socket = "127.0.0.1:3030";
   // HttpServer::new(app).bind(server_socket)?.run().await
 
-  let app = lesson_routes();
+  let args: Vec<String> = std::env::args().skip(1).collect();
+  let arguments = match Arguments::parse(&args) {
+    Ok(arguments) => arguments,
+    Err(error) => {
+      eprintln!("{error}\n{USAGE}");
+      std::process::exit(2);
+    }
+  };
+
+  // The display options, e.g. the seed of the shuffled samples, come from the command line,
+  // the environment and the configuration file
+  match display_options(&arguments) {
+    Ok(display_options) => display_options.set_process_default(),
+    Err(error) => {
+      eprintln!("{error}");
+      std::process::exit(2);
+    }
+  }
+
+  let registry = match dataset_registry(&arguments) {
+    Ok(registry) => registry,
+    Err(error) => {
+      eprintln!("{error}\n{USAGE}");
+      std::process::exit(2);
+    }
+  };
+
+  let app = lesson_routes(AppState::new(registry));
 
   let server_socket = SocketAddr::from(([127, 0, 0, 1], 3030));
 